@@ -0,0 +1,16 @@
+-- Email verification and password reset tokens
+-- Tokens now carry a purpose so a verification link can't reset a password,
+-- and are single-use.
+
+ALTER TABLE email_verification_tokens
+ADD COLUMN IF NOT EXISTS purpose VARCHAR(30) NOT NULL DEFAULT 'verify_email',
+ADD COLUMN IF NOT EXISTS used_at TIMESTAMP;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_email_verification_tokens_token ON email_verification_tokens(token);
+CREATE INDEX IF NOT EXISTS idx_email_verification_tokens_user ON email_verification_tokens(user_id, purpose);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP;
+
+-- Accounts that predate verification are treated as verified so enabling
+-- REQUIRE_EMAIL_VERIFICATION doesn't lock them out.
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
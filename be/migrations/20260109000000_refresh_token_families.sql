@@ -0,0 +1,32 @@
+-- Refresh token rotation
+-- Every login starts a session (token family). Each refresh marks the
+-- presented token used and issues a new one in the same session; presenting
+-- a used token again revokes the whole session.
+
+CREATE TABLE user_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT,
+    ip_address TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMP,
+    revoked_reason VARCHAR(30) -- logout, logout_all, revoked, token_reuse, password_reset
+);
+
+ALTER TABLE refresh_tokens
+ADD COLUMN session_id UUID REFERENCES user_sessions(id) ON DELETE CASCADE,
+ADD COLUMN used_at TIMESTAMP;
+
+-- Give each existing token its own session so nobody is logged out
+INSERT INTO user_sessions (id, user_id, created_at, last_used_at)
+SELECT id, user_id, created_at, created_at FROM refresh_tokens WHERE user_id IS NOT NULL;
+
+UPDATE refresh_tokens SET session_id = id;
+DELETE FROM refresh_tokens WHERE session_id IS NULL OR user_id IS NULL;
+ALTER TABLE refresh_tokens ALTER COLUMN session_id SET NOT NULL;
+
+CREATE INDEX idx_refresh_tokens_token ON refresh_tokens(token);
+CREATE INDEX idx_refresh_tokens_session ON refresh_tokens(session_id);
+CREATE INDEX idx_refresh_tokens_expires ON refresh_tokens(expires_at);
+CREATE INDEX idx_user_sessions_user ON user_sessions(user_id);
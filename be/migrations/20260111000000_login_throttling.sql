@@ -0,0 +1,24 @@
+-- Failed login counters and login history
+
+-- One row per throttled key: 'user:<lowercased user name>' or 'ip:<address>'
+CREATE TABLE login_throttles (
+    key VARCHAR(300) PRIMARY KEY,
+    failed_count INT NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP
+);
+
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_name VARCHAR(255) NOT NULL, -- as typed, the account may not exist
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    success BOOLEAN NOT NULL,
+    outcome VARCHAR(30) NOT NULL, -- success, mfa_required, unknown_user, invalid_password, invalid_mfa_code, email_not_verified, throttled
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_attempts_created ON login_attempts(created_at DESC);
+CREATE INDEX idx_login_attempts_user ON login_attempts(user_id, created_at DESC);
+CREATE INDEX idx_login_attempts_ip ON login_attempts(ip_address, created_at DESC);
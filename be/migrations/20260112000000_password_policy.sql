@@ -0,0 +1,18 @@
+-- Password history for the reuse rule and password age for expiry
+
+CREATE TABLE password_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_password_history_user ON password_history(user_id, created_at DESC);
+
+ALTER TABLE users ADD COLUMN password_changed_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- Existing passwords count from account creation and become the first history entry
+UPDATE users SET password_changed_at = created_at;
+
+INSERT INTO password_history (user_id, password_hash, created_at)
+SELECT id, password_hash, created_at FROM users;
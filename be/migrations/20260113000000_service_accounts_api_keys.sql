@@ -0,0 +1,29 @@
+-- Service accounts and scoped API keys
+-- Service accounts are users without a person, email or phone that can only
+-- authenticate with API keys.
+
+ALTER TABLE users
+ADD COLUMN is_service_account BOOLEAN NOT NULL DEFAULT false,
+ADD COLUMN description TEXT;
+
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+ALTER TABLE users ALTER COLUMN phone DROP NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_contact_required
+    CHECK (is_service_account OR (email IS NOT NULL AND phone IS NOT NULL));
+
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(20) NOT NULL, -- first characters of the key, to recognise it in listings
+    key_hash VARCHAR(64) NOT NULL UNIQUE, -- SHA-256 hex of the full key
+    scopes TEXT[] NOT NULL DEFAULT '{}', -- e.g. attendance:write, reports:read
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    last_used_ip TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX idx_api_keys_user ON api_keys(user_id);
@@ -0,0 +1,33 @@
+-- OpenID Connect single sign-on
+-- Users signed up through an identity provider have no phone number, so only
+-- the email is required for people.
+
+ALTER TABLE users DROP CONSTRAINT users_contact_required;
+ALTER TABLE users ADD CONSTRAINT users_contact_required
+    CHECK (is_service_account OR email IS NOT NULL);
+
+-- Links an identity provider account (by its stable subject) to a user after
+-- the first login matched them by email
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    subject TEXT NOT NULL,
+    email TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMP,
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX idx_user_identities_user ON user_identities(user_id);
+
+-- Pending authorization requests, consumed by the callback
+CREATE TABLE oidc_auth_requests (
+    state VARCHAR(64) PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    code_verifier VARCHAR(128) NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    redirect_uri TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,31 @@
+-- LDAP / Active Directory authentication and sync
+-- Users with an ldap_dn are managed by the directory: they sign in with a bind
+-- as that DN and their details are overwritten by the sync job.
+
+ALTER TABLE users
+ADD COLUMN ldap_dn TEXT UNIQUE,
+ADD COLUMN ldap_synced_at TIMESTAMP;
+
+-- Directory groups whose members get a department and/or position on their
+-- employee and intern records. Group DNs are stored lower case.
+CREATE TABLE ldap_group_mappings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    group_dn TEXT NOT NULL UNIQUE,
+    department_id UUID REFERENCES departments(id) ON DELETE CASCADE,
+    position_id UUID REFERENCES positions(id) ON DELETE CASCADE,
+    -- When a user is in several mapped groups the highest priority wins
+    priority INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT ldap_mapping_target CHECK (department_id IS NOT NULL OR position_id IS NOT NULL)
+);
+
+CREATE TABLE ldap_sync_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP,
+    created_count INT NOT NULL DEFAULT 0,
+    updated_count INT NOT NULL DEFAULT 0,
+    removed_count INT NOT NULL DEFAULT 0,
+    error_count INT NOT NULL DEFAULT 0,
+    error TEXT
+);
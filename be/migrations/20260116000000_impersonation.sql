@@ -0,0 +1,32 @@
+-- Admin impersonation ("log in as")
+-- An admin can get a short-lived access token acting as another user. The
+-- token is tied to the admin's own session and to a row here, so ending
+-- either ends the impersonation.
+
+CREATE TABLE impersonations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    admin_session_id UUID NOT NULL REFERENCES user_sessions(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP
+);
+
+CREATE INDEX idx_impersonations_admin ON impersonations(admin_id, started_at DESC);
+CREATE INDEX idx_impersonations_user ON impersonations(user_id, started_at DESC);
+
+-- Every request made with an impersonation token
+CREATE TABLE impersonation_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    impersonation_id UUID NOT NULL REFERENCES impersonations(id) ON DELETE CASCADE,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    status SMALLINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_impersonation_requests_impersonation ON impersonation_requests(impersonation_id, created_at);
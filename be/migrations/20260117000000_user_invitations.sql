@@ -0,0 +1,13 @@
+-- User invitations
+-- Instead of choosing a password for a new user, an admin can invite them:
+-- the account is created pending, without a password, and the invitee picks
+-- their username and password from a single-use link (an
+-- email_verification_tokens row with purpose 'invitation').
+
+ALTER TABLE users
+ADD COLUMN invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+ADD COLUMN invited_at TIMESTAMP,
+ADD COLUMN invitation_accepted_at TIMESTAMP;
+
+CREATE INDEX idx_users_pending_invitation ON users(invited_at)
+    WHERE invited_at IS NOT NULL AND invitation_accepted_at IS NULL;
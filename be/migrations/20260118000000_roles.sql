@@ -0,0 +1,54 @@
+-- First-class roles, per-user grants and denies
+-- Role permissions can now target a role, which is assigned to users
+-- directly, in addition to a department and/or position. Effective
+-- permissions on a navigation item are resolved as:
+--   1. the union of the role_permissions of the user's department, position
+--      and active roles,
+--   2. plus the user's own grants,
+--   3. minus the user's own denies, which always win.
+
+CREATE TABLE roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role_id)
+);
+
+CREATE INDEX idx_user_roles_role ON user_roles(role_id);
+
+ALTER TABLE role_permissions
+ADD COLUMN role_id UUID REFERENCES roles(id) ON DELETE CASCADE;
+
+-- A permission targets either a role or a department/position
+ALTER TABLE role_permissions DROP CONSTRAINT at_least_one_role;
+ALTER TABLE role_permissions ADD CONSTRAINT at_least_one_role CHECK (
+    (role_id IS NOT NULL AND department_id IS NULL AND position_id IS NULL)
+    OR (role_id IS NULL AND (department_id IS NOT NULL OR position_id IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX idx_role_permissions_role_nav ON role_permissions(role_id, navigation_item_id)
+    WHERE role_id IS NOT NULL;
+
+-- Exceptions for a single user. For a grant the flags add rights, for a
+-- deny they remove them whatever the user's roles say.
+CREATE TABLE user_permissions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    navigation_item_id UUID NOT NULL REFERENCES navigation_items(id) ON DELETE CASCADE,
+    effect VARCHAR(10) NOT NULL CHECK (effect IN ('grant', 'deny')),
+    can_create BOOLEAN NOT NULL DEFAULT false,
+    can_read BOOLEAN NOT NULL DEFAULT false,
+    can_update BOOLEAN NOT NULL DEFAULT false,
+    can_delete BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, navigation_item_id, effect)
+);
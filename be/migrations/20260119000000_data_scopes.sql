@@ -0,0 +1,58 @@
+-- Row-level data scopes
+-- A permission row now also says which records its holder can see on the
+-- resource: only their own ('own'), their reporting line through
+-- employees.manager_id ('reports'), everyone in their department(s)
+-- ('department') or everything ('all'). The widest scope among the rows
+-- granting read applies. Existing rows keep seeing everything.
+--
+-- The scopes are applied in the employee, attendance and leave queries
+-- through scoped_employee_ids() rather than as row-level security policies:
+-- the API connects as the tables' owner, which RLS policies don't apply to,
+-- and pooled connections are shared between users.
+
+ALTER TABLE role_permissions
+ADD COLUMN data_scope VARCHAR(20) NOT NULL DEFAULT 'all'
+    CHECK (data_scope IN ('own', 'reports', 'department', 'all'));
+
+-- Ignored on denies
+ALTER TABLE user_permissions
+ADD COLUMN data_scope VARCHAR(20) NOT NULL DEFAULT 'all'
+    CHECK (data_scope IN ('own', 'reports', 'department', 'all'));
+
+CREATE INDEX idx_employees_manager ON employees(manager_id);
+
+-- The employees a user can see with the given scope. Every scope includes
+-- the user's own employee record.
+CREATE FUNCTION scoped_employee_ids(p_user_id UUID, p_scope TEXT)
+RETURNS SETOF UUID
+LANGUAGE sql STABLE
+AS $$
+    WITH RECURSIVE me AS (
+        SELECT e.id FROM employees e
+        JOIN users u ON u.person_id = e.person_id
+        WHERE u.id = p_user_id
+    ),
+    my_departments AS (
+        SELECT e.department_id FROM employees e
+        JOIN users u ON u.person_id = e.person_id
+        WHERE u.id = p_user_id AND e.department_id IS NOT NULL
+        UNION
+        SELECT i.department_id FROM interns i
+        JOIN users u ON u.person_id = i.person_id
+        WHERE u.id = p_user_id AND i.department_id IS NOT NULL
+    ),
+    reporting_line AS (
+        SELECT id FROM me
+        UNION
+        SELECT e.id FROM employees e
+        JOIN reporting_line r ON e.manager_id = r.id
+    )
+    SELECT id FROM me
+    UNION
+    SELECT id FROM reporting_line WHERE p_scope = 'reports'
+    UNION
+    SELECT e.id FROM employees e
+    WHERE p_scope = 'department' AND e.department_id IN (SELECT department_id FROM my_departments)
+    UNION
+    SELECT e.id FROM employees e WHERE p_scope = 'all'
+$$;
@@ -0,0 +1,51 @@
+-- Navigation names per locale
+-- navigation_items.name stays the English name and the fallback; a row here
+-- replaces it for users whose Accept-Language prefers that locale.
+
+CREATE TABLE navigation_item_translations (
+    navigation_item_id UUID NOT NULL REFERENCES navigation_items(id) ON DELETE CASCADE,
+    locale VARCHAR(10) NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (navigation_item_id, locale)
+);
+
+-- Nepali names for the default menu
+INSERT INTO navigation_item_translations (navigation_item_id, locale, name)
+SELECT n.id, 'ne', t.name
+FROM navigation_items n
+JOIN (VALUES
+    ('/admin/dashboard', 'ड्यासबोर्ड'),
+    ('/admin/profile', 'प्रोफाइल'),
+    ('/admin/profile/information', 'जानकारी'),
+    ('/admin/profile/schedule', 'तालिका'),
+    ('/admin/settings', 'सेटिङहरू'),
+    ('/admin/settings/role', 'भूमिका'),
+    ('/admin/settings/contact', 'सम्पर्क'),
+    ('/admin/settings/product', 'उत्पादन'),
+    ('/admin/settings/calendar', 'पात्रो'),
+    ('/admin/settings/department', 'विभाग'),
+    ('/admin/settings/position', 'पद'),
+    ('/admin/settings/navigation', 'नेभिगेसन'),
+    ('/admin/settings/permissions', 'अनुमतिहरू'),
+    ('/admin/settings/user', 'प्रयोगकर्ता'),
+    ('/admin/purchase', 'खरिद'),
+    ('/admin/purchase/order', 'खरिद आदेश'),
+    ('/admin/purchase/bill', 'बिल'),
+    ('/admin/purchase/receipt', 'रसिद'),
+    ('/admin/sales', 'बिक्री'),
+    ('/admin/sales/quotation', 'दरभाउपत्र'),
+    ('/admin/sales/invoice', 'बीजक'),
+    ('/admin/sales/payment', 'भुक्तानी'),
+    ('/admin/hr', 'मानव संसाधन'),
+    ('/admin/hr/employee', 'कर्मचारी'),
+    ('/admin/hr/intern', 'प्रशिक्षार्थी'),
+    ('/admin/hr/leave', 'बिदा'),
+    ('/admin/hr/attendance', 'हाजिरी'),
+    ('/admin/report', 'प्रतिवेदन'),
+    ('/admin/report/income', 'आम्दानी'),
+    ('/admin/report/expenditure', 'खर्च'),
+    ('/admin/report/sales', 'बिक्री प्रतिवेदन')
+) AS t(path, name) ON t.path = n.path
+ON CONFLICT DO NOTHING;
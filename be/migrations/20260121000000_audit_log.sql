@@ -0,0 +1,47 @@
+-- Audit log
+-- One row per change made through the API: who made it (and the admin
+-- behind them when impersonating), what was changed, the record before and
+-- after and the fields that differ, and where the request came from.
+-- actor_id and entity_id are not foreign keys so entries outlive the users
+-- and records they mention.
+--
+-- Entries are append-only: updating, deleting or truncating the table is
+-- refused by triggers, whoever is connected.
+
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    actor_id UUID,
+    impersonator_id UUID,
+    action VARCHAR(50) NOT NULL,
+    entity_type VARCHAR(50) NOT NULL,
+    entity_id UUID,
+    before JSONB,
+    after JSONB,
+    changes JSONB,
+    ip_address TEXT,
+    user_agent TEXT,
+    request_id VARCHAR(128),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_created_at ON audit_log(created_at DESC);
+CREATE INDEX idx_audit_log_entity ON audit_log(entity_type, entity_id, created_at DESC);
+CREATE INDEX idx_audit_log_actor ON audit_log(actor_id, created_at DESC);
+CREATE INDEX idx_audit_log_request ON audit_log(request_id);
+
+CREATE FUNCTION audit_log_append_only()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$;
+
+CREATE TRIGGER audit_log_no_update_delete
+BEFORE UPDATE OR DELETE ON audit_log
+FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+CREATE TRIGGER audit_log_no_truncate
+BEFORE TRUNCATE ON audit_log
+FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
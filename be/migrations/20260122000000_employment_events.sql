@@ -0,0 +1,82 @@
+-- Employment history
+-- Every change to an employee's department, position, manager, salary or
+-- status is recorded as an event taking effect on a date. An event only
+-- holds the fields it changes (NULL = unchanged), so an employee's
+-- assignment on any date is, per field, the latest event in effect by then.
+--
+-- Events dated in the future are kept pending (applied_at IS NULL) and copied
+-- onto the employees row by the scheduler once their date comes; until then
+-- they can be cancelled.
+
+CREATE TABLE employment_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    employee_id UUID NOT NULL REFERENCES employees(id) ON DELETE CASCADE,
+    event_type VARCHAR(20) NOT NULL CHECK (event_type IN ('hire', 'change')),
+    effective_date DATE NOT NULL,
+    department_id UUID REFERENCES departments(id) ON DELETE SET NULL,
+    position_id UUID REFERENCES positions(id) ON DELETE SET NULL,
+    manager_id UUID REFERENCES employees(id) ON DELETE SET NULL,
+    salary NUMERIC(12, 2),
+    status VARCHAR(20),
+    reason TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    applied_at TIMESTAMP
+);
+
+CREATE INDEX idx_employment_events_employee ON employment_events(employee_id, effective_date, created_at);
+CREATE INDEX idx_employment_events_pending ON employment_events(effective_date) WHERE applied_at IS NULL;
+
+-- Existing employees start their history with what they have today,
+-- effective from their hire date. Anyone no longer active left on the day
+-- their record was last changed, the closest we know.
+INSERT INTO employment_events (
+    employee_id, event_type, effective_date,
+    department_id, position_id, manager_id, salary, status, reason, applied_at
+)
+SELECT id, 'hire', hire_date, department_id, position_id, manager_id, salary, 'active',
+       'Recorded when employment history was introduced', NOW()
+FROM employees;
+
+INSERT INTO employment_events (employee_id, event_type, effective_date, status, reason, applied_at)
+SELECT id, 'change', GREATEST(updated_at::date, hire_date), status,
+       'Recorded when employment history was introduced', NOW()
+FROM employees
+WHERE status IS DISTINCT FROM 'active';
+
+-- Each employee's department, position, manager, salary and status in effect
+-- on p_date, for everyone hired by then. Pending events count once their
+-- date has come, even before the scheduler has applied them.
+CREATE FUNCTION employment_as_of(p_date DATE)
+RETURNS TABLE (
+    employee_id UUID,
+    department_id UUID,
+    position_id UUID,
+    manager_id UUID,
+    salary NUMERIC(12, 2),
+    status VARCHAR(20)
+)
+LANGUAGE sql STABLE
+AS $$
+    SELECT e.id,
+        (SELECT ev.department_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.department_id IS NOT NULL
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.position_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.position_id IS NOT NULL
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.manager_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.manager_id IS NOT NULL
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.salary FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.salary IS NOT NULL
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        COALESCE(
+            (SELECT ev.status FROM employment_events ev
+             WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.status IS NOT NULL
+             ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+            'active'
+        )
+    FROM employees e
+    WHERE e.hire_date <= p_date
+$$;
@@ -0,0 +1,105 @@
+-- Onboarding and offboarding
+-- Checklist templates list the tasks to do when an employee joins or
+-- leaves, each assigned to HR, IT, the employee's manager or the employee
+-- and due some days after the hire or termination date. A template applies
+-- to every department or to one. When an employee is created, or given a
+-- termination date, the matching templates are copied into checklists of
+-- their own, so later template edits don't change work already underway.
+--
+-- Once every offboarding task is done and the termination date has come,
+-- the employee is terminated, their user account deactivated and their
+-- pending leave requests cancelled.
+
+ALTER TABLE employees
+ADD COLUMN termination_date DATE,
+ADD COLUMN termination_reason TEXT;
+
+-- Deactivated users can't sign in; their sessions and API keys are revoked
+ALTER TABLE users ADD COLUMN deactivated_at TIMESTAMP;
+
+CREATE TABLE checklist_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('onboarding', 'offboarding')),
+    name VARCHAR(255) NOT NULL,
+    -- NULL applies to every department
+    department_id UUID REFERENCES departments(id) ON DELETE CASCADE,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE checklist_template_tasks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    assignee_role VARCHAR(20) NOT NULL CHECK (assignee_role IN ('hr', 'it', 'manager', 'employee')),
+    -- Days after the hire or termination date, negative for before
+    due_offset_days INT NOT NULL DEFAULT 0,
+    display_order INT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX idx_checklist_template_tasks_template ON checklist_template_tasks(template_id, display_order);
+
+CREATE TABLE employee_checklists (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    employee_id UUID NOT NULL REFERENCES employees(id) ON DELETE CASCADE,
+    template_id UUID REFERENCES checklist_templates(id) ON DELETE SET NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('onboarding', 'offboarding')),
+    name VARCHAR(255) NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP
+);
+
+CREATE INDEX idx_employee_checklists_employee ON employee_checklists(employee_id, kind);
+
+CREATE TABLE employee_checklist_tasks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    checklist_id UUID NOT NULL REFERENCES employee_checklists(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    assignee_role VARCHAR(20) NOT NULL CHECK (assignee_role IN ('hr', 'it', 'manager', 'employee')),
+    -- The manager or employee the task fell to when the checklist was
+    -- created; NULL for HR and IT tasks, which go to the whole team
+    assignee_employee_id UUID REFERENCES employees(id) ON DELETE SET NULL,
+    due_date DATE NOT NULL,
+    display_order INT NOT NULL DEFAULT 0,
+    notes TEXT,
+    completed_at TIMESTAMP,
+    completed_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_employee_checklist_tasks_checklist ON employee_checklist_tasks(checklist_id, display_order);
+CREATE INDEX idx_employee_checklist_tasks_open ON employee_checklist_tasks(assignee_role, due_date) WHERE completed_at IS NULL;
+
+-- Default templates for every department
+WITH onboarding AS (
+    INSERT INTO checklist_templates (kind, name)
+    VALUES ('onboarding', 'Standard onboarding')
+    RETURNING id
+)
+INSERT INTO checklist_template_tasks (template_id, title, assignee_role, due_offset_days, display_order)
+SELECT onboarding.id, t.title, t.assignee_role, t.due_offset_days, t.display_order
+FROM onboarding, (VALUES
+    ('Collect signed contract and tax forms', 'hr', 0, 1),
+    ('Create user account and email', 'it', -1, 2),
+    ('Prepare laptop and equipment', 'it', -1, 3),
+    ('Plan the first week and introduce the team', 'manager', 0, 4),
+    ('Enroll in payroll and benefits', 'hr', 7, 5),
+    ('Complete the 30-day check-in', 'manager', 30, 6)
+) AS t(title, assignee_role, due_offset_days, display_order);
+
+WITH offboarding AS (
+    INSERT INTO checklist_templates (kind, name)
+    VALUES ('offboarding', 'Standard offboarding')
+    RETURNING id
+)
+INSERT INTO checklist_template_tasks (template_id, title, assignee_role, due_offset_days, display_order)
+SELECT offboarding.id, t.title, t.assignee_role, t.due_offset_days, t.display_order
+FROM offboarding, (VALUES
+    ('Hand over work and documentation', 'manager', -3, 1),
+    ('Hold the exit interview', 'hr', -1, 2),
+    ('Collect laptop, badge and equipment', 'it', 0, 3),
+    ('Settle final pay and leave balance', 'hr', 0, 4)
+) AS t(title, assignee_role, due_offset_days, display_order);
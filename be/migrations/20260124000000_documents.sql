@@ -0,0 +1,63 @@
+-- Document vault
+-- Contracts, ID copies, certificates, visas and the like, attached to an
+-- employee, an intern or a person. The files live in the storage backend;
+-- the database keeps their metadata. Uploading a new file adds a version
+-- rather than replacing the old one, so a renewed visa keeps its history.
+-- A document's expiry date is that of its current version.
+
+CREATE TABLE document_categories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code VARCHAR(50) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    -- Uploads in the category must give an expiry date
+    requires_expiry BOOLEAN NOT NULL DEFAULT FALSE,
+    -- How long before expiry the document shows in the upcoming-expiry report
+    expiry_warning_days INT NOT NULL DEFAULT 30 CHECK (expiry_warning_days >= 0),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE documents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    employee_id UUID REFERENCES employees(id) ON DELETE CASCADE,
+    intern_id UUID REFERENCES interns(id) ON DELETE CASCADE,
+    person_id UUID REFERENCES persons(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES document_categories(id),
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    current_version INT NOT NULL DEFAULT 1,
+    expires_on DATE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT documents_single_owner CHECK (num_nonnulls(employee_id, intern_id, person_id) = 1)
+);
+
+CREATE INDEX idx_documents_employee ON documents(employee_id) WHERE employee_id IS NOT NULL;
+CREATE INDEX idx_documents_intern ON documents(intern_id) WHERE intern_id IS NOT NULL;
+CREATE INDEX idx_documents_person ON documents(person_id) WHERE person_id IS NOT NULL;
+CREATE INDEX idx_documents_expires_on ON documents(expires_on) WHERE expires_on IS NOT NULL;
+
+CREATE TABLE document_versions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    document_id UUID NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    sha256 CHAR(64) NOT NULL,
+    storage_key VARCHAR(512) NOT NULL,
+    expires_on DATE,
+    note TEXT,
+    uploaded_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (document_id, version)
+);
+
+INSERT INTO document_categories (code, name, requires_expiry, expiry_warning_days) VALUES
+    ('contract', 'Employment contract', FALSE, 30),
+    ('id_copy', 'ID copy', TRUE, 60),
+    ('certificate', 'Certificate', FALSE, 30),
+    ('visa', 'Visa or work permit', TRUE, 90),
+    ('other', 'Other', FALSE, 30);
@@ -0,0 +1,7 @@
+-- Explicit identity provider linking
+-- Email matches only link accounts without a local password; everyone else
+-- links the provider account from a signed-in session. Such requests record
+-- the user so only they can complete them.
+
+ALTER TABLE oidc_auth_requests
+    ADD COLUMN link_user_id UUID REFERENCES users(id) ON DELETE CASCADE;
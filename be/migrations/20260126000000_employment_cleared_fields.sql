@@ -0,0 +1,47 @@
+-- Clearing fields in employment history
+-- A NULL field on an event means "unchanged", so removing an employee's
+-- manager (or department, position or salary) is recorded by naming the
+-- column in cleared_fields instead. The latest event that sets or clears a
+-- field decides its value.
+
+ALTER TABLE employment_events
+    ADD COLUMN cleared_fields TEXT[] NOT NULL DEFAULT '{}'
+        CHECK (cleared_fields <@ ARRAY['department_id', 'position_id', 'manager_id', 'salary']);
+
+CREATE OR REPLACE FUNCTION employment_as_of(p_date DATE)
+RETURNS TABLE (
+    employee_id UUID,
+    department_id UUID,
+    position_id UUID,
+    manager_id UUID,
+    salary NUMERIC(12, 2),
+    status VARCHAR(20)
+)
+LANGUAGE sql STABLE
+AS $$
+    SELECT e.id,
+        (SELECT ev.department_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date
+           AND (ev.department_id IS NOT NULL OR 'department_id' = ANY(ev.cleared_fields))
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.position_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date
+           AND (ev.position_id IS NOT NULL OR 'position_id' = ANY(ev.cleared_fields))
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.manager_id FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date
+           AND (ev.manager_id IS NOT NULL OR 'manager_id' = ANY(ev.cleared_fields))
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        (SELECT ev.salary FROM employment_events ev
+         WHERE ev.employee_id = e.id AND ev.effective_date <= p_date
+           AND (ev.salary IS NOT NULL OR 'salary' = ANY(ev.cleared_fields))
+         ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+        COALESCE(
+            (SELECT ev.status FROM employment_events ev
+             WHERE ev.employee_id = e.id AND ev.effective_date <= p_date AND ev.status IS NOT NULL
+             ORDER BY ev.effective_date DESC, ev.created_at DESC LIMIT 1),
+            'active'
+        )
+    FROM employees e
+    WHERE e.hire_date <= p_date
+$$;
@@ -0,0 +1,15 @@
+-- Employment history references
+-- A NULL department, position or manager on an event means "unchanged", so
+-- deleting one of them must not quietly turn the events naming it into
+-- "unchanged". They are only ever deactivated, so deletion is refused.
+
+ALTER TABLE employment_events
+    DROP CONSTRAINT employment_events_department_id_fkey,
+    ADD CONSTRAINT employment_events_department_id_fkey
+        FOREIGN KEY (department_id) REFERENCES departments(id) ON DELETE RESTRICT,
+    DROP CONSTRAINT employment_events_position_id_fkey,
+    ADD CONSTRAINT employment_events_position_id_fkey
+        FOREIGN KEY (position_id) REFERENCES positions(id) ON DELETE RESTRICT,
+    DROP CONSTRAINT employment_events_manager_id_fkey,
+    ADD CONSTRAINT employment_events_manager_id_fkey
+        FOREIGN KEY (manager_id) REFERENCES employees(id) ON DELETE RESTRICT;
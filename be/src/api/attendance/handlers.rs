@@ -1,6 +1,10 @@
 use crate::{
-    api::attendance::{dto::{CheckInRequest, CheckOutRequest, ListAttendanceQuery}, service},
+    api::{
+        attendance::{dto::{CheckInRequest, CheckOutRequest, ListAttendanceQuery}, service},
+    },
     db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
 };
 use axum::{
     extract::{Extension, Path, Query},
@@ -13,9 +17,10 @@ use serde_json::json;
 
 pub async fn check_in_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Json(payload): Json<CheckInRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    match service::check_in(&db, payload).await {
+    match service::check_in(&db, &ctx, payload).await {
         Ok(attendance) => Ok((StatusCode::CREATED, Json(json!(attendance)))),
         Err(e) => {
             eprintln!("Error checking in: {}", e);
@@ -26,10 +31,11 @@ pub async fn check_in_handler(
 
 pub async fn check_out_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(employee_id): Path<String>,
     Json(payload): Json<CheckOutRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    match service::check_out(&db, employee_id, payload).await {
+    match service::check_out(&db, &ctx, employee_id, payload).await {
         Ok(attendance) => Ok((StatusCode::OK, Json(json!(attendance)))),
         Err(e) => {
             eprintln!("Error checking out: {}", e);
@@ -40,9 +46,10 @@ pub async fn check_out_handler(
 
 pub async fn list_attendance_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Query(query): Query<ListAttendanceQuery>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let response = service::get_attendance_records(&db, query)
+    let response = service::get_attendance_records(&db, scope, query)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok((StatusCode::OK, Json(json!(response))))
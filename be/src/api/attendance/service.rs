@@ -3,7 +3,10 @@ use crate::{
         AttendanceResponse, AttendanceSummary, CheckInRequest, CheckOutRequest,
         ListAttendanceQuery, ListAttendanceResponse,
     },
+    api::audit::service::{self as audit, AuditEntry},
     db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
     models::attendance::AttendanceWithEmployee,
 };
 use anyhow::{anyhow, Result};
@@ -12,7 +15,7 @@ use sqlx::types::BigDecimal;
 use std::str::FromStr;
 use uuid::Uuid;
 
-pub async fn check_in(db: &Db, req: CheckInRequest) -> Result<AttendanceResponse> {
+pub async fn check_in(db: &Db, ctx: &AuditContext, req: CheckInRequest) -> Result<AttendanceResponse> {
     let today = Local::now().date_naive();
 
     // Lookup employee UUID from string code
@@ -37,6 +40,7 @@ pub async fn check_in(db: &Db, req: CheckInRequest) -> Result<AttendanceResponse
         return Err(anyhow!("Already checked in today"));
     }
 
+    let mut tx = db.begin().await?;
     let attendance = sqlx::query_as::<_, AttendanceWithEmployee>(
         r#"
         WITH new_attendance AS (
@@ -63,16 +67,30 @@ pub async fn check_in(db: &Db, req: CheckInRequest) -> Result<AttendanceResponse
     .bind(&req.notes)
     .bind(&req.image)
     .bind(req.method.unwrap_or_else(|| "MANUAL".to_string()))
-    .bind(req.latitude.map(|l| BigDecimal::from_str(&l.to_string()).ok()).flatten())
-    .bind(req.longitude.map(|l| BigDecimal::from_str(&l.to_string()).ok()).flatten())
+    .bind(req.latitude.and_then(|l| BigDecimal::from_str(&l.to_string()).ok()))
+    .bind(req.longitude.and_then(|l| BigDecimal::from_str(&l.to_string()).ok()))
     .bind(Local::now().naive_local())
-    .fetch_one(db)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let attendance = map_attendance_to_response(attendance);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("attendance", "check_in", Some(attendance.id)).after(&attendance),
+    )
     .await?;
+    tx.commit().await?;
 
-    Ok(map_attendance_to_response(attendance))
+    Ok(attendance)
 }
 
-pub async fn check_out(db: &Db, employee_id: String, req: CheckOutRequest) -> Result<AttendanceResponse> {
+pub async fn check_out(
+    db: &Db,
+    ctx: &AuditContext,
+    employee_id: String,
+    req: CheckOutRequest,
+) -> Result<AttendanceResponse> {
     let today = Local::now().date_naive();
 
     // Lookup employee UUID from string code
@@ -84,6 +102,7 @@ pub async fn check_out(db: &Db, employee_id: String, req: CheckOutRequest) -> Re
     .await?
     .ok_or_else(|| anyhow!("Employee not found with ID: {}", employee_id))?;
 
+    let mut tx = db.begin().await?;
     let attendance = sqlx::query_as::<_, AttendanceWithEmployee>(
         r#"
         UPDATE attendance_records ar
@@ -106,15 +125,26 @@ pub async fn check_out(db: &Db, employee_id: String, req: CheckOutRequest) -> Re
     .bind(employee_uuid)
     .bind(today)
     .bind(&req.notes)
-    .fetch_optional(db)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| anyhow!("No active check-in found for today"))?;
 
-    Ok(map_attendance_to_response(attendance))
+    let attendance = map_attendance_to_response(attendance);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("attendance", "check_out", Some(attendance.id)).after(&attendance),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(attendance)
 }
 
+/// Only returns records within `scope`.
 pub async fn get_attendance_records(
     db: &Db,
+    scope: RowScope,
     query: ListAttendanceQuery,
 ) -> Result<ListAttendanceResponse> {
     let page = query.page.unwrap_or(1).max(1);
@@ -124,6 +154,12 @@ pub async fn get_attendance_records(
     let mut where_clauses: Vec<String> = vec!["1=1".to_string()];
     let mut param_index = 1;
 
+    let scoped = scope.condition("ar.employee_id", param_index);
+    if let Some(condition) = &scoped {
+        where_clauses.push(condition.clone());
+        param_index += 1;
+    }
+
     if query.employee_id.is_some() {
         where_clauses.push(format!("ar.employee_id = ${}", param_index));
         param_index += 1;
@@ -167,6 +203,11 @@ pub async fn get_attendance_records(
     let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
     let mut select_q = sqlx::query_as::<_, AttendanceWithEmployee>(&select_query);
 
+    if scoped.is_some() {
+        count_q = count_q.bind(scope.user_id);
+        select_q = select_q.bind(scope.user_id);
+    }
+
     if let Some(emp_id) = query.employee_id {
         count_q = count_q.bind(emp_id);
         select_q = select_q.bind(emp_id);
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::audit_log::AuditLog;
+
+/// Filters for `GET /audit`. `from`/`to` take a date ("2026-01-31", the
+/// whole day) or a date and time.
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQueryDto {
+    pub actor_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub request_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPageDto {
+    pub entries: Vec<AuditLog>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
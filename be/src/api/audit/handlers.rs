@@ -0,0 +1,34 @@
+use crate::{
+    api::audit::{dto::*, service},
+    db::Db,
+    models::user::User,
+};
+use axum::{
+    extract::{Extension, Query},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+
+/// Admins only.
+pub async fn list_audit_log_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Query(query): Query<AuditLogQueryDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    if !user.is_admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": "Only admins can read the audit log"})),
+        ));
+    }
+
+    let page = service::list_audit_log(&db, query)
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing audit log: {}", e);
+            (StatusCode::BAD_REQUEST, Json(json!({"error": e.to_string()})))
+        })?;
+
+    Ok((StatusCode::OK, Json(json!(page))))
+}
@@ -0,0 +1,6 @@
+use crate::api::audit::handlers;
+use axum::{routing::get, Router};
+
+pub fn audit_routes() -> Router {
+    Router::new().route("/", get(handlers::list_audit_log_handler))
+}
@@ -0,0 +1,332 @@
+use anyhow::{Result, anyhow};
+use axum::http::StatusCode;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    api::audit::dto::{AuditLogPageDto, AuditLogQueryDto},
+    db::Db,
+    extractors::AuditContext,
+    models::audit_log::AuditLog,
+};
+
+// Fields whose values never go into the log, matched on the end of the field
+// name ignoring case and underscores, so both `new_password` and
+// `keyHash` are caught but `passwordChangedAt` isn't.
+const REDACTED_FIELDS: [&str; 5] = ["password", "hash", "secret", "token", "descriptor"];
+
+/// A change to record: what was done to which record, with the record as it
+/// was before and is after (either is left out on create and delete, and a
+/// `None` is the same as leaving it out).
+#[derive(Debug, Default)]
+pub struct AuditEntry {
+    pub action: &'static str,
+    pub entity_type: &'static str,
+    pub entity_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditEntry {
+    pub fn new(entity_type: &'static str, action: &'static str, entity_id: Option<Uuid>) -> Self {
+        AuditEntry {
+            action,
+            entity_type,
+            entity_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn before(mut self, value: &impl Serialize) -> Self {
+        self.before = serde_json::to_value(value)
+            .ok()
+            .filter(|v| !v.is_null())
+            .map(redact);
+        self
+    }
+
+    pub fn after(mut self, value: &impl Serialize) -> Self {
+        self.after = serde_json::to_value(value)
+            .ok()
+            .filter(|v| !v.is_null())
+            .map(redact);
+        self
+    }
+}
+
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let normalized = name.to_lowercase().replace('_', "");
+                    if REDACTED_FIELDS.iter().any(|f| normalized.ends_with(f)) {
+                        (name, json!("[redacted]"))
+                    } else {
+                        (name, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        other => other,
+    }
+}
+
+/// The top-level fields that differ between two versions of a record as
+/// `{"field": {"before": .., "after": ..}}`. A missing side counts as an
+/// empty record; values that aren't records are compared as a whole.
+fn changes(before: Option<&Value>, after: Option<&Value>) -> Option<Value> {
+    let empty = Map::new();
+    let (before_fields, after_fields) = match (before, after) {
+        (None, None) => return None,
+        (Some(Value::Object(b)), Some(Value::Object(a))) => (b, a),
+        (Some(Value::Object(b)), None) => (b, &empty),
+        (None, Some(Value::Object(a))) => (&empty, a),
+        (before, after) => {
+            if before == after {
+                return Some(json!({}));
+            }
+            return Some(json!({"value": {"before": before, "after": after}}));
+        }
+    };
+
+    let mut changed = Map::new();
+    for name in before_fields.keys().chain(after_fields.keys()) {
+        let old = before_fields.get(name).unwrap_or(&Value::Null);
+        let new = after_fields.get(name).unwrap_or(&Value::Null);
+        if old != new && !changed.contains_key(name) {
+            changed.insert(name.clone(), json!({"before": old, "after": new}));
+        }
+    }
+    Some(Value::Object(changed))
+}
+
+/// Appends `entry` to the audit log as part of the caller's transaction, so
+/// the change and its entry are committed together or not at all.
+pub async fn record_in(conn: &mut PgConnection, ctx: &AuditContext, entry: AuditEntry) -> Result<()> {
+    let changes = changes(entry.before.as_ref(), entry.after.as_ref());
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log
+            (actor_id, impersonator_id, action, entity_type, entity_id,
+             before, after, changes, ip_address, user_agent, request_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+    )
+    .bind(ctx.actor_id)
+    .bind(ctx.impersonator_id)
+    .bind(entry.action)
+    .bind(entry.entity_type)
+    .bind(entry.entity_id)
+    .bind(entry.before)
+    .bind(entry.after)
+    .bind(changes)
+    .bind(&ctx.client.ip_address)
+    .bind(&ctx.client.user_agent)
+    .bind(&ctx.request_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Appends `entry` to the audit log on its own, for operations that change
+/// nothing, such as downloads. Changes are recorded with `record_in` in the
+/// transaction that makes them. A failed write fails the request.
+pub async fn record(db: &Db, ctx: &AuditContext, entry: AuditEntry) -> Result<(), StatusCode> {
+    let (action, entity_type) = (entry.action, entry.entity_type);
+    let result = match db.acquire().await {
+        Ok(mut conn) => record_in(&mut conn, ctx, entry).await,
+        Err(e) => Err(e.into()),
+    };
+    result.map_err(|e| {
+        eprintln!(
+            "Error recording audit entry {} {}: {}",
+            action, entity_type, e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+// A date covers the whole day: from its first or up to its last microsecond
+fn parse_time(value: &str, end_of_day: bool) -> Result<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let start = date.and_hms_opt(0, 0, 0).unwrap();
+        return Ok(if end_of_day { start + Duration::days(1) - Duration::microseconds(1) } else { start });
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f"))
+        .map_err(|_| anyhow!("Invalid date or time: {}", value))
+}
+
+/// Newest first.
+pub async fn list_audit_log(db: &Db, query: AuditLogQueryDto) -> Result<AuditLogPageDto> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 200);
+    let offset = (page - 1) * page_size;
+
+    let from = query.from.as_deref().map(|v| parse_time(v, false)).transpose()?;
+    let to = query.to.as_deref().map(|v| parse_time(v, true)).transpose()?;
+
+    let mut conditions: Vec<String> = vec!["1=1".to_string()];
+    let mut param_index = 1;
+
+    let mut push = |condition: &str| {
+        conditions.push(condition.replace("{}", &format!("${}", param_index)));
+        param_index += 1;
+    };
+    if query.actor_id.is_some() {
+        push("actor_id = {}");
+    }
+    if query.entity_type.is_some() {
+        push("entity_type = {}");
+    }
+    if query.entity_id.is_some() {
+        push("entity_id = {}");
+    }
+    if query.action.is_some() {
+        push("action = {}");
+    }
+    if query.request_id.is_some() {
+        push("request_id = {}");
+    }
+    if from.is_some() {
+        push("created_at >= {}");
+    }
+    if to.is_some() {
+        push("created_at <= {}");
+    }
+
+    let where_clause = conditions.join(" AND ");
+    let count_query = format!("SELECT COUNT(*) FROM audit_log WHERE {}", where_clause);
+    let select_query = format!(
+        "SELECT * FROM audit_log WHERE {} ORDER BY created_at DESC, id LIMIT ${} OFFSET ${}",
+        where_clause,
+        param_index,
+        param_index + 1
+    );
+
+    let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
+    let mut select_q = sqlx::query_as::<_, AuditLog>(&select_query);
+
+    if let Some(actor_id) = query.actor_id {
+        count_q = count_q.bind(actor_id);
+        select_q = select_q.bind(actor_id);
+    }
+    if let Some(entity_type) = &query.entity_type {
+        count_q = count_q.bind(entity_type);
+        select_q = select_q.bind(entity_type);
+    }
+    if let Some(entity_id) = query.entity_id {
+        count_q = count_q.bind(entity_id);
+        select_q = select_q.bind(entity_id);
+    }
+    if let Some(action) = &query.action {
+        count_q = count_q.bind(action);
+        select_q = select_q.bind(action);
+    }
+    if let Some(request_id) = &query.request_id {
+        count_q = count_q.bind(request_id);
+        select_q = select_q.bind(request_id);
+    }
+    if let Some(from) = from {
+        count_q = count_q.bind(from);
+        select_q = select_q.bind(from);
+    }
+    if let Some(to) = to {
+        count_q = count_q.bind(to);
+        select_q = select_q.bind(to);
+    }
+
+    select_q = select_q.bind(page_size).bind(offset);
+
+    let total = count_q.fetch_one(db).await?;
+    let entries = select_q.fetch_all(db).await?;
+
+    Ok(AuditLogPageDto {
+        entries,
+        total,
+        page,
+        page_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets_at_any_depth() {
+        let redacted = redact(json!({
+            "userName": "alice",
+            "password_hash": "$argon2id$...",
+            "passwordChangedAt": "2026-01-01T00:00:00",
+            "keys": [{"keyHash": "abc", "prefix": "ubk_1234"}],
+            "mfa": {"secret": "JBSWY3DP", "enabled": true},
+            "faceDescriptor": [0.1, 0.2],
+        }));
+        assert_eq!(
+            redacted,
+            json!({
+                "userName": "alice",
+                "password_hash": "[redacted]",
+                "passwordChangedAt": "2026-01-01T00:00:00",
+                "keys": [{"keyHash": "[redacted]", "prefix": "ubk_1234"}],
+                "mfa": {"secret": "[redacted]", "enabled": true},
+                "faceDescriptor": "[redacted]",
+            })
+        );
+    }
+
+    #[test]
+    fn builder_drops_null_values() {
+        let entry = AuditEntry::new("role", "update", None)
+            .before(&None::<Value>)
+            .after(&json!({"name": "HR"}));
+        assert_eq!(entry.before, None);
+        assert_eq!(entry.after, Some(json!({"name": "HR"})));
+    }
+
+    #[test]
+    fn changes_lists_only_differing_fields() {
+        let before = json!({"name": "HR", "isActive": true, "description": null});
+        let after = json!({"name": "People", "isActive": true, "description": "Staff"});
+        assert_eq!(
+            changes(Some(&before), Some(&after)),
+            Some(json!({
+                "name": {"before": "HR", "after": "People"},
+                "description": {"before": null, "after": "Staff"},
+            }))
+        );
+    }
+
+    #[test]
+    fn changes_treats_a_missing_side_as_empty() {
+        let record = json!({"name": "HR"});
+        assert_eq!(
+            changes(None, Some(&record)),
+            Some(json!({"name": {"before": null, "after": "HR"}}))
+        );
+        assert_eq!(
+            changes(Some(&record), None),
+            Some(json!({"name": {"before": "HR", "after": null}}))
+        );
+        assert_eq!(changes(None, None), None);
+    }
+
+    #[test]
+    fn changes_compares_non_records_as_a_whole() {
+        let (before, after) = (json!([1, 2]), json!([1, 3]));
+        assert_eq!(
+            changes(Some(&before), Some(&after)),
+            Some(json!({"value": {"before": [1, 2], "after": [1, 3]}}))
+        );
+        assert_eq!(changes(Some(&before), Some(&before)), Some(json!({})));
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use rand::{Rng, distributions::Alphanumeric};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    db::Db,
+    middlewares::authorize::{Action, Resource},
+    models::{api_key::ApiKey, user::User},
+};
+
+/// Every API key starts with this, which is how `authenticate` tells them
+/// apart from JWTs in the `Authorization` header.
+pub const API_KEY_PREFIX: &str = "ubk_";
+const SECRET_LENGTH: usize = 40;
+// Characters of the key kept in plain text so admins can recognise it
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+/// Grants read access to every resource, for reporting scripts.
+pub const REPORTS_READ_SCOPE: &str = "reports:read";
+
+/// How a request was authenticated when it used an API key; inserted into
+/// the request extensions next to the service account's `User`.
+#[derive(Debug, Clone)]
+pub struct ApiKeyAuth {
+    pub key_id: uuid::Uuid,
+    pub scopes: Vec<String>,
+}
+
+/// Scopes an API key can be given: `<resource>:read`, `<resource>:write` for
+/// each protected resource, plus `reports:read`.
+pub fn known_scopes() -> Vec<String> {
+    let mut scopes: Vec<String> = Resource::ALL
+        .iter()
+        .flat_map(|r| [format!("{}:read", r.scope), format!("{}:write", r.scope)])
+        .collect();
+    scopes.push(REPORTS_READ_SCOPE.to_string());
+    scopes
+}
+
+/// Whether the scopes allow `action` on `resource`. Reads need `:read` (or
+/// `reports:read`); creates, updates and deletes need `:write`.
+pub fn allows(scopes: &[String], resource: Resource, action: Action) -> bool {
+    let needed = match action {
+        Action::Read => "read",
+        Action::Create | Action::Update | Action::Delete => "write",
+    };
+    let scope = format!("{}:{}", resource.scope, needed);
+
+    scopes
+        .iter()
+        .any(|s| *s == scope || (action == Action::Read && s == REPORTS_READ_SCOPE))
+}
+
+/// A new random key and the prefix shown in listings.
+pub fn generate_key() -> (String, String) {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+    let key = format!("{}{}", API_KEY_PREFIX, secret);
+    let prefix = key[..DISPLAY_PREFIX_LENGTH].to_string();
+    (key, prefix)
+}
+
+// Keys are long and random, so a fast hash is enough; only the hash is stored
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Looks up an active, unexpired key and its service account, recording the
+/// use. Returns `None` for anything that shouldn't be let in.
+pub async fn authenticate_key(
+    db: &Db,
+    key: &str,
+    ip_address: Option<&str>,
+) -> Result<Option<(User, ApiKey)>> {
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        SELECT * FROM api_keys
+        WHERE key_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(hash_key(key))
+    .fetch_optional(db)
+    .await?;
+
+    let Some(api_key) = api_key else {
+        return Ok(None);
+    };
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE id = $1 AND is_service_account = true AND deactivated_at IS NULL",
+    )
+    .bind(api_key.user_id)
+    .fetch_optional(db)
+    .await?;
+
+    let Some(user) = user else {
+        return Ok(None);
+    };
+
+    // Written at most once a minute so busy kiosks don't update on every call
+    sqlx::query(
+        r#"
+        UPDATE api_keys
+        SET last_used_at = NOW(), last_used_ip = $2
+        WHERE id = $1
+          AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+        "#,
+    )
+    .bind(api_key.id)
+    .bind(ip_address)
+    .execute(db)
+    .await?;
+
+    Ok(Some((user, api_key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn read_scope_only_allows_reads() {
+        let scopes = scopes(&["employees:read"]);
+        assert!(allows(&scopes, Resource::EMPLOYEES, Action::Read));
+        assert!(!allows(&scopes, Resource::EMPLOYEES, Action::Create));
+        assert!(!allows(&scopes, Resource::EMPLOYEES, Action::Update));
+        assert!(!allows(&scopes, Resource::EMPLOYEES, Action::Delete));
+        assert!(!allows(&scopes, Resource::LEAVE, Action::Read));
+    }
+
+    #[test]
+    fn write_scope_does_not_imply_read() {
+        let scopes = scopes(&["attendance:write"]);
+        assert!(allows(&scopes, Resource::ATTENDANCE, Action::Create));
+        assert!(allows(&scopes, Resource::ATTENDANCE, Action::Delete));
+        assert!(!allows(&scopes, Resource::ATTENDANCE, Action::Read));
+        assert!(!allows(&scopes, Resource::EMPLOYEES, Action::Create));
+    }
+
+    #[test]
+    fn reports_scope_reads_everything_but_writes_nothing() {
+        let scopes = scopes(&[REPORTS_READ_SCOPE]);
+        for resource in Resource::ALL {
+            assert!(allows(&scopes, resource, Action::Read));
+            assert!(!allows(&scopes, resource, Action::Update));
+        }
+    }
+
+    #[test]
+    fn no_scopes_allow_nothing() {
+        assert!(!allows(&[], Resource::USERS, Action::Read));
+    }
+
+    #[test]
+    fn generated_keys_are_recognisable_and_hash_stably() {
+        let (key, prefix) = generate_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.len(), API_KEY_PREFIX.len() + SECRET_LENGTH);
+        assert!(key.starts_with(&prefix));
+        assert_eq!(hash_key(&key), hash_key(&key));
+        assert_ne!(hash_key(&key), hash_key(&generate_key().0));
+    }
+}
@@ -1,4 +1,8 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::{impersonation::Impersonation, user::User};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,6 +35,19 @@ pub struct ForgotPasswordRequest {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RefreshRequest {
@@ -42,4 +59,182 @@ pub struct RefreshRequest {
 pub struct AuthResponse {
     pub access_token: String,
     pub refresh_token: String,
+    // The password has expired; only /auth endpoints work until it is changed
+    pub password_change_required: bool,
+}
+
+/// Returned by `/auth/login` instead of tokens when the account has MFA
+/// enabled; `mfa_token` is exchanged at `/auth/login/mfa`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    // A TOTP code or one of the recovery codes
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaStatusResponse {
+    pub enabled: bool,
+    pub pending_setup: bool,
+    pub required: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaPolicy {
+    pub required_for_delete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub alg: String,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    // True for the session the request was made with
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcProviderResponse {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcAuthorizeResponse {
+    pub authorization_url: String,
+}
+
+/// The `code` and `state` the identity provider appended to the redirect URI.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLdapGroupMappingRequest {
+    pub group_dn: String,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub priority: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartImpersonationRequest {
+    pub user_id: Uuid,
+    // Why support needs to act as the user, kept with the audit trail
+    pub reason: String,
+}
+
+/// An access token acting as the user. There is no refresh token; once it
+/// expires the admin has to start a new impersonation.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationResponse {
+    pub access_token: String,
+    pub impersonation: Impersonation,
+}
+
+/// Who is really behind the request, for the "you are impersonating" banner.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonatorResponse {
+    pub admin_id: Uuid,
+    pub admin_user_name: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub user: User,
+    pub impersonating: bool,
+    pub impersonated_by: Option<ImpersonatorResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+    pub user_name: String,
+    pub password: String,
+}
+
+/// What the invitation form shows before the invitee accepts.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationResponse {
+    pub email: Option<String>,
+    // Suggested by the admin or a placeholder
+    pub user_name: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub expires_at: NaiveDateTime,
 }
@@ -3,6 +3,25 @@ use uuid::Uuid;
 
 use crate::VERIFICATION_TOKEN_TTL_HOURS;
 
+/// What an `email_verification_tokens` row may be used for. A token is only
+/// accepted by the endpoint matching its purpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+    Invitation,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::VerifyEmail => "verify_email",
+            TokenPurpose::ResetPassword => "reset_password",
+            TokenPurpose::Invitation => "invitation",
+        }
+    }
+}
+
 pub fn generate_verification_token() -> String {
     Uuid::new_v4().to_string()
 }
@@ -11,6 +30,10 @@ pub fn token_expiry_time() -> NaiveDateTime {
     (Utc::now() + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS)).naive_utc()
 }
 
-pub fn send_email_stub(to: &str, token: &str) {
-    tracing::info!("[EMAIL MOCK] sending token {} to {}", to, token);
+/// When `REQUIRE_EMAIL_VERIFICATION` is true, accounts must verify their email
+/// address before they can log in or use their tokens.
+pub fn email_verification_required() -> bool {
+    std::env::var("REQUIRE_EMAIL_VERIFICATION")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
 }
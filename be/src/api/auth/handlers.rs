@@ -1,48 +1,400 @@
 use crate::{
     api::auth::{
         dto::{
-            AuthResponse, ChangePasswordRequest, ForgotPasswordRequest, LoginRequest,
-            RefreshRequest, RegisterRequest,
+            AcceptInvitationRequest, AuthResponse, ChangePasswordRequest, CreateLdapGroupMappingRequest, ForgotPasswordRequest,
+            ImpersonationResponse, InvitationResponse, JwkSet, LoginRequest, LoginResponse, MfaCodeRequest, MfaLoginRequest, MfaPolicy, MfaSetupResponse,
+            MfaStatusResponse, OidcAuthorizeResponse, OidcCallbackRequest, OidcProviderResponse,
+            ProfileResponse, RecoveryCodesResponse, RefreshRequest, RegisterRequest,
+            ResetPasswordRequest, SessionResponse, StartImpersonationRequest, VerifyEmailRequest,
         },
-        service,
+        jwt::{self, Claims},
+        ldap,
+        oidc::OidcError,
+        password_policy,
+        service::{self, AuthServiceError},
     },
     db::Db,
-    models::user::User,
+    extractors::ClientInfo,
+    i18n::Locale,
+    models::{
+        impersonation::{Impersonation, ImpersonationRequest},
+        ldap_group_mapping::LdapGroupMapping,
+        ldap_sync_run::LdapSyncRun,
+        user::User,
+    },
 };
 use axum::http::StatusCode;
-use axum::{Json, extract::Extension};
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use uuid::Uuid;
 use serde_json::json;
 
 pub async fn register_handler(
     Extension(db): Extension<Db>,
+    client: ClientInfo,
+    locale: Locale,
     Json(payload): Json<RegisterRequest>,
-) -> Result<(StatusCode, Json<AuthResponse>), StatusCode> {
-    let tokens = service::register(&db, payload)
+) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let tokens = service::register(&db, payload, &client)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| {
+            password_policy::error_response(&e, locale).unwrap_or_else(|| {
+                eprintln!("Error registering user: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Internal server error"})),
+                )
+            })
+        })?;
     Ok((StatusCode::CREATED, Json(tokens)))
 }
 
+// Unverified accounts get a distinct status so the client can offer to resend
+// the verification email, throttled attempts get a 429 with the wait, and a
+// reused refresh token is flagged so the client knows the session was revoked;
+// every other failure is a plain 401.
+fn login_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    match e.downcast_ref::<AuthServiceError>() {
+        Some(err @ AuthServiceError::EmailNotVerified) => (
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": err.to_string(), "code": "email_not_verified"})),
+        ),
+        Some(err @ AuthServiceError::TokenReuse) => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": err.to_string(), "code": "refresh_token_reused"})),
+        ),
+        Some(err @ AuthServiceError::TooManyAttempts { retry_after }) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({
+                "error": err.to_string(),
+                "code": "too_many_attempts",
+                "retryAfter": retry_after,
+            })),
+        ),
+        Some(err @ AuthServiceError::InvalidMfaCode) => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": err.to_string(), "code": "invalid_mfa_code"})),
+        ),
+        Some(err @ AuthServiceError::AccountDeactivated) => (
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": err.to_string(), "code": "account_deactivated"})),
+        ),
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"error": "Invalid credentials"})),
+        ),
+    }
+}
+
 pub async fn login_handler(
     Extension(db): Extension<Db>,
+    client: ClientInfo,
     Json(payload): Json<LoginRequest>,
-) -> Result<(StatusCode, Json<AuthResponse>), StatusCode> {
-    let tokens = service::login(&db, payload)
+) -> Result<(StatusCode, Json<LoginResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let response = service::login(&db, payload, &client)
+        .await
+        .map_err(login_error)?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn login_mfa_handler(
+    Extension(db): Extension<Db>,
+    client: ClientInfo,
+    Json(payload): Json<MfaLoginRequest>,
+) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let tokens = service::login_mfa(&db, payload, &client)
         .await
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .map_err(login_error)?;
     Ok((StatusCode::OK, Json(tokens)))
 }
 
+// Problems on the identity provider's side are 401s with a code the login
+// page can show; account problems reuse the login errors.
+fn oidc_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    if let Some(err) = e.downcast_ref::<OidcError>() {
+        return match err {
+            OidcError::UnknownProvider => (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": err.to_string()})),
+            ),
+            OidcError::InvalidState => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": err.to_string(), "code": "invalid_oidc_state"})),
+            ),
+            OidcError::Provider(_) => {
+                eprintln!("OIDC login failed: {}", err);
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({"error": err.to_string(), "code": "oidc_failed"})),
+                )
+            }
+        };
+    }
+    match e.downcast_ref::<AuthServiceError>() {
+        Some(err @ AuthServiceError::SsoAccountNotFound) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": err.to_string(), "code": "sso_account_not_found"})),
+            );
+        }
+        Some(err @ AuthServiceError::SsoLinkRequired) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": err.to_string(), "code": "sso_link_required"})),
+            );
+        }
+        Some(err @ AuthServiceError::SsoIdentityTaken) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({"error": err.to_string(), "code": "sso_identity_taken"})),
+            );
+        }
+        _ => {}
+    }
+    if e.downcast_ref::<AuthServiceError>().is_some() {
+        return login_error(e);
+    }
+    if e.downcast_ref::<reqwest::Error>().is_some() {
+        eprintln!("Identity provider request failed: {:#}", e);
+        return (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({"error": "Identity provider is unavailable"})),
+        );
+    }
+
+    eprintln!("Error during OIDC login: {:#}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({"error": "Internal server error"})),
+    )
+}
+
+pub async fn oidc_providers_handler() -> (StatusCode, Json<Vec<OidcProviderResponse>>) {
+    (StatusCode::OK, Json(service::oidc_providers()))
+}
+
+pub async fn oidc_authorize_handler(
+    Extension(db): Extension<Db>,
+    Path(provider): Path<String>,
+) -> Result<(StatusCode, Json<OidcAuthorizeResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let response = service::oidc_authorize(&db, &provider)
+        .await
+        .map_err(oidc_error)?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn oidc_callback_handler(
+    Extension(db): Extension<Db>,
+    client: ClientInfo,
+    Path(provider): Path<String>,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> Result<(StatusCode, Json<LoginResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let response = service::oidc_login(&db, &provider, payload, &client)
+        .await
+        .map_err(oidc_error)?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn oidc_link_authorize_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Path(provider): Path<String>,
+) -> Result<(StatusCode, Json<OidcAuthorizeResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let response = service::oidc_link_authorize(&db, user.id, &provider)
+        .await
+        .map_err(oidc_error)?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn oidc_link_callback_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Path(provider): Path<String>,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    service::link_oidc_identity(&db, user.id, &provider, payload)
+        .await
+        .map_err(oidc_error)?;
+    Ok((StatusCode::OK, Json(json!({"message": "Identity linked"}))))
+}
+
 pub async fn refresh_handler(
     Extension(db): Extension<Db>,
+    client: ClientInfo,
     Json(payload): Json<RefreshRequest>,
-) -> Result<(StatusCode, Json<AuthResponse>), StatusCode> {
-    let tokens = service::refresh(&db, payload)
+) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let tokens = service::refresh(&db, payload, &client)
         .await
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .map_err(login_error)?;
     Ok((StatusCode::OK, Json(tokens)))
 }
 
+pub async fn logout_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let Some(session_id) = claims.sid else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Token is not bound to a session"})),
+        ));
+    };
+
+    service::logout(&db, user.id, session_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!({"message": "Logged out"}))))
+}
+
+pub async fn logout_all_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let revoked = service::logout_all(&db, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Logged out of all sessions", "revoked": revoked})),
+    ))
+}
+
+pub async fn list_sessions_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<Claims>,
+) -> Result<(StatusCode, Json<Vec<SessionResponse>>), StatusCode> {
+    let sessions = service::list_sessions(&db, user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|s| SessionResponse {
+            current: claims.sid == Some(s.id),
+            id: s.id,
+            user_agent: s.user_agent,
+            ip_address: s.ip_address,
+            created_at: s.created_at,
+            last_used_at: s.last_used_at,
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(sessions)))
+}
+
+pub async fn revoke_session_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let revoked = service::revoke_session(&db, user.id, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !revoked {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok((StatusCode::OK, Json(json!({"message": "Session revoked"}))))
+}
+
+// Expected MFA failures are reported to the client, anything else is a 500
+fn mfa_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    match e.downcast_ref::<AuthServiceError>() {
+        Some(err) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": err.to_string()})),
+        ),
+        None => {
+            eprintln!("MFA error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+        }
+    }
+}
+
+pub async fn mfa_status_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<MfaStatusResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let status = service::mfa_status(&db, &user).await.map_err(mfa_error)?;
+    Ok((StatusCode::OK, Json(status)))
+}
+
+pub async fn mfa_setup_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<MfaSetupResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let setup = service::setup_mfa(&db, &user).await.map_err(mfa_error)?;
+    Ok((StatusCode::OK, Json(setup)))
+}
+
+pub async fn mfa_enable_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<(StatusCode, Json<RecoveryCodesResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let codes = service::enable_mfa(&db, user.id, payload)
+        .await
+        .map_err(mfa_error)?;
+    Ok((StatusCode::OK, Json(codes)))
+}
+
+pub async fn mfa_disable_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    service::disable_mfa(&db, &user, payload)
+        .await
+        .map_err(mfa_error)?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Two-factor authentication disabled"})),
+    ))
+}
+
+pub async fn mfa_recovery_codes_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<(StatusCode, Json<RecoveryCodesResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let codes = service::regenerate_recovery_codes(&db, user.id, payload)
+        .await
+        .map_err(mfa_error)?;
+    Ok((StatusCode::OK, Json(codes)))
+}
+
+pub async fn get_mfa_policy_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<MfaPolicy>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let policy = service::get_mfa_policy(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(policy)))
+}
+
+pub async fn update_mfa_policy_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<MfaPolicy>,
+) -> Result<(StatusCode, Json<MfaPolicy>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let policy = service::update_mfa_policy(&db, user.id, payload)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(policy)))
+}
+
 pub async fn forgot_password_handler(
     Extension(db): Extension<Db>,
     Json(payload): Json<ForgotPasswordRequest>,
@@ -56,23 +408,284 @@ pub async fn forgot_password_handler(
     ))
 }
 
+pub async fn verify_email_handler(
+    Extension(db): Extension<Db>,
+    locale: Locale,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match service::verify_email(&db, payload).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({"message": "Email verified"})))),
+        Err(e) => token_error(e, locale),
+    }
+}
+
+pub async fn reset_password_handler(
+    Extension(db): Extension<Db>,
+    locale: Locale,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match service::reset_password(&db, payload).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({"message": "Password has been reset"})))),
+        Err(e) => token_error(e, locale),
+    }
+}
+
+fn token_error(
+    e: anyhow::Error,
+    locale: Locale,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if let Some(response) = password_policy::error_response(&e, locale) {
+        return Ok(response);
+    }
+    match e.downcast_ref::<AuthServiceError>() {
+        Some(err @ AuthServiceError::InvalidToken) => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": err.to_string()})),
+        )),
+        _ => {
+            eprintln!("Error consuming email token: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Bad or used links, taken usernames and rejected passwords are the
+// invitee's to fix; anything else is a 500
+fn invitation_error(e: anyhow::Error, locale: Locale) -> (StatusCode, Json<serde_json::Value>) {
+    if let Some(response) = password_policy::error_response(&e, locale) {
+        return response;
+    }
+    match e.downcast_ref::<AuthServiceError>() {
+        Some(
+            err @ (AuthServiceError::InvalidToken
+            | AuthServiceError::UserNameRequired
+            | AuthServiceError::UserNameTaken),
+        ) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": err.to_string()})),
+        ),
+        _ => {
+            eprintln!("Error accepting invitation: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Internal server error"})),
+            )
+        }
+    }
+}
+
+pub async fn get_invitation_handler(
+    Extension(db): Extension<Db>,
+    locale: Locale,
+    Path(token): Path<String>,
+) -> Result<(StatusCode, Json<InvitationResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let invitation = service::get_invitation(&db, &token)
+        .await
+        .map_err(|e| invitation_error(e, locale))?;
+    Ok((StatusCode::OK, Json(invitation)))
+}
+
+pub async fn accept_invitation_handler(
+    Extension(db): Extension<Db>,
+    client: ClientInfo,
+    locale: Locale,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let tokens = service::accept_invitation(&db, payload, &client)
+        .await
+        .map_err(|e| invitation_error(e, locale))?;
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
 pub async fn change_password_handler(
     Extension(db): Extension<Db>,
     Extension(user): Extension<User>,
+    locale: Locale,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::change_password(&db, user.id, payload)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok((StatusCode::OK, Json(json!({"message": "Password changed"}))))
+    match service::change_password(&db, user.id, payload).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({"message": "Password changed"})))),
+        Err(e) => password_policy::error_response(&e, locale).ok_or(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 }
 
 pub async fn profile_handler(
     Extension(db): Extension<Db>,
     Extension(user): Extension<User>,
-) -> Result<(StatusCode, Json<User>), StatusCode> {
-    let profile = service::get_profile(&db, user.id)
+    impersonation: Option<Extension<Impersonation>>,
+) -> Result<(StatusCode, Json<ProfileResponse>), StatusCode> {
+    let profile = service::get_profile(&db, user.id, impersonation.as_ref().map(|i| &i.0))
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok((StatusCode::OK, Json(profile)))
 }
+
+pub async fn jwks_handler() -> (StatusCode, Json<JwkSet>) {
+    (StatusCode::OK, Json(jwt::jwks()))
+}
+
+pub async fn list_ldap_group_mappings_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<Vec<LdapGroupMapping>>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let mappings = service::list_ldap_group_mappings(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(mappings)))
+}
+
+pub async fn create_ldap_group_mapping_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Json(payload): Json<CreateLdapGroupMappingRequest>,
+) -> Result<(StatusCode, Json<LdapGroupMapping>), (StatusCode, Json<serde_json::Value>)> {
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "Forbidden"}))));
+    }
+    let mapping = service::create_ldap_group_mapping(&db, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error creating LDAP group mapping: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "Group is already mapped or the department/position is invalid"})),
+            )
+        })?;
+    Ok((StatusCode::CREATED, Json(mapping)))
+}
+
+pub async fn delete_ldap_group_mapping_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    match service::delete_ldap_group_mapping(&db, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+pub async fn run_ldap_sync_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<LdapSyncRun>), (StatusCode, Json<serde_json::Value>)> {
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "Forbidden"}))));
+    }
+    if ldap::config().is_none() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({"error": "LDAP is not configured"})),
+        ));
+    }
+    let run = service::run_ldap_sync(&db)
+        .await
+        .map_err(|e| {
+            eprintln!("LDAP sync failed: {:#}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": format!("LDAP sync failed: {}", e)})),
+            )
+        })?;
+    Ok((StatusCode::OK, Json(run)))
+}
+
+pub async fn list_ldap_sync_runs_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<Vec<LdapSyncRun>>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let runs = service::list_ldap_sync_runs(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(runs)))
+}
+
+pub async fn start_impersonation_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    claims: Option<Extension<Claims>>,
+    client: ClientInfo,
+    Json(payload): Json<StartImpersonationRequest>,
+) -> Result<(StatusCode, Json<ImpersonationResponse>), (StatusCode, Json<serde_json::Value>)> {
+    if !user.is_admin {
+        return Err((StatusCode::FORBIDDEN, Json(json!({"error": "Forbidden"}))));
+    }
+    // The impersonation lives in the admin's session, so API keys can't start one
+    let Some(session_id) = claims.and_then(|c| c.sid) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Token is not bound to a session"})),
+        ));
+    };
+
+    match service::start_impersonation(&db, &user, session_id, payload, &client).await {
+        Ok(Some(response)) => Ok((StatusCode::CREATED, Json(response))),
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json(json!({"error": "User not found"})))),
+        Err(e) => match e.downcast_ref::<AuthServiceError>() {
+            Some(err) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": err.to_string()})),
+            )),
+            None => {
+                eprintln!("Error starting impersonation: {}", e);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Internal server error"})),
+                ))
+            }
+        },
+    }
+}
+
+pub async fn end_impersonation_handler(
+    Extension(db): Extension<Db>,
+    impersonation: Option<Extension<Impersonation>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let Some(Extension(impersonation)) = impersonation else {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Not impersonating a user"})),
+        ));
+    };
+    service::end_impersonation(&db, impersonation.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!({"message": "Impersonation ended"}))))
+}
+
+pub async fn list_impersonations_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Json<Vec<Impersonation>>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let impersonations = service::list_impersonations(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(impersonations)))
+}
+
+pub async fn list_impersonation_requests_handler(
+    Extension(db): Extension<Db>,
+    Extension(user): Extension<User>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Vec<ImpersonationRequest>>), StatusCode> {
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let requests = service::list_impersonation_requests(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(requests)))
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use axum::{
+    body::Body,
+    http::{Request, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    api::auth::jwt::Claims,
+    db::Db,
+    errors::AuthError,
+    middlewares::authorize::{Action, Resource},
+    models::impersonation::Impersonation,
+};
+
+/// The impersonation an impersonation token belongs to, if it is still
+/// running: not ended, not expired and the admin is still an admin.
+pub async fn find_active(db: &Db, claims: &Claims) -> Result<Option<Impersonation>> {
+    let (Some(actor), Some(impersonation_id)) = (&claims.act, claims.jti) else {
+        return Ok(None);
+    };
+
+    let impersonation = sqlx::query_as::<_, Impersonation>(
+        r#"
+        SELECT i.* FROM impersonations i
+        JOIN users a ON a.id = i.admin_id
+        WHERE i.id = $1
+          AND i.admin_id = $2
+          AND i.user_id = $3
+          AND i.ended_at IS NULL
+          AND i.expires_at > NOW()
+          AND a.is_admin = true
+        "#,
+    )
+    .bind(impersonation_id)
+    .bind(actor.sub)
+    .bind(claims.sub)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(impersonation)
+}
+
+/// Adds a request made with an impersonation token to its audit trail.
+pub async fn record_request(
+    db: &Db,
+    impersonation_id: Uuid,
+    method: &str,
+    path: &str,
+    status: StatusCode,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO impersonation_requests (impersonation_id, method, path, status) VALUES ($1,$2,$3,$4)",
+    )
+    .bind(impersonation_id)
+    .bind(method)
+    .bind(path)
+    .bind(status.as_u16() as i16)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Whether an impersonating admin is kept from `action` on `resource`:
+/// changing users (passwords, keys, lockouts), roles and permissions has to be
+/// done as themselves.
+pub fn blocks(resource: Resource, action: Action) -> bool {
+    action != Action::Read
+        && [Resource::USERS.path, Resource::ROLES.path, Resource::PERMISSIONS.path]
+            .contains(&resource.path)
+}
+
+pub(crate) fn forbidden() -> Response<Body> {
+    AuthError {
+        message: "This action is not allowed while impersonating a user".to_string(),
+        status_code: StatusCode::FORBIDDEN,
+    }
+    .into_response()
+}
+
+/// Route layer for account security endpoints (password, MFA, sessions,
+/// directory settings) that must not be used while impersonating. Must run
+/// after `authenticate`.
+pub async fn forbid(req: Request<Body>, next: Next) -> Response<Body> {
+    if req.extensions().get::<Impersonation>().is_some() {
+        return forbidden();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WRITES: [Action; 3] = [Action::Create, Action::Update, Action::Delete];
+
+    #[test]
+    fn blocks_changes_to_access_control() {
+        for resource in [Resource::USERS, Resource::ROLES, Resource::PERMISSIONS] {
+            assert!(!blocks(resource, Action::Read));
+            for action in WRITES {
+                assert!(blocks(resource, action));
+            }
+        }
+    }
+
+    #[test]
+    fn allows_everything_else() {
+        for resource in [Resource::EMPLOYEES, Resource::LEAVE, Resource::NAVIGATION] {
+            assert!(!blocks(resource, Action::Read));
+            for action in WRITES {
+                assert!(!blocks(resource, action));
+            }
+        }
+    }
+}
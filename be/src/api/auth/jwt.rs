@@ -1,60 +1,397 @@
+use std::{env, fs, sync::OnceLock};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+};
+use rsa::{RsaPublicKey, pkcs8::DecodePublicKey, traits::PublicKeyParts};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{ACCESS_TOKEN_TTL_MINUTES, REFRESH_TOKEN_TTL_DAYS};
+use crate::{
+    ACCESS_TOKEN_TTL_MINUTES, IMPERSONATION_TTL_MINUTES, MFA_TOKEN_TTL_MINUTES,
+    REFRESH_TOKEN_TTL_DAYS,
+    api::auth::dto::{Jwk, JwkSet},
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,
     pub exp: usize,
+    // Session (refresh token family) the token belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+    // Unique token id, keeps refresh tokens issued in the same second distinct
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<Uuid>,
+    // Restricts what the token can be used for, e.g. `MFA_PENDING_SCOPE`.
+    // Scoped tokens are never accepted as access tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // The admin really making the request when the token is an impersonation
+    // token; `jti` is then the impersonation id and `sid` the admin's session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+/// The `act` (actor) claim of an impersonation token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: Uuid,
+}
+
+/// Scope of the token handed out by `/auth/login` while the second factor is
+/// still outstanding.
+pub const MFA_PENDING_SCOPE: &str = "mfa_pending";
+
+/// Scope of refresh tokens, which only `/auth/refresh` accepts.
+pub const REFRESH_SCOPE: &str = "refresh";
+
+const DEFAULT_KID: &str = "primary";
+
+// DER prefix of an Ed25519 SubjectPublicKeyInfo; the raw 32-byte key follows it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+struct VerificationKey {
+    kid: String,
+    algorithm: Algorithm,
+    key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+struct KeyStore {
+    kid: String,
+    algorithm: Algorithm,
+    signing_key: EncodingKey,
+    verification_keys: Vec<VerificationKey>,
+}
+
+static KEYS: OnceLock<KeyStore> = OnceLock::new();
+
+/// Loads the signing and verification keys from the environment.
+///
+/// - `JWT_ALGORITHM`: `HS256` (default), `RS256` or `EdDSA`
+/// - `JWT_KID`: key id written to the token header (default `primary`)
+/// - `JWT_SECRET`: shared secret when the algorithm is `HS256`
+/// - `JWT_PRIVATE_KEY` / `JWT_PUBLIC_KEY`: PEM file paths for `RS256`/`EdDSA`
+/// - `JWT_VERIFICATION_KEYS`: comma separated `kid:ALG:value` entries that are
+///   still accepted but no longer used for signing, where `value` is the secret
+///   for `HS256` or a public key PEM path otherwise
+pub fn init_keys() -> Result<()> {
+    let store = KeyStore::from_env()?;
+    let _ = KEYS.set(store);
+    Ok(())
+}
+
+fn keys() -> &'static KeyStore {
+    KEYS.get_or_init(|| KeyStore::from_env().expect("Invalid JWT key configuration"))
+}
+
+impl KeyStore {
+    fn from_env() -> Result<Self> {
+        let algorithm = parse_algorithm(
+            &env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string()),
+        )?;
+        let kid = env::var("JWT_KID").unwrap_or_else(|_| DEFAULT_KID.to_string());
+
+        let (signing_key, active) = match algorithm {
+            Algorithm::HS256 => {
+                let secret = env::var("JWT_SECRET").context("JWT_SECRET not set")?;
+                (
+                    EncodingKey::from_secret(secret.as_bytes()),
+                    load_verification_key(&kid, algorithm, &secret)?,
+                )
+            }
+            _ => {
+                let private_path =
+                    env::var("JWT_PRIVATE_KEY").context("JWT_PRIVATE_KEY not set")?;
+                let public_path = env::var("JWT_PUBLIC_KEY").context("JWT_PUBLIC_KEY not set")?;
+                let private_pem = fs::read(&private_path)
+                    .with_context(|| format!("Failed to read {}", private_path))?;
+                let signing_key = match algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem)?,
+                    _ => EncodingKey::from_ed_pem(&private_pem)?,
+                };
+                (
+                    signing_key,
+                    load_verification_key(&kid, algorithm, &public_path)?,
+                )
+            }
+        };
+
+        let mut verification_keys = vec![active];
+        if let Ok(extra) = env::var("JWT_VERIFICATION_KEYS") {
+            for entry in extra.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let mut parts = entry.splitn(3, ':');
+                let (Some(old_kid), Some(alg), Some(value)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    bail!("Invalid JWT_VERIFICATION_KEYS entry: {}", entry);
+                };
+                if verification_keys.iter().any(|k| k.kid == old_kid) {
+                    bail!("Duplicate JWT key id: {}", old_kid);
+                }
+                verification_keys.push(load_verification_key(
+                    old_kid,
+                    parse_algorithm(alg)?,
+                    value,
+                )?);
+            }
+        }
+
+        Ok(KeyStore {
+            kid,
+            algorithm,
+            signing_key,
+            verification_keys,
+        })
+    }
+}
+
+fn parse_algorithm(value: &str) -> Result<Algorithm> {
+    match value {
+        "HS256" => Ok(Algorithm::HS256),
+        "RS256" => Ok(Algorithm::RS256),
+        "EdDSA" => Ok(Algorithm::EdDSA),
+        other => Err(anyhow!("Unsupported JWT algorithm: {}", other)),
+    }
 }
 
-const SECRET: &[u8] = b"your-secret-key";
+// `value` is the shared secret for HS256 and a public key PEM path otherwise.
+fn load_verification_key(kid: &str, algorithm: Algorithm, value: &str) -> Result<VerificationKey> {
+    let (key, jwk) = match algorithm {
+        Algorithm::HS256 => (DecodingKey::from_secret(value.as_bytes()), None),
+        Algorithm::RS256 => {
+            let pem = fs::read_to_string(value).with_context(|| format!("Failed to read {}", value))?;
+            let public_key = RsaPublicKey::from_public_key_pem(&pem)
+                .map_err(|e| anyhow!("Invalid RSA public key {}: {}", value, e))?;
+            let jwk = Jwk {
+                kty: "RSA".to_string(),
+                key_use: "sig".to_string(),
+                alg: "RS256".to_string(),
+                kid: kid.to_string(),
+                n: Some(URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be())),
+                e: Some(URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be())),
+                crv: None,
+                x: None,
+            };
+            (DecodingKey::from_rsa_pem(pem.as_bytes())?, Some(jwk))
+        }
+        Algorithm::EdDSA => {
+            let pem = fs::read_to_string(value).with_context(|| format!("Failed to read {}", value))?;
+            let der = pem::parse(&pem)
+                .map_err(|e| anyhow!("Invalid Ed25519 public key {}: {}", value, e))?;
+            let raw = der
+                .contents()
+                .strip_prefix(ED25519_SPKI_PREFIX.as_slice())
+                .filter(|raw| raw.len() == 32)
+                .ok_or_else(|| anyhow!("{} is not an Ed25519 public key", value))?;
+            let jwk = Jwk {
+                kty: "OKP".to_string(),
+                key_use: "sig".to_string(),
+                alg: "EdDSA".to_string(),
+                kid: kid.to_string(),
+                n: None,
+                e: None,
+                crv: Some("Ed25519".to_string()),
+                x: Some(URL_SAFE_NO_PAD.encode(raw)),
+            };
+            (DecodingKey::from_ed_der(raw), Some(jwk))
+        }
+        other => bail!("Unsupported JWT algorithm: {:?}", other),
+    };
+
+    Ok(VerificationKey {
+        kid: kid.to_string(),
+        algorithm,
+        key,
+        jwk,
+    })
+}
+
+fn sign(claims: &Claims) -> String {
+    let store = keys();
+    let mut header = Header::new(store.algorithm);
+    header.kid = Some(store.kid.clone());
+    encode(&header, claims, &store.signing_key).unwrap()
+}
+
+pub fn generate_access_token(user_id: Uuid, session_id: Uuid) -> String {
+    sign(&access_claims(user_id, session_id))
+}
 
-pub fn generate_access_token(user_id: Uuid) -> String {
+fn access_claims(user_id: Uuid, session_id: Uuid) -> Claims {
     let expiration = Utc::now()
         .checked_add_signed(Duration::minutes(ACCESS_TOKEN_TTL_MINUTES))
         .unwrap()
         .timestamp() as usize;
 
+    Claims {
+        sub: user_id,
+        exp: expiration,
+        sid: Some(session_id),
+        jti: None,
+        scope: None,
+        act: None,
+    }
+}
+
+pub fn generate_refresh_token(user_id: Uuid, session_id: Uuid, token_id: Uuid) -> String {
+    sign(&refresh_claims(user_id, session_id, token_id))
+}
+
+fn refresh_claims(user_id: Uuid, session_id: Uuid, token_id: Uuid) -> Claims {
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .unwrap()
+        .timestamp() as usize;
+
+    Claims {
+        sub: user_id,
+        exp: expiration,
+        sid: Some(session_id),
+        jti: Some(token_id),
+        scope: Some(REFRESH_SCOPE.to_string()),
+        act: None,
+    }
+}
+
+pub fn generate_mfa_token(user_id: Uuid) -> String {
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::minutes(MFA_TOKEN_TTL_MINUTES))
+        .unwrap()
+        .timestamp() as usize;
+
     let claims = Claims {
         sub: user_id,
         exp: expiration,
+        sid: None,
+        jti: Some(Uuid::new_v4()),
+        scope: Some(MFA_PENDING_SCOPE.to_string()),
+        act: None,
     };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(SECRET),
-    )
-    .unwrap()
+    sign(&claims)
 }
 
-pub fn generate_refresh_token(user_id: Uuid) -> String {
+/// Access token for `user_id` acting on behalf of `admin_id`. It has no
+/// refresh token and dies with the admin's session.
+pub fn generate_impersonation_token(
+    user_id: Uuid,
+    admin_id: Uuid,
+    admin_session_id: Uuid,
+    impersonation_id: Uuid,
+) -> String {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .checked_add_signed(Duration::minutes(IMPERSONATION_TTL_MINUTES))
         .unwrap()
         .timestamp() as usize;
 
     let claims = Claims {
         sub: user_id,
         exp: expiration,
+        sid: Some(admin_session_id),
+        jti: Some(impersonation_id),
+        scope: None,
+        act: Some(Actor { sub: admin_id }),
     };
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(SECRET),
-    )
-    .unwrap()
+    sign(&claims)
+}
+
+/// Whether the claims can authenticate a request. Scoped tokens, such as
+/// refresh and MFA pending tokens, only work on their own endpoint.
+pub fn is_access_token(claims: &Claims) -> bool {
+    claims.scope.is_none()
 }
 
 pub fn validate_token(token: &str) -> Option<Claims> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(SECRET),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .ok()
+    let store = keys();
+    let header = decode_header(token).ok()?;
+    // Tokens without a kid can only have come from the active key
+    let kid = header.kid.as_deref().unwrap_or(&store.kid);
+    let key = store.verification_keys.iter().find(|k| k.kid == kid)?;
+    if header.alg != key.algorithm {
+        return None;
+    }
+
+    decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))
+        .map(|data| data.claims)
+        .ok()
+}
+
+/// Public keys other services can use to verify our tokens. Shared HS256
+/// secrets are never published.
+pub fn jwks() -> JwkSet {
+    JwkSet {
+        keys: keys()
+            .verification_keys
+            .iter()
+            .filter_map(|k| k.jwk.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_test_keys() {
+        let _ = KEYS.set(KeyStore {
+            kid: DEFAULT_KID.to_string(),
+            algorithm: Algorithm::HS256,
+            signing_key: EncodingKey::from_secret(b"test-secret"),
+            verification_keys: vec![
+                load_verification_key(DEFAULT_KID, Algorithm::HS256, "test-secret").unwrap(),
+            ],
+        });
+    }
+
+    #[test]
+    fn refresh_token_is_not_an_access_token() {
+        init_test_keys();
+        let token = generate_refresh_token(Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let claims = validate_token(&token).unwrap();
+        assert_eq!(claims.scope.as_deref(), Some(REFRESH_SCOPE));
+        assert!(!is_access_token(&claims));
+    }
+
+    #[test]
+    fn mfa_token_is_not_an_access_token() {
+        init_test_keys();
+        let claims = validate_token(&generate_mfa_token(Uuid::new_v4())).unwrap();
+        assert!(!is_access_token(&claims));
+    }
+
+    #[test]
+    fn access_token_round_trips() {
+        init_test_keys();
+        let (user_id, session_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let claims = validate_token(&generate_access_token(user_id, session_id)).unwrap();
+        assert!(is_access_token(&claims));
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.sid, Some(session_id));
+    }
+
+    #[test]
+    fn rejects_tampered_and_unknown_kid_tokens() {
+        init_test_keys();
+        let token = generate_access_token(Uuid::new_v4(), Uuid::new_v4());
+        let mut tampered = token.clone();
+        tampered.push('x');
+        assert!(validate_token(&tampered).is_none());
+
+        let other = encode(
+            &Header {
+                kid: Some("retired".to_string()),
+                ..Header::new(Algorithm::HS256)
+            },
+            &access_claims(Uuid::new_v4(), Uuid::new_v4()),
+            &EncodingKey::from_secret(b"test-secret"),
+        )
+        .unwrap();
+        assert!(validate_token(&other).is_none());
+    }
 }
@@ -0,0 +1,616 @@
+use std::{collections::HashSet, env, sync::OnceLock, time::Duration};
+
+use anyhow::{Context, Result};
+use ldap3::{
+    Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry,
+    adapters::{Adapter, EntriesOnly, PagedResults},
+    ldap_escape,
+};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    api::auth::{password::NO_PASSWORD, service::revoke_sessions},
+    db::Db,
+    models::{ldap_group_mapping::LdapGroupMapping, user::User},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const PAGE_SIZE: i32 = 500;
+// LDAP result code for a failed bind
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Directory settings, read once from the environment. LDAP is off unless
+/// `LDAP_URL` is set.
+///
+/// - `LDAP_URL`: e.g. `ldap://localhost:389` or `ldaps://dc.example.com`
+/// - `LDAP_STARTTLS`: upgrade plain `ldap://` connections (default false)
+/// - `LDAP_BIND_DN` / `LDAP_BIND_PASSWORD`: account used to search the directory
+/// - `LDAP_BASE_DN`: subtree the users are searched in
+/// - `LDAP_USER_FILTER`: which entries are users (default
+///   `(objectClass=inetOrgPerson)`; for AD `(&(objectClass=user)(objectCategory=person))`)
+/// - `LDAP_LOGIN_ATTRIBUTE`: attribute matched against the login username
+///   (default `uid`; for AD `sAMAccountName`)
+/// - `LDAP_EMAIL_ATTRIBUTE` / `LDAP_FIRST_NAME_ATTRIBUTE` /
+///   `LDAP_LAST_NAME_ATTRIBUTE` / `LDAP_PHONE_ATTRIBUTE`: defaults `mail`,
+///   `givenName`, `sn` and `telephoneNumber`
+/// - `LDAP_GROUP_BASE_DN`: also look up groups here by `member`,
+///   `uniqueMember` or `memberUid`, for servers without `memberOf`
+/// - `LDAP_SYNC_INTERVAL_MINUTES`: how often the sync job runs (default 60,
+///   0 disables it)
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    pub url: String,
+    pub starttls: bool,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub base_dn: String,
+    pub user_filter: String,
+    pub login_attribute: String,
+    pub email_attribute: String,
+    pub first_name_attribute: String,
+    pub last_name_attribute: String,
+    pub phone_attribute: String,
+    pub group_base_dn: Option<String>,
+    pub sync_interval_minutes: u64,
+}
+
+static CONFIG: OnceLock<Option<LdapConfig>> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum LdapError {
+    #[error("{0} matches more than one local account")]
+    AmbiguousEmail(String),
+    #[error("the local account for {0} is an admin or has its own password and is not taken over")]
+    AccountNotAdoptable(String),
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    env::var(name)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+pub fn config() -> Option<&'static LdapConfig> {
+    CONFIG
+        .get_or_init(|| {
+            let url = env::var("LDAP_URL").ok().filter(|v| !v.trim().is_empty())?;
+            Some(LdapConfig {
+                url,
+                starttls: env_or("LDAP_STARTTLS", "false") == "true",
+                bind_dn: env_or("LDAP_BIND_DN", ""),
+                bind_password: env_or("LDAP_BIND_PASSWORD", ""),
+                base_dn: env_or("LDAP_BASE_DN", ""),
+                user_filter: env_or("LDAP_USER_FILTER", "(objectClass=inetOrgPerson)"),
+                login_attribute: env_or("LDAP_LOGIN_ATTRIBUTE", "uid"),
+                email_attribute: env_or("LDAP_EMAIL_ATTRIBUTE", "mail"),
+                first_name_attribute: env_or("LDAP_FIRST_NAME_ATTRIBUTE", "givenName"),
+                last_name_attribute: env_or("LDAP_LAST_NAME_ATTRIBUTE", "sn"),
+                phone_attribute: env_or("LDAP_PHONE_ATTRIBUTE", "telephoneNumber"),
+                group_base_dn: env::var("LDAP_GROUP_BASE_DN").ok().filter(|v| !v.trim().is_empty()),
+                sync_interval_minutes: env_or("LDAP_SYNC_INTERVAL_MINUTES", "60").parse().unwrap_or(60),
+            })
+        })
+        .as_ref()
+}
+
+/// A user entry as read from the directory.
+#[derive(Debug, Clone)]
+pub struct DirectoryEntry {
+    pub dn: String,
+    pub login: String,
+    pub email: Option<String>,
+    pub first_name: String,
+    pub last_name: String,
+    pub phone: Option<String>,
+    // Lower case DNs of the groups the user is in
+    pub groups: Vec<String>,
+}
+
+impl DirectoryEntry {
+    fn from_search_entry(config: &LdapConfig, entry: SearchEntry) -> Option<Self> {
+        // Servers may return attribute names in a different case than asked
+        let values = |name: &str| -> Vec<String> {
+            entry
+                .attrs
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default()
+        };
+        let first = |name: &str| values(name).into_iter().next().filter(|v| !v.trim().is_empty());
+
+        Some(DirectoryEntry {
+            login: first(&config.login_attribute)?,
+            email: first(&config.email_attribute),
+            first_name: first(&config.first_name_attribute).unwrap_or_default(),
+            last_name: first(&config.last_name_attribute).unwrap_or_default(),
+            phone: first(&config.phone_attribute),
+            groups: values("memberOf").iter().map(|g| g.to_lowercase()).collect(),
+            dn: entry.dn,
+        })
+    }
+}
+
+fn user_attributes(config: &LdapConfig) -> Vec<String> {
+    vec![
+        config.login_attribute.clone(),
+        config.email_attribute.clone(),
+        config.first_name_attribute.clone(),
+        config.last_name_attribute.clone(),
+        config.phone_attribute.clone(),
+        "memberOf".to_string(),
+    ]
+}
+
+async fn connect(config: &LdapConfig) -> Result<Ldap> {
+    let settings = LdapConnSettings::new()
+        .set_conn_timeout(TIMEOUT)
+        .set_starttls(config.starttls);
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, &config.url)
+        .await
+        .with_context(|| format!("Failed to connect to {}", config.url))?;
+    ldap3::drive!(conn);
+    Ok(ldap)
+}
+
+// Connection bound as the search account
+async fn service_connection(config: &LdapConfig) -> Result<Ldap> {
+    let mut ldap = connect(config).await?;
+    ldap.simple_bind(&config.bind_dn, &config.bind_password)
+        .await?
+        .success()
+        .context("LDAP service bind failed")?;
+    Ok(ldap)
+}
+
+// Adds the groups found under LDAP_GROUP_BASE_DN to the entry's memberOf groups
+async fn load_groups(ldap: &mut Ldap, config: &LdapConfig, entry: &mut DirectoryEntry) -> Result<()> {
+    let Some(group_base) = &config.group_base_dn else {
+        return Ok(());
+    };
+
+    let filter = format!(
+        "(|(member={dn})(uniqueMember={dn})(memberUid={login}))",
+        dn = ldap_escape(&entry.dn),
+        login = ldap_escape(&entry.login),
+    );
+    let (results, _) = ldap
+        .search(group_base, Scope::Subtree, &filter, vec!["1.1"])
+        .await?
+        .success()?;
+
+    for result in results {
+        let group = SearchEntry::construct(result).dn.to_lowercase();
+        if !entry.groups.contains(&group) {
+            entry.groups.push(group);
+        }
+    }
+    Ok(())
+}
+
+// Matches the user entry for a login; the login is escaped so it can't add
+// filter clauses of its own
+fn login_filter(config: &LdapConfig, login: &str) -> String {
+    format!(
+        "(&{}({}={}))",
+        config.user_filter,
+        config.login_attribute,
+        ldap_escape(login)
+    )
+}
+
+/// Looks up the directory entry for a login username.
+pub async fn find_user(login: &str) -> Result<Option<DirectoryEntry>> {
+    let Some(config) = config() else {
+        return Ok(None);
+    };
+    let mut ldap = service_connection(config).await?;
+
+    let (results, _) = ldap
+        .search(&config.base_dn, Scope::Subtree, &login_filter(config, login), user_attributes(config))
+        .await?
+        .success()?;
+
+    // An ambiguous login is treated as unknown rather than picking one
+    let entry = match <[_; 1]>::try_from(results) {
+        Ok([result]) => DirectoryEntry::from_search_entry(config, SearchEntry::construct(result)),
+        Err(_) => None,
+    };
+    let entry = match entry {
+        Some(mut entry) => {
+            load_groups(&mut ldap, config, &mut entry).await?;
+            Some(entry)
+        }
+        None => None,
+    };
+
+    let _ = ldap.unbind().await;
+    Ok(entry)
+}
+
+/// Checks the password by binding as `dn`. Returns false for wrong
+/// credentials and errors only when the directory can't be reached.
+pub async fn bind(dn: &str, password: &str) -> Result<bool> {
+    let Some(config) = config() else {
+        return Ok(false);
+    };
+    // An empty password would be an unauthenticated bind, which succeeds
+    if password.is_empty() {
+        return Ok(false);
+    }
+
+    let mut ldap = connect(config).await?;
+    let result = ldap.simple_bind(dn, password).await?;
+    let _ = ldap.unbind().await;
+
+    match result.rc {
+        0 => Ok(true),
+        INVALID_CREDENTIALS => Ok(false),
+        _ => Err(result.success().unwrap_err().into()),
+    }
+}
+
+/// All user entries under `LDAP_BASE_DN`, fetched in pages.
+pub async fn search_users(config: &LdapConfig) -> Result<Vec<DirectoryEntry>> {
+    let mut ldap = service_connection(config).await?;
+
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+        Box::new(EntriesOnly::new()),
+        Box::new(PagedResults::new(PAGE_SIZE)),
+    ];
+    let mut stream = ldap
+        .streaming_search_with(
+            adapters,
+            &config.base_dn,
+            Scope::Subtree,
+            &config.user_filter,
+            user_attributes(config),
+        )
+        .await?;
+
+    let mut entries = Vec::new();
+    while let Some(result) = stream.next().await? {
+        if let Some(entry) = DirectoryEntry::from_search_entry(config, SearchEntry::construct(result)) {
+            entries.push(entry);
+        }
+    }
+    stream.finish().await.success()?;
+
+    for entry in entries.iter_mut() {
+        load_groups(&mut ldap, config, entry).await?;
+    }
+
+    let _ = ldap.unbind().await;
+    Ok(entries)
+}
+
+// A free username for a new directory user, normally the login itself
+async fn unique_user_name(conn: &mut PgConnection, login: &str) -> Result<String> {
+    for n in 1..=100 {
+        let candidate = if n == 1 { login.to_string() } else { format!("{}{}", login, n) };
+        let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_name = $1)")
+            .bind(&candidate)
+            .fetch_one(&mut *conn)
+            .await?;
+        if !taken {
+            return Ok(candidate);
+        }
+    }
+    Ok(format!("{}-{}", login, &Uuid::new_v4().simple().to_string()[..8]))
+}
+
+// Gives the person's employee and intern records the department and position
+// of their highest priority mapped groups
+async fn apply_group_mappings(conn: &mut PgConnection, person_id: Uuid, groups: &[String]) -> Result<()> {
+    if groups.is_empty() {
+        return Ok(());
+    }
+
+    let mappings = sqlx::query_as::<_, LdapGroupMapping>(
+        "SELECT * FROM ldap_group_mappings WHERE group_dn = ANY($1) ORDER BY priority DESC, created_at",
+    )
+    .bind(groups)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let department_id = mappings.iter().find_map(|m| m.department_id);
+    let position_id = mappings.iter().find_map(|m| m.position_id);
+    if department_id.is_none() && position_id.is_none() {
+        return Ok(());
+    }
+
+    for table in ["employees", "interns"] {
+        sqlx::query(&format!(
+            r#"
+            UPDATE {}
+            SET department_id = COALESCE($2, department_id),
+                position_id = COALESCE($3, position_id)
+            WHERE person_id = $1
+            "#,
+            table
+        ))
+        .bind(person_id)
+        .bind(department_id)
+        .bind(position_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// The local user a directory entry should be adopted into, matched by email.
+// Only a single non-admin account without a local password is taken over;
+// anything else would let whoever controls the directory entry sign in as
+// that account.
+async fn adoptable_user(conn: &mut PgConnection, email: &str) -> Result<Option<User>> {
+    let mut users = sqlx::query_as::<_, User>(
+        r#"
+        SELECT * FROM users
+        WHERE ldap_dn IS NULL AND NOT is_service_account AND LOWER(email) = LOWER($1)
+        LIMIT 2
+        "#,
+    )
+    .bind(email)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    match users.len() {
+        0 => Ok(None),
+        1 if !users[0].is_admin && users[0].password_hash == NO_PASSWORD => Ok(users.pop()),
+        1 => Err(LdapError::AccountNotAdoptable(email.to_string()).into()),
+        _ => Err(LdapError::AmbiguousEmail(email.to_string()).into()),
+    }
+}
+
+/// Creates or updates the person, contact and user for a directory entry.
+/// An existing local user with the same email is adopted if it's a plain
+/// account without a password; other matches are errors. Returns the user
+/// and whether it was created.
+pub async fn upsert_user(conn: &mut PgConnection, entry: &DirectoryEntry) -> Result<(User, bool)> {
+    let linked = sqlx::query_as::<_, User>("SELECT * FROM users WHERE ldap_dn = $1")
+        .bind(&entry.dn)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let existing = match (linked, &entry.email) {
+        (Some(user), _) => Some(user),
+        (None, Some(email)) => adoptable_user(conn, email).await?,
+        (None, None) => None,
+    };
+
+    let (person_id, created) = match &existing {
+        Some(user) if user.person_id.is_some() => (user.person_id.unwrap(), false),
+        _ => {
+            let person_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO persons (id, first_name, middle_name, last_name) VALUES ($1,$2,'',$3)",
+            )
+            .bind(person_id)
+            .bind(&entry.first_name)
+            .bind(&entry.last_name)
+            .execute(&mut *conn)
+            .await?;
+            (person_id, existing.is_none())
+        }
+    };
+
+    sqlx::query("UPDATE persons SET first_name = $2, last_name = $3 WHERE id = $1")
+        .bind(person_id)
+        .bind(&entry.first_name)
+        .bind(&entry.last_name)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some(email) = &entry.email {
+        sqlx::query(
+            r#"
+            INSERT INTO person_contacts (id, person_id, email, phone)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (email) DO UPDATE
+            SET phone = COALESCE(EXCLUDED.phone, person_contacts.phone)
+            WHERE person_contacts.person_id = EXCLUDED.person_id
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(person_id)
+        .bind(email)
+        .bind(&entry.phone)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let user = match existing {
+        Some(user) => {
+            sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users
+                SET ldap_dn = $2,
+                    email = COALESCE($3, email),
+                    phone = COALESCE($4, phone),
+                    person_id = $5,
+                    email_verified_at = COALESCE(email_verified_at, NOW()),
+                    ldap_synced_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#,
+            )
+            .bind(user.id)
+            .bind(&entry.dn)
+            .bind(&entry.email)
+            .bind(&entry.phone)
+            .bind(person_id)
+            .fetch_one(&mut *conn)
+            .await?
+        }
+        None => {
+            let user_name = unique_user_name(conn, &entry.login).await?;
+            sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (id, user_name, email, phone, password_hash, person_id, email_verified_at, ldap_dn, ldap_synced_at)
+                VALUES ($1, $2, $3, $4, $5, $6, NOW(), $7, NOW())
+                RETURNING *
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_name)
+            .bind(&entry.email)
+            .bind(&entry.phone)
+            .bind(NO_PASSWORD)
+            .bind(person_id)
+            .bind(&entry.dn)
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    apply_group_mappings(conn, person_id, &entry.groups).await?;
+    Ok((user, created))
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SyncReport {
+    created: i32,
+    updated: i32,
+    // Users no longer in the directory whose sessions were revoked
+    removed: i32,
+    // Entries that couldn't be saved
+    errors: i32,
+}
+
+/// Brings all directory users into the database and records the run in
+/// `ldap_sync_runs`. Users that have left the directory can no longer bind,
+/// and their sessions are revoked.
+pub async fn sync(db: &Db) -> Result<Uuid> {
+    let config = config().context("LDAP is not configured")?;
+    let run_id = sqlx::query_scalar::<_, Uuid>("INSERT INTO ldap_sync_runs DEFAULT VALUES RETURNING id")
+        .fetch_one(db)
+        .await?;
+
+    let result = sync_entries(db, config).await;
+    let (report, error) = match &result {
+        Ok(report) => (*report, None),
+        Err(e) => (SyncReport::default(), Some(format!("{:#}", e))),
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE ldap_sync_runs
+        SET finished_at = NOW(), created_count = $2, updated_count = $3,
+            removed_count = $4, error_count = $5, error = $6
+        WHERE id = $1
+        "#,
+    )
+    .bind(run_id)
+    .bind(report.created)
+    .bind(report.updated)
+    .bind(report.removed)
+    .bind(report.errors)
+    .bind(&error)
+    .execute(db)
+    .await?;
+
+    result.map(|_| run_id)
+}
+
+async fn sync_entries(db: &Db, config: &LdapConfig) -> Result<SyncReport> {
+    let entries = search_users(config).await?;
+    let mut report = SyncReport::default();
+    let mut seen = HashSet::new();
+
+    for entry in &entries {
+        seen.insert(entry.dn.to_lowercase());
+
+        // One bad entry (e.g. a duplicate email) shouldn't stop the others
+        let mut tx = db.begin().await?;
+        match upsert_user(&mut tx, entry).await {
+            Ok((_, created)) => {
+                tx.commit().await?;
+                if created {
+                    report.created += 1;
+                } else {
+                    report.updated += 1;
+                }
+            }
+            Err(e) => {
+                tracing::error!("LDAP sync failed for {}: {:#}", entry.dn, e);
+                report.errors += 1;
+            }
+        }
+    }
+
+    let managed = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, ldap_dn FROM users WHERE ldap_dn IS NOT NULL",
+    )
+    .fetch_all(db)
+    .await?;
+    for (user_id, dn) in managed {
+        if !seen.contains(&dn.to_lowercase())
+            && revoke_sessions(db, user_id, None, "ldap_removed").await? > 0
+        {
+            report.removed += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+pub fn spawn_sync_worker(db: Db) -> Option<tokio::task::JoinHandle<()>> {
+    let minutes = config()?.sync_interval_minutes;
+    if minutes == 0 {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = sync(&db).await {
+                tracing::error!("LDAP sync error: {:#}", e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> LdapConfig {
+        LdapConfig {
+            url: "ldap://localhost".to_string(),
+            starttls: false,
+            bind_dn: String::new(),
+            bind_password: String::new(),
+            base_dn: "dc=example,dc=com".to_string(),
+            user_filter: "(objectClass=inetOrgPerson)".to_string(),
+            login_attribute: "uid".to_string(),
+            email_attribute: "mail".to_string(),
+            first_name_attribute: "givenName".to_string(),
+            last_name_attribute: "sn".to_string(),
+            phone_attribute: "telephoneNumber".to_string(),
+            group_base_dn: None,
+            sync_interval_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn login_filter_matches_the_login_attribute() {
+        assert_eq!(
+            login_filter(&test_config(), "jdoe"),
+            "(&(objectClass=inetOrgPerson)(uid=jdoe))"
+        );
+    }
+
+    #[test]
+    fn login_filter_escapes_filter_syntax() {
+        assert_eq!(
+            login_filter(&test_config(), "*)(uid=admin"),
+            r"(&(objectClass=inetOrgPerson)(uid=\2a\29\28uid=admin))"
+        );
+        assert_eq!(
+            login_filter(&test_config(), "a\\b\0"),
+            r"(&(objectClass=inetOrgPerson)(uid=a\5cb\00))"
+        );
+    }
+}
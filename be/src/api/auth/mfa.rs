@@ -0,0 +1,198 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use rand::{Rng, distributions::Alphanumeric};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::{api::navigation::service::get_effective_permissions, db::Db, models::user::User};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+// Codes from one step either side are accepted to allow for clock drift
+const TOTP_SKEW_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// `security_settings` key for the policy requiring MFA from anyone who can
+/// delete records.
+pub const MFA_REQUIRED_FOR_DELETE: &str = "mfa_required_for_delete";
+
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn totp(secret: &str, account: &str) -> Result<TOTP> {
+    let issuer = std::env::var("MFA_ISSUER").unwrap_or_else(|_| "Ubuck ERP".to_string());
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| anyhow!("Invalid TOTP secret: {:?}", e))?;
+
+    // ':' separates issuer and account in the otpauth label
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECONDS,
+        bytes,
+        Some(issuer.replace(':', " ")),
+        account.replace(':', "_"),
+    )
+    .map_err(|e| anyhow!("Invalid TOTP parameters: {}", e))
+}
+
+/// `otpauth://` URI for authenticator apps, usually shown as a QR code.
+pub fn otpauth_uri(secret: &str, account: &str) -> Result<String> {
+    Ok(totp(secret, account)?.get_url())
+}
+
+/// Checks a code against the secret and returns the time step it matched.
+/// Steps at or before `last_used_step` are rejected so a code can't be replayed.
+pub fn verify_code(secret: &str, code: &str, last_used_step: Option<i64>) -> Result<Option<i64>> {
+    if !is_totp_code(code) {
+        return Ok(None);
+    }
+    let code = code.trim();
+
+    let totp = totp(secret, "")?;
+    let current = Utc::now().timestamp() / TOTP_STEP_SECONDS as i64;
+
+    for step in (current - TOTP_SKEW_STEPS)..=(current + TOTP_SKEW_STEPS) {
+        if last_used_step.is_some_and(|last| step <= last) {
+            continue;
+        }
+        let expected = totp.generate(step as u64 * TOTP_STEP_SECONDS);
+        if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn is_totp_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == TOTP_DIGITS && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Fresh one-time recovery codes in `xxxxx-xxxxx` form. Only their hashes are
+/// stored, so they must be shown to the user straight away.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw: String = (&mut rng)
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| (c as char).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect()
+}
+
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Sha256::digest(normalized.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub async fn is_enabled(db: &Db, user_id: Uuid) -> Result<bool> {
+    let enabled = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM user_mfa WHERE user_id = $1 AND enabled_at IS NOT NULL)",
+    )
+    .bind(user_id)
+    .fetch_one(db)
+    .await?;
+    Ok(enabled)
+}
+
+pub async fn required_for_delete(db: &Db) -> Result<bool> {
+    let value = sqlx::query_scalar::<_, String>("SELECT value FROM security_settings WHERE key = $1")
+        .bind(MFA_REQUIRED_FOR_DELETE)
+        .fetch_optional(db)
+        .await?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// Whether policy requires this user to use MFA: the policy is on and they are
+/// an admin or hold delete rights on any navigation item.
+pub async fn is_required(db: &Db, user: &User) -> Result<bool> {
+    if !required_for_delete(db).await? {
+        return Ok(false);
+    }
+    if user.is_admin {
+        return Ok(true);
+    }
+
+    let permissions = get_effective_permissions(db, user.id, None).await?;
+    Ok(permissions.iter().any(|p| p.can_delete.unwrap_or(false)))
+}
+
+/// True when policy requires MFA for the user but they haven't enrolled yet.
+pub async fn enrollment_required(db: &Db, user: &User) -> Result<bool> {
+    Ok(is_required(db, user).await? && !is_enabled(db, user.id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_at(secret: &str, step: i64) -> String {
+        totp(secret, "").unwrap().generate(step as u64 * TOTP_STEP_SECONDS)
+    }
+
+    fn current_step() -> i64 {
+        Utc::now().timestamp() / TOTP_STEP_SECONDS as i64
+    }
+
+    #[test]
+    fn accepts_current_code_and_returns_its_step() {
+        let secret = generate_secret();
+        let step = current_step();
+        assert_eq!(verify_code(&secret, &code_at(&secret, step), None).unwrap(), Some(step));
+    }
+
+    #[test]
+    fn rejects_replayed_codes() {
+        let secret = generate_secret();
+        let step = current_step();
+        let code = code_at(&secret, step);
+        assert_eq!(verify_code(&secret, &code, Some(step)).unwrap(), None);
+        assert_eq!(verify_code(&secret, &code, Some(step + 1)).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_codes_outside_the_skew_window() {
+        let secret = generate_secret();
+        let far = code_at(&secret, current_step() - 5);
+        // A stale code could still collide with a valid one by chance
+        if far != code_at(&secret, current_step()) {
+            assert_eq!(verify_code(&secret, &far, None).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let secret = generate_secret();
+        assert_eq!(verify_code(&secret, "12345", None).unwrap(), None);
+        assert_eq!(verify_code(&secret, "12a456", None).unwrap(), None);
+        assert!(is_totp_code(" 123456 "));
+        assert!(!is_totp_code("1234567"));
+    }
+
+    #[test]
+    fn recovery_code_hash_ignores_case_and_separators() {
+        assert_eq!(hash_recovery_code("ABCDE-fghij"), hash_recovery_code("abcdefghij"));
+        assert_ne!(hash_recovery_code("abcde-fghij"), hash_recovery_code("abcde-fghik"));
+        assert_eq!(generate_recovery_codes().len(), RECOVERY_CODE_COUNT);
+    }
+}
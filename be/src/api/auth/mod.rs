@@ -1,7 +1,15 @@
+pub mod api_key;
 pub mod dto;
 pub mod email;
 pub mod handlers;
+pub mod impersonation;
 pub mod jwt;
+pub mod ldap;
+pub mod mfa;
+pub mod oidc;
 pub mod password;
+pub mod password_policy;
+pub mod purge;
 pub mod routes;
 mod service;
+pub mod throttle;
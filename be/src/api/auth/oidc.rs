@@ -0,0 +1,488 @@
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::Jwk};
+use rand::{Rng, distributions::Alphanumeric};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{db::Db, mail::templates::app_base_url};
+
+// How long the user has to finish signing in at the identity provider
+const AUTH_REQUEST_TTL_MINUTES: i64 = 10;
+// Discovery documents and signing keys are refetched after this long, or
+// sooner when a token is signed with a key we haven't seen
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+// An unknown key id only triggers a refetch when the cached keys are at least
+// this old, so tokens with made-up key ids can't hammer the provider
+const KEY_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Asymmetric algorithms only; HS256 would mean trusting the client secret as
+// a signing key
+const ALLOWED_ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+/// An OpenID Connect identity provider, read once from the environment.
+///
+/// `OIDC_PROVIDERS` lists the provider names (e.g. `corp,google`), each
+/// configured with `OIDC_<NAME>_...` variables:
+/// - `ISSUER`: issuer URL, used for discovery
+/// - `CLIENT_ID` / `CLIENT_SECRET`: client credentials; leave the secret out
+///   for public clients, PKCE protects the flow either way
+/// - `REDIRECT_URI`: frontend page the provider sends the browser back to
+///   (default `{APP_BASE_URL}/auth/oidc/<name>/callback`), for both sign-in
+///   and linking
+/// - `SCOPES`: requested scopes (default `openid email profile`)
+/// - `AUTO_PROVISION`: create a person and user the first time an unknown
+///   email signs in (default false)
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub auto_provision: bool,
+}
+
+static PROVIDERS: OnceLock<Vec<OidcProvider>> = OnceLock::new();
+
+fn load_providers() -> Vec<OidcProvider> {
+    let names = env::var("OIDC_PROVIDERS").unwrap_or_default();
+    names
+        .split(',')
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .filter_map(|name| {
+            let var = |key: &str| {
+                env::var(format!("OIDC_{}_{}", name.to_uppercase(), key))
+                    .ok()
+                    .filter(|v| !v.trim().is_empty())
+            };
+            let (Some(issuer), Some(client_id)) = (var("ISSUER"), var("CLIENT_ID")) else {
+                eprintln!("OIDC provider {} is missing its issuer or client id, skipping", name);
+                return None;
+            };
+            Some(OidcProvider {
+                redirect_uri: var("REDIRECT_URI").unwrap_or_else(|| {
+                    format!("{}/auth/oidc/{}/callback", app_base_url(), name)
+                }),
+                scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+                auto_provision: var("AUTO_PROVISION").is_some_and(|v| v == "true"),
+                client_secret: var("CLIENT_SECRET"),
+                issuer: issuer.trim_end_matches('/').to_string(),
+                client_id,
+                name,
+            })
+        })
+        .collect()
+}
+
+pub fn providers() -> &'static [OidcProvider] {
+    PROVIDERS.get_or_init(load_providers)
+}
+
+pub fn provider(name: &str) -> Result<&'static OidcProvider> {
+    providers()
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| OidcError::UnknownProvider.into())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("Unknown identity provider")]
+    UnknownProvider,
+    #[error("Sign-in request is invalid or has expired")]
+    InvalidState,
+    #[error("Identity provider sign-in failed: {0}")]
+    Provider(String),
+}
+
+/// The parts of the discovery document the flow needs.
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    #[serde(default)]
+    token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+struct CachedProvider {
+    metadata: ProviderMetadata,
+    keys: Vec<Jwk>,
+    fetched_at: Instant,
+}
+
+static CACHE: OnceLock<RwLock<HashMap<String, Arc<CachedProvider>>>> = OnceLock::new();
+// Held while fetching so concurrent logins share one refetch
+static FETCH_LOCK: Mutex<()> = Mutex::const_new(());
+static HTTP: OnceLock<reqwest::Client> = OnceLock::new();
+
+fn http() -> &'static reqwest::Client {
+    HTTP.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client")
+    })
+}
+
+fn cache() -> &'static RwLock<HashMap<String, Arc<CachedProvider>>> {
+    CACHE.get_or_init(Default::default)
+}
+
+// A panic elsewhere can't leave the map half-written, so a poisoned lock is
+// still safe to use
+fn cached(provider: &OidcProvider) -> Option<Arc<CachedProvider>> {
+    cache()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&provider.name)
+        .cloned()
+}
+
+async fn fetch_keys(jwks_uri: &str) -> Result<Vec<Jwk>> {
+    #[derive(Deserialize)]
+    struct RawJwkSet {
+        keys: Vec<serde_json::Value>,
+    }
+
+    let set: RawJwkSet = http()
+        .get(jwks_uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Invalid JWKS at {}", jwks_uri))?;
+
+    // Key types we can't use (e.g. encryption keys) are skipped rather than
+    // failing the whole set
+    Ok(set
+        .keys
+        .into_iter()
+        .filter_map(|k| serde_json::from_value(k).ok())
+        .collect())
+}
+
+async fn fetch_provider(provider: &OidcProvider) -> Result<Arc<CachedProvider>> {
+    let url = format!("{}/.well-known/openid-configuration", provider.issuer);
+    let metadata: ProviderMetadata = http()
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Invalid discovery document at {}", url))?;
+
+    if metadata.issuer.trim_end_matches('/') != provider.issuer {
+        return Err(OidcError::Provider(format!(
+            "discovery issuer {} does not match {}",
+            metadata.issuer, provider.issuer
+        ))
+        .into());
+    }
+
+    let keys = fetch_keys(&metadata.jwks_uri).await?;
+    let cached = Arc::new(CachedProvider {
+        metadata,
+        keys,
+        fetched_at: Instant::now(),
+    });
+    cache()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(provider.name.clone(), cached.clone());
+    Ok(cached)
+}
+
+// The cached metadata if it's younger than `max_age`, otherwise a fresh copy.
+// Fetches are serialized and the cache is checked again once it's our turn,
+// so a burst of requests only goes to the provider once.
+async fn metadata_newer_than(provider: &OidcProvider, max_age: Duration) -> Result<Arc<CachedProvider>> {
+    let fresh = |c: &Arc<CachedProvider>| c.fetched_at.elapsed() < max_age;
+    if let Some(cached) = cached(provider).filter(fresh) {
+        return Ok(cached);
+    }
+
+    let _guard = FETCH_LOCK.lock().await;
+    match cached(provider).filter(fresh) {
+        Some(cached) => Ok(cached),
+        None => fetch_provider(provider).await,
+    }
+}
+
+async fn metadata(provider: &OidcProvider) -> Result<Arc<CachedProvider>> {
+    metadata_newer_than(provider, METADATA_TTL).await
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Starts an authorization-code + PKCE sign-in and returns the provider URL
+/// to send the browser to. The state, nonce and code verifier are kept until
+/// the callback. `link_user_id` is set when a signed-in user is linking the
+/// provider account to their own, and only that user can complete it.
+pub async fn authorization_url(
+    db: &Db,
+    provider: &OidcProvider,
+    link_user_id: Option<Uuid>,
+) -> Result<String> {
+    let cached = metadata(provider).await?;
+    let state = random_string(43);
+    let nonce = random_string(43);
+    let code_verifier = random_string(64);
+
+    sqlx::query(
+        r#"
+        INSERT INTO oidc_auth_requests (state, provider, code_verifier, nonce, redirect_uri, expires_at, link_user_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(&state)
+    .bind(&provider.name)
+    .bind(&code_verifier)
+    .bind(&nonce)
+    .bind(&provider.redirect_uri)
+    .bind((Utc::now() + chrono::Duration::minutes(AUTH_REQUEST_TTL_MINUTES)).naive_utc())
+    .bind(link_user_id)
+    .execute(db)
+    .await?;
+
+    let url = Url::parse_with_params(
+        &cached.metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("scope", provider.scopes.as_str()),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", pkce_challenge(&code_verifier).as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .context("Invalid authorization endpoint")?;
+
+    Ok(url.to_string())
+}
+
+/// Claims of a validated ID token that are used to find or create the user.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    // Some providers send this as a string
+    #[serde(default)]
+    email_verified: Option<serde_json::Value>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub preferred_username: Option<String>,
+    nonce: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// True only when the provider says it has verified the email; a
+    /// missing claim counts as unverified.
+    pub fn email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(serde_json::Value::Bool(verified)) => *verified,
+            Some(serde_json::Value::String(verified)) => verified == "true",
+            _ => false,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct AuthRequest {
+    code_verifier: String,
+    nonce: String,
+    redirect_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+async fn exchange_code(
+    provider: &OidcProvider,
+    cached: &CachedProvider,
+    code: &str,
+    request: &AuthRequest,
+) -> Result<String> {
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", request.redirect_uri.as_str()),
+        ("client_id", provider.client_id.as_str()),
+        ("code_verifier", request.code_verifier.as_str()),
+    ];
+
+    // HTTP Basic is the spec default; the secret only goes in the body when
+    // the provider doesn't support Basic
+    let mut http_request = http().post(&cached.metadata.token_endpoint);
+    if let Some(secret) = &provider.client_secret {
+        let methods = &cached.metadata.token_endpoint_auth_methods_supported;
+        if methods.iter().any(|m| m == "client_secret_post")
+            && !methods.iter().any(|m| m == "client_secret_basic")
+        {
+            form.push(("client_secret", secret.as_str()));
+        } else {
+            http_request = http_request.basic_auth(&provider.client_id, Some(secret));
+        }
+    }
+
+    let response: TokenResponse = http_request.form(&form).send().await?.json().await?;
+    if let Some(error) = response.error {
+        return Err(OidcError::Provider(
+            response.error_description.unwrap_or(error),
+        )
+        .into());
+    }
+    response
+        .id_token
+        .ok_or_else(|| OidcError::Provider("no ID token returned".to_string()).into())
+}
+
+async fn validate_id_token(
+    provider: &OidcProvider,
+    mut cached: Arc<CachedProvider>,
+    id_token: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims> {
+    let invalid = |reason: &str| OidcError::Provider(format!("invalid ID token: {}", reason));
+
+    let header = decode_header(id_token).map_err(|_| invalid("malformed"))?;
+    if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+        return Err(invalid("unsupported signing algorithm").into());
+    }
+
+    let find_key = |cached: &CachedProvider| -> Option<Jwk> {
+        match &header.kid {
+            Some(kid) => cached
+                .keys
+                .iter()
+                .find(|k| k.common.key_id.as_deref() == Some(kid.as_str()))
+                .cloned(),
+            None => cached.keys.first().cloned(),
+        }
+    };
+    // The provider may have rotated its keys since we cached them
+    let jwk = match find_key(&cached) {
+        Some(jwk) => jwk,
+        None => {
+            cached = metadata_newer_than(provider, KEY_REFRESH_INTERVAL).await?;
+            find_key(&cached).ok_or_else(|| invalid("unknown signing key"))?
+        }
+    };
+
+    let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid("unusable signing key"))?;
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&cached.metadata.issuer]);
+    validation.set_audience(&[&provider.client_id]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| invalid(&e.to_string()))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(invalid("nonce mismatch").into());
+    }
+    Ok(claims)
+}
+
+/// Finishes a sign-in: consumes the pending request for `state`, exchanges
+/// the code and returns the verified ID token claims. `link_user_id` has to
+/// match the one the request was started with.
+pub async fn complete(
+    db: &Db,
+    provider: &OidcProvider,
+    code: &str,
+    state: &str,
+    link_user_id: Option<Uuid>,
+) -> Result<IdTokenClaims> {
+    let request = sqlx::query_as::<_, AuthRequest>(
+        r#"
+        DELETE FROM oidc_auth_requests
+        WHERE state = $1 AND provider = $2 AND expires_at > NOW()
+          AND link_user_id IS NOT DISTINCT FROM $3
+        RETURNING code_verifier, nonce, redirect_uri
+        "#,
+    )
+    .bind(state)
+    .bind(&provider.name)
+    .bind(link_user_id)
+    .fetch_optional(db)
+    .await?
+    .ok_or(OidcError::InvalidState)?;
+
+    let cached = metadata(provider).await?;
+    let id_token = exchange_code(provider, &cached, code, &request).await?;
+    validate_id_token(provider, cached, &id_token, &request.nonce).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(email_verified: serde_json::Value) -> IdTokenClaims {
+        let mut claims = serde_json::json!({"sub": "abc", "email": "a@example.com"});
+        if !email_verified.is_null() {
+            claims["email_verified"] = email_verified;
+        }
+        serde_json::from_value(claims).unwrap()
+    }
+
+    #[test]
+    fn email_is_verified_only_when_the_provider_says_so() {
+        assert!(claims(serde_json::json!(true)).email_verified());
+        assert!(claims(serde_json::json!("true")).email_verified());
+        assert!(!claims(serde_json::json!(false)).email_verified());
+        assert!(!claims(serde_json::json!("false")).email_verified());
+        assert!(!claims(serde_json::json!(1)).email_verified());
+        assert!(!claims(serde_json::Value::Null).email_verified());
+    }
+
+    #[test]
+    fn pkce_challenge_is_unpadded_url_safe_sha256() {
+        // SHA-256 of "abc", which contains both `-` and `_` once encoded
+        assert_eq!(pkce_challenge("abc"), "ungWv48Bz-pBQUDeXa4iI7ADYaOWF3qctBD_YfIAFa0");
+    }
+}
@@ -4,6 +4,11 @@ use argon2::{
     password_hash::{SaltString, rand_core::OsRng},
 };
 
+/// Stored instead of a hash for accounts without a local password, such as
+/// service accounts and users signed up through single sign-on. It is never a
+/// valid Argon2 hash, so no password matches it.
+pub const NO_PASSWORD: &str = "!";
+
 pub fn hash_password(password: &str) -> Result<String> {
     let salt = SaltString::generate(&mut OsRng);
     let hash = Argon2::default()
@@ -12,11 +17,51 @@ pub fn hash_password(password: &str) -> Result<String> {
     Ok(hash.to_string())
 }
 
+/// Accounts without a usable hash, such as `NO_PASSWORD` ones, still pay
+/// for a verification so the response time doesn't reveal the account type.
 pub fn verify_password(hash: &str, password: &str) -> Result<()> {
-    let parsed_hash =
-        PasswordHash::new(hash).map_err(|e| anyhow!("Invalid password hash format: {}", e))?;
+    let parsed_hash = match PasswordHash::new(hash) {
+        Ok(parsed_hash) => parsed_hash,
+        Err(e) => {
+            verify_dummy_password(password);
+            return Err(anyhow!("Invalid password hash format: {}", e));
+        }
+    };
 
     Argon2::default()
         .verify_password(password.as_bytes(), &parsed_hash)
         .map_err(|e| anyhow!("Password verification failed: {}", e))
 }
+
+// Hash of a random, discarded password with the default Argon2 parameters.
+// Verified against when the username doesn't exist, so that failed logins take
+// as long for unknown users as for wrong passwords.
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$QZc3RLQa4Pd42jhGwPojCQ$wwQkGq5hsHvJlB/YyhlF6IPcd6xPJAnwmvNVfW5NFg4";
+
+pub fn verify_dummy_password(password: &str) {
+    let _ = verify_password(DUMMY_HASH, password);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_hashed_passwords() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(verify_password(&hash, "correct horse").is_ok());
+        assert!(verify_password(&hash, "wrong horse").is_err());
+    }
+
+    #[test]
+    fn no_password_matches_accounts_without_one() {
+        assert!(verify_password(NO_PASSWORD, "").is_err());
+        assert!(verify_password(NO_PASSWORD, "!").is_err());
+    }
+
+    #[test]
+    fn dummy_hash_is_a_valid_argon2_hash() {
+        assert!(PasswordHash::new(DUMMY_HASH).is_ok());
+    }
+}
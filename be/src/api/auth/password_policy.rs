@@ -0,0 +1,374 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    sync::OnceLock,
+};
+
+use anyhow::Result;
+use axum::{Json, http::StatusCode};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    api::auth::password::{NO_PASSWORD, hash_password, verify_password},
+    i18n::{self, Locale},
+    models::{
+        service_response::{ErrorItem, ServiceResponse},
+        user::User,
+    },
+};
+
+// Longer inputs are rejected before hashing so Argon2 can't be made to chew
+// on megabytes of data
+const MAX_LENGTH: usize = 128;
+
+static COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Password rules, read once from the environment.
+///
+/// - `PASSWORD_MIN_LENGTH`: minimum number of characters (default 10)
+/// - `PASSWORD_REQUIRE_UPPERCASE` / `_LOWERCASE` / `_DIGIT` / `_SYMBOL`:
+///   required character classes (defaults true, true, true, false)
+/// - `PASSWORD_HISTORY_SIZE`: how many previous passwords can't be reused,
+///   counting the current one (default 5, 0 disables)
+/// - `PASSWORD_MAX_AGE_DAYS`: days after which the password must be changed
+///   on next login (default 0, never)
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub history_size: i64,
+    pub max_age_days: i64,
+}
+
+static POLICY: OnceLock<PasswordPolicy> = OnceLock::new();
+static DENYLIST: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+pub fn policy() -> &'static PasswordPolicy {
+    POLICY.get_or_init(|| PasswordPolicy {
+        min_length: env_or("PASSWORD_MIN_LENGTH", 10),
+        require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", true),
+        require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", true),
+        require_digit: env_or("PASSWORD_REQUIRE_DIGIT", true),
+        require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", false),
+        history_size: env_or("PASSWORD_HISTORY_SIZE", 5),
+        max_age_days: env_or("PASSWORD_MAX_AGE_DAYS", 0),
+    })
+}
+
+fn denylist() -> &'static HashSet<&'static str> {
+    DENYLIST.get_or_init(|| {
+        COMMON_PASSWORDS
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    })
+}
+
+/// A password rejected by the policy, with one `ErrorItem` per broken rule.
+#[derive(Debug, thiserror::Error)]
+#[error("Password does not meet the password policy")]
+pub struct PasswordPolicyError {
+    pub violations: Vec<ErrorItem>,
+}
+
+impl PasswordPolicyError {
+    pub fn to_response(&self, locale: Locale) -> ServiceResponse<()> {
+        let message = i18n::translate(locale, "password_policy", &BTreeMap::new())
+            .unwrap_or_else(|| self.to_string());
+        ServiceResponse::<()>::builder()
+            .success(false)
+            .message(message)
+            .errors(self.violations.clone())
+            .build()
+            .localized(locale)
+    }
+}
+
+/// The 400 response listing the broken rules in the caller's language, if
+/// `e` is a policy violation.
+pub fn error_response(
+    e: &anyhow::Error,
+    locale: Locale,
+) -> Option<(StatusCode, Json<serde_json::Value>)> {
+    e.downcast_ref::<PasswordPolicyError>().map(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!(err.to_response(locale))),
+        )
+    })
+}
+
+fn violation(code: &str, message: String) -> ErrorItem {
+    ErrorItem {
+        code: code.to_string(),
+        message,
+        params: BTreeMap::new(),
+    }
+}
+
+/// Checks the rules that don't need the database. `user_name` is used to
+/// reject passwords containing it.
+pub fn validate(password: &str, user_name: Option<&str>) -> Vec<ErrorItem> {
+    validate_with(policy(), password, user_name)
+}
+
+fn validate_with(policy: &PasswordPolicy, password: &str, user_name: Option<&str>) -> Vec<ErrorItem> {
+    let mut violations = Vec::new();
+    let length = password.chars().count();
+
+    if length < policy.min_length {
+        violations.push(violation(
+            "password_too_short",
+            format!("Password must be at least {} characters long", policy.min_length),
+        )
+        .with_param("min_length", policy.min_length));
+    }
+    if length > MAX_LENGTH {
+        violations.push(violation(
+            "password_too_long",
+            format!("Password must be at most {} characters long", MAX_LENGTH),
+        )
+        .with_param("max_length", MAX_LENGTH));
+    }
+    if policy.require_uppercase && !password.chars().any(char::is_uppercase) {
+        violations.push(violation(
+            "password_missing_uppercase",
+            "Password must contain an uppercase letter".to_string(),
+        ));
+    }
+    if policy.require_lowercase && !password.chars().any(char::is_lowercase) {
+        violations.push(violation(
+            "password_missing_lowercase",
+            "Password must contain a lowercase letter".to_string(),
+        ));
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        violations.push(violation(
+            "password_missing_digit",
+            "Password must contain a digit".to_string(),
+        ));
+    }
+    if policy.require_symbol && password.chars().all(char::is_alphanumeric) {
+        violations.push(violation(
+            "password_missing_symbol",
+            "Password must contain a symbol".to_string(),
+        ));
+    }
+
+    let lowered = password.to_lowercase();
+    if denylist().contains(lowered.as_str()) {
+        violations.push(violation(
+            "password_too_common",
+            "Password is too common".to_string(),
+        ));
+    }
+    if let Some(user_name) = user_name.map(str::trim).filter(|u| u.len() >= 3)
+        && lowered.contains(&user_name.to_lowercase())
+    {
+        violations.push(violation(
+            "password_contains_user_name",
+            "Password must not contain the user name".to_string(),
+        ));
+    }
+
+    violations
+}
+
+// True if the password matches one of the user's last `history_size` hashes
+async fn was_used_recently(conn: &mut PgConnection, user_id: Uuid, password: &str) -> Result<bool> {
+    let history_size = policy().history_size;
+    if history_size <= 0 {
+        return Ok(false);
+    }
+
+    let hashes = sqlx::query_scalar::<_, String>(
+        "SELECT password_hash FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2",
+    )
+    .bind(user_id)
+    .bind(history_size)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(hashes.iter().any(|h| verify_password(h, password).is_ok()))
+}
+
+/// Validates the password for a new account; there is no history to check yet.
+pub fn check_new(password: &str, user_name: &str) -> Result<()> {
+    let violations = validate(password, Some(user_name));
+    if !violations.is_empty() {
+        return Err(PasswordPolicyError { violations }.into());
+    }
+    Ok(())
+}
+
+/// Records a hash in the user's password history and trims entries beyond
+/// what the policy needs.
+pub async fn record_history(conn: &mut PgConnection, user_id: Uuid, hash: &str) -> Result<()> {
+    sqlx::query("INSERT INTO password_history (user_id, password_hash) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(hash)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM password_history
+        WHERE user_id = $1
+          AND id NOT IN (
+            SELECT id FROM password_history
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+          )
+        "#,
+    )
+    .bind(user_id)
+    .bind(policy().history_size.max(1))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Validates `password` against the full policy, including reuse, then
+/// stores it as the user's password. Fails with `PasswordPolicyError` when a
+/// rule is broken.
+pub async fn set_password(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    user_name: &str,
+    password: &str,
+) -> Result<()> {
+    let mut violations = validate(password, Some(user_name));
+    if violations.is_empty() && was_used_recently(conn, user_id, password).await? {
+        violations.push(violation(
+            "password_reused",
+            format!(
+                "Password must differ from the last {} passwords",
+                policy().history_size
+            ),
+        )
+        .with_param("count", policy().history_size));
+    }
+    if !violations.is_empty() {
+        return Err(PasswordPolicyError { violations }.into());
+    }
+
+    let hash = hash_password(password)?;
+    sqlx::query("UPDATE users SET password_hash = $1, password_changed_at = NOW() WHERE id = $2")
+        .bind(&hash)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    record_history(conn, user_id, &hash).await
+}
+
+/// Whether the password is older than `PASSWORD_MAX_AGE_DAYS` and has to be
+/// changed before the account can be used.
+pub fn is_expired(password_changed_at: NaiveDateTime) -> bool {
+    older_than(password_changed_at, policy().max_age_days)
+}
+
+fn older_than(password_changed_at: NaiveDateTime, max_age_days: i64) -> bool {
+    max_age_days > 0 && Utc::now().naive_utc() - password_changed_at > Duration::days(max_age_days)
+}
+
+/// Whether the user has to change an expired password. Accounts without a
+/// local password never do.
+pub fn must_change(user: &User) -> bool {
+    user.password_hash != NO_PASSWORD && is_expired(user.password_changed_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 10,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: true,
+            history_size: 5,
+            max_age_days: 0,
+        }
+    }
+
+    fn codes(password: &str, user_name: Option<&str>) -> Vec<String> {
+        validate_with(&test_policy(), password, user_name)
+            .into_iter()
+            .map(|v| v.code)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_strong_password() {
+        assert!(codes("Tr1cky-Wombat!", Some("alice")).is_empty());
+    }
+
+    #[test]
+    fn reports_each_missing_character_class() {
+        assert_eq!(
+            codes("abcdefghijkl", None),
+            vec![
+                "password_missing_uppercase",
+                "password_missing_digit",
+                "password_missing_symbol"
+            ]
+        );
+        assert_eq!(
+            codes("ABCDEFGHIJ1!", None),
+            vec!["password_missing_lowercase"]
+        );
+    }
+
+    #[test]
+    fn enforces_length_limits() {
+        assert!(codes("Ab1!", None).contains(&"password_too_short".to_string()));
+        let long = format!("Ab1!{}", "x".repeat(MAX_LENGTH));
+        assert!(codes(&long, None).contains(&"password_too_long".to_string()));
+    }
+
+    #[test]
+    fn rejects_the_user_name_case_insensitively() {
+        assert!(
+            codes("xxALICExx-1A", Some(" alice "))
+                .contains(&"password_contains_user_name".to_string())
+        );
+        // Very short names would match too much to be useful
+        assert!(
+            !codes("Tr1cky-Wombat!", Some("wo"))
+                .contains(&"password_contains_user_name".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_common_passwords() {
+        let common = denylist().iter().next().copied().unwrap();
+        assert!(codes(common, None).contains(&"password_too_common".to_string()));
+    }
+
+    #[test]
+    fn expiry_follows_max_age() {
+        let changed_at = Utc::now().naive_utc() - Duration::days(100);
+        assert!(older_than(changed_at, 90));
+        assert!(!older_than(changed_at, 120));
+        // Zero disables expiry altogether
+        assert!(!older_than(changed_at, 0));
+    }
+}
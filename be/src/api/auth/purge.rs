@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::{
+    INVITATION_TTL_DAYS,
+    api::auth::{email::TokenPurpose, throttle},
+    db::Db,
+};
+
+const PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// Deletes expired refresh tokens and the sessions left without any token.
+/// Used tokens are kept until they expire so reuse can still be detected.
+pub async fn purge_expired(db: &Db) -> Result<(u64, u64)> {
+    let tokens = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    let sessions = sqlx::query(
+        r#"
+        DELETE FROM user_sessions s
+        WHERE NOT EXISTS (SELECT 1 FROM refresh_tokens rt WHERE rt.session_id = s.id)
+        "#,
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok((tokens, sessions))
+}
+
+/// Deletes login throttle counters that no longer block anyone, abandoned
+/// single sign-on requests and login history older than
+/// `LOGIN_ATTEMPT_RETENTION_DAYS` (default 90).
+pub async fn purge_login_records(db: &Db) -> Result<()> {
+    let retention_days: i32 = std::env::var("LOGIN_ATTEMPT_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(90);
+    let window = throttle::config().failure_window_minutes as i32;
+
+    sqlx::query(
+        r#"
+        DELETE FROM login_throttles
+        WHERE last_failed_at < NOW() - make_interval(mins => $1)
+          AND (locked_until IS NULL OR locked_until < NOW())
+        "#,
+    )
+    .bind(window)
+    .execute(db)
+    .await?;
+
+    sqlx::query("DELETE FROM oidc_auth_requests WHERE expires_at < NOW()")
+        .execute(db)
+        .await?;
+
+    sqlx::query("DELETE FROM login_attempts WHERE created_at < NOW() - make_interval(days => $1)")
+        .bind(retention_days)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes invited accounts that were never accepted once their last link has
+/// been expired for another `INVITATION_TTL_DAYS`, leaving admins time to
+/// resend it.
+pub async fn purge_stale_invitations(db: &Db) -> Result<u64> {
+    let deleted = sqlx::query(
+        r#"
+        DELETE FROM users u
+        WHERE u.invited_at IS NOT NULL
+          AND u.invitation_accepted_at IS NULL
+          AND u.invited_at < NOW() - make_interval(days => $1)
+          AND NOT EXISTS (
+            SELECT 1 FROM email_verification_tokens t
+            WHERE t.user_id = u.id
+              AND t.purpose = $2
+              AND t.expires_at > NOW() - make_interval(days => $1)
+          )
+        "#,
+    )
+    .bind(INVITATION_TTL_DAYS as i32)
+    .bind(TokenPurpose::Invitation.as_str())
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(deleted)
+}
+
+pub fn spawn_purge_worker(db: Db) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL_SECONDS));
+        loop {
+            interval.tick().await;
+            match purge_expired(&db).await {
+                Ok((0, 0)) => {}
+                Ok((tokens, sessions)) => tracing::info!(
+                    "Purged {} expired refresh tokens and {} sessions",
+                    tokens,
+                    sessions
+                ),
+                Err(e) => tracing::error!("Session purge worker error: {}", e),
+            }
+            if let Err(e) = purge_login_records(&db).await {
+                tracing::error!("Login record purge error: {}", e);
+            }
+            match purge_stale_invitations(&db).await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!("Removed {} stale invitations", deleted),
+                Err(e) => tracing::error!("Invitation purge error: {}", e),
+            }
+        }
+    })
+}
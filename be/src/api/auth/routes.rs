@@ -1,22 +1,83 @@
-use crate::api::auth::handlers::{
-    change_password_handler, forgot_password_handler, login_handler, profile_handler,
-    refresh_handler, register_handler,
+use crate::api::auth::{
+    handlers::{
+        accept_invitation_handler, change_password_handler, create_ldap_group_mapping_handler, delete_ldap_group_mapping_handler,
+        end_impersonation_handler, forgot_password_handler, get_invitation_handler,
+        get_mfa_policy_handler, jwks_handler,
+        list_impersonation_requests_handler, list_impersonations_handler,
+        list_ldap_group_mappings_handler, list_ldap_sync_runs_handler, list_sessions_handler,
+        login_handler, login_mfa_handler, logout_all_handler, logout_handler, mfa_disable_handler,
+        mfa_enable_handler, mfa_recovery_codes_handler, mfa_setup_handler, mfa_status_handler,
+        oidc_authorize_handler, oidc_callback_handler, oidc_link_authorize_handler,
+        oidc_link_callback_handler, oidc_providers_handler, profile_handler,
+        refresh_handler, register_handler, reset_password_handler, revoke_session_handler,
+        run_ldap_sync_handler, start_impersonation_handler, update_mfa_policy_handler,
+        verify_email_handler,
+    },
+    impersonation,
 };
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 
 pub fn auth_routes() -> Router {
     let public_routes = Router::new()
         .route("/register", post(register_handler))
         .route("/login", post(login_handler))
+        .route("/login/mfa", post(login_mfa_handler))
         .route("/refresh", post(refresh_handler))
-        .route("/forgot-password", post(forgot_password_handler));
+        .route("/forgot-password", post(forgot_password_handler))
+        .route("/reset-password", post(reset_password_handler))
+        .route("/verify-email", post(verify_email_handler))
+        .route("/invitations/{token}", get(get_invitation_handler))
+        .route("/accept-invitation", post(accept_invitation_handler))
+        .route("/oidc", get(oidc_providers_handler))
+        .route("/oidc/{provider}", get(oidc_authorize_handler))
+        .route("/oidc/{provider}/callback", post(oidc_callback_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler));
 
-    let protected_routes = Router::new()
+    // Account security and admin endpoints an impersonating admin can't use
+    let sensitive_routes = Router::new()
         .route("/change-password", post(change_password_handler))
+        .route("/logout", post(logout_handler))
+        .route("/logout-all", post(logout_all_handler))
+        .route("/sessions", get(list_sessions_handler))
+        .route("/sessions/{id}", delete(revoke_session_handler))
+        .route("/mfa/setup", post(mfa_setup_handler))
+        .route("/mfa/enable", post(mfa_enable_handler))
+        .route("/mfa/disable", post(mfa_disable_handler))
+        .route("/mfa/recovery-codes", post(mfa_recovery_codes_handler))
+        .route("/oidc/{provider}/link", post(oidc_link_authorize_handler))
+        .route("/oidc/{provider}/link/callback", post(oidc_link_callback_handler))
+        .route(
+            "/mfa/policy",
+            get(get_mfa_policy_handler).put(update_mfa_policy_handler),
+        )
+        .route(
+            "/ldap/group-mappings",
+            get(list_ldap_group_mappings_handler).post(create_ldap_group_mapping_handler),
+        )
+        .route(
+            "/ldap/group-mappings/{id}",
+            delete(delete_ldap_group_mapping_handler),
+        )
+        .route("/ldap/sync", post(run_ldap_sync_handler))
+        .route("/ldap/sync-runs", get(list_ldap_sync_runs_handler))
+        .route(
+            "/impersonations",
+            get(list_impersonations_handler).post(start_impersonation_handler),
+        )
+        .route(
+            "/impersonations/{id}/requests",
+            get(list_impersonation_requests_handler),
+        )
+        .route_layer(axum::middleware::from_fn(impersonation::forbid));
+
+    let protected_routes = Router::new()
         .route("/me", get(profile_handler))
+        .route("/mfa", get(mfa_status_handler))
+        .route("/impersonations/end", post(end_impersonation_handler))
+        .merge(sensitive_routes)
         .route_layer(axum::middleware::from_fn(
             crate::middlewares::auth::authenticate,
         ));
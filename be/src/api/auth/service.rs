@@ -1,27 +1,174 @@
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use chrono::{Duration, Utc};
+use sqlx::{PgConnection, PgExecutor};
 use uuid::Uuid;
 
 use crate::{
-    REFRESH_TOKEN_TTL_DAYS,
+    IMPERSONATION_TTL_MINUTES, REFRESH_TOKEN_TTL_DAYS,
     api::auth::{
         dto::{
-            AuthResponse, ChangePasswordRequest, ForgotPasswordRequest, LoginRequest,
-            RefreshRequest, RegisterRequest,
+            AcceptInvitationRequest, AuthResponse, ChangePasswordRequest, CreateLdapGroupMappingRequest, ForgotPasswordRequest,
+            ImpersonationResponse, ImpersonatorResponse, InvitationResponse, LoginRequest, LoginResponse, MfaChallengeResponse, MfaCodeRequest, MfaLoginRequest, MfaPolicy,
+            MfaSetupResponse, MfaStatusResponse, OidcAuthorizeResponse, OidcCallbackRequest,
+            OidcProviderResponse, RecoveryCodesResponse,
+            RefreshRequest,
+            ProfileResponse, RegisterRequest, ResetPasswordRequest, StartImpersonationRequest,
+            VerifyEmailRequest,
         },
-        email::{generate_verification_token, send_email_stub, token_expiry_time},
-        jwt::{generate_access_token, generate_refresh_token},
-        password::{hash_password, verify_password},
+        email::{
+            TokenPurpose, email_verification_required, generate_verification_token,
+            token_expiry_time,
+        },
+        jwt::{
+            MFA_PENDING_SCOPE, REFRESH_SCOPE, generate_access_token, generate_impersonation_token,
+            generate_mfa_token, generate_refresh_token, validate_token,
+        },
+        ldap, mfa,
+        oidc::{self, IdTokenClaims},
+        password::{NO_PASSWORD, hash_password, verify_dummy_password, verify_password},
+        password_policy,
+        throttle::{self, LoginOutcome, ThrottleKey},
     },
     db::Db,
-    models::{refresh_token::RefreshToken, user::User},
+    extractors::ClientInfo,
+    mail::{outbox, templates::EmailTemplate},
+    models::{
+        impersonation::{Impersonation, ImpersonationRequest},
+        ldap_group_mapping::LdapGroupMapping, ldap_sync_run::LdapSyncRun,
+        refresh_token::RefreshToken, user::User, user_mfa::UserMfa, user_session::UserSession,
+    },
 };
 
-pub async fn register(db: &Db, req: RegisterRequest) -> Result<AuthResponse> {
+#[derive(Debug, thiserror::Error)]
+pub enum AuthServiceError {
+    #[error("Email address has not been verified")]
+    EmailNotVerified,
+    #[error("Token is invalid or has expired")]
+    InvalidToken,
+    #[error("Refresh token has already been used; the session has been revoked")]
+    TokenReuse,
+    #[error("Invalid two-factor authentication code")]
+    InvalidMfaCode,
+    #[error("Two-factor authentication is already enabled")]
+    MfaAlreadyEnabled,
+    #[error("Two-factor authentication has not been set up")]
+    MfaNotEnabled,
+    #[error("Two-factor authentication is required for your account")]
+    MfaRequired,
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("Too many failed login attempts, try again in {retry_after} seconds")]
+    TooManyAttempts { retry_after: i64 },
+    #[error("No account matches this identity")]
+    SsoAccountNotFound,
+    #[error("Sign in with your password and link this identity from your account first")]
+    SsoLinkRequired,
+    #[error("This identity is already linked to another account")]
+    SsoIdentityTaken,
+    #[error("A reason is required to impersonate a user")]
+    ImpersonationReasonRequired,
+    #[error("Admins and service accounts cannot be impersonated")]
+    CannotImpersonate,
+    #[error("Username is required")]
+    UserNameRequired,
+    #[error("Username already taken")]
+    UserNameTaken,
+    #[error("This account has been deactivated")]
+    AccountDeactivated,
+}
+
+fn ensure_email_verified(user: &User) -> Result<()> {
+    if email_verification_required() && user.email_verified_at.is_none() {
+        return Err(AuthServiceError::EmailNotVerified.into());
+    }
+    Ok(())
+}
+
+// Inserts a new refresh token into the session and pairs it with an access token
+async fn issue_tokens(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<AuthResponse> {
+    let token_id = Uuid::new_v4();
+    let refresh_token = generate_refresh_token(user_id, session_id, token_id);
+    let expires = (Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc();
+
+    sqlx::query(
+        "INSERT INTO refresh_tokens (id, user_id, token, expires_at, session_id) VALUES ($1,$2,$3,$4,$5)",
+    )
+    .bind(token_id)
+    .bind(user_id)
+    .bind(&refresh_token)
+    .bind(expires)
+    .bind(session_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(AuthResponse {
+        access_token: generate_access_token(user_id, session_id),
+        refresh_token,
+        password_change_required: false,
+    })
+}
+
+// Starts a new token family for a fresh login
+async fn start_session(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<AuthResponse> {
+    let deactivated = sqlx::query_scalar::<_, bool>(
+        "SELECT deactivated_at IS NOT NULL FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if deactivated {
+        return Err(AuthServiceError::AccountDeactivated.into());
+    }
+
+    let session_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO user_sessions (user_id, user_agent, ip_address) VALUES ($1,$2,$3) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    issue_tokens(conn, user_id, session_id).await
+}
+
+pub(crate) async fn revoke_sessions<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+    reason: &str,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE user_sessions
+        SET revoked_at = NOW(), revoked_reason = $3
+        WHERE user_id = $1
+          AND ($2::uuid IS NULL OR id = $2)
+          AND revoked_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(session_id)
+    .bind(reason)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn register(db: &Db, req: RegisterRequest, client: &ClientInfo) -> Result<AuthResponse> {
+    password_policy::check_new(&req.password, &req.user_name)?;
     let hashed = hash_password(&req.password)?;
     let person_id = Uuid::new_v4();
     let user_id = Uuid::new_v4();
-    let refresh_id = Uuid::new_v4();
     let verify_id = Uuid::new_v4();
     let mut tx = db.begin().await?;
 
@@ -53,120 +200,966 @@ pub async fn register(db: &Db, req: RegisterRequest) -> Result<AuthResponse> {
     .bind(person_id)
     .execute(&mut *tx)
     .await?;
+    password_policy::record_history(&mut tx, user_id, &hashed).await?;
 
-    let refresh_token = generate_refresh_token(user_id);
-    let expires = (Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc();
-
-    sqlx::query("INSERT INTO refresh_tokens (id, user_id, token, expires_at) VALUES ($1,$2,$3,$4)")
-        .bind(refresh_id)
-        .bind(user_id)
-        .bind(&refresh_token)
-        .bind(expires)
-        .execute(&mut *tx)
-        .await?;
-
-    let access_token = generate_access_token(user_id);
+    let tokens = start_session(&mut tx, user_id, client).await?;
 
     let token = generate_verification_token();
     let token_exp = token_expiry_time();
 
     sqlx::query(
-        "INSERT INTO email_verification_tokens (id, user_id, token, expires_at) VALUES ($1,$2,$3,$4)",
+        "INSERT INTO email_verification_tokens (id, user_id, token, expires_at, purpose) VALUES ($1,$2,$3,$4,$5)",
     )
     .bind(verify_id)
     .bind(user_id)
     .bind(&token)
     .bind(token_exp)
+    .bind(TokenPurpose::VerifyEmail.as_str())
     .execute(&mut *tx)
     .await?;
 
+    let email = EmailTemplate::VerifyEmail { token: &token }.render(&req.email);
+    outbox::enqueue(&mut *tx, &email).await?;
+
     tx.commit().await?;
-    send_email_stub(&req.email, &token);
 
-    Ok(AuthResponse {
-        access_token,
-        refresh_token,
-    })
+    Ok(tokens)
 }
 
-pub async fn login(db: &Db, req: LoginRequest) -> Result<AuthResponse> {
+// First login of a directory user: finds their entry, binds as it and
+// creates the local user
+async fn ldap_login(db: &Db, login: &str, password: &str) -> Result<Option<User>> {
+    let Some(entry) = ldap::find_user(login).await? else {
+        return Ok(None);
+    };
+    if !ldap::bind(&entry.dn, password).await? {
+        return Ok(None);
+    }
+
+    let mut tx = db.begin().await?;
+    let (user, _) = ldap::upsert_user(&mut tx, &entry).await?;
+    tx.commit().await?;
+    Ok(Some(user))
+}
+
+/// Checks the password and starts a session, unless the account has MFA
+/// enabled, in which case a short-lived MFA token is returned instead.
+pub async fn login(db: &Db, req: LoginRequest, client: &ClientInfo) -> Result<LoginResponse> {
+    let keys = throttle::login_keys(&req.user_name, client);
+    if let Some(retry_after) = throttle::begin_attempt(db, &keys).await? {
+        throttle::record_attempt(db, &req.user_name, None, client, LoginOutcome::Throttled).await?;
+        return Err(AuthServiceError::TooManyAttempts { retry_after }.into());
+    }
+
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE user_name = $1")
         .bind(&req.user_name)
+        .fetch_optional(db)
+        .await?;
+
+    // Unknown users still pay for a hash verification so the response time
+    // doesn't reveal whether the username exists
+    let (user, failure) = match user {
+        // Service accounts only authenticate with API keys
+        Some(user) if user.is_service_account => {
+            verify_dummy_password(&req.password);
+            (user, Some(LoginOutcome::InvalidPassword))
+        }
+        // Directory users are checked by binding as them
+        Some(user) if user.ldap_dn.is_some() => {
+            let dn = user.ldap_dn.clone().unwrap_or_default();
+            match ldap::bind(&dn, &req.password).await? {
+                true => (user, None),
+                false => (user, Some(LoginOutcome::InvalidPassword)),
+            }
+        }
+        Some(user) => match verify_password(&user.password_hash, &req.password) {
+            Ok(()) => (user, None),
+            Err(_) => (user, Some(LoginOutcome::InvalidPassword)),
+        },
+        None if ldap::config().is_some() => match ldap_login(db, &req.user_name, &req.password).await? {
+            Some(user) => (user, None),
+            None => {
+                throttle::record_attempt(db, &req.user_name, None, client, LoginOutcome::UnknownUser)
+                    .await?;
+                return Err(AuthServiceError::InvalidCredentials.into());
+            }
+        },
+        None => {
+            verify_dummy_password(&req.password);
+            throttle::record_attempt(db, &req.user_name, None, client, LoginOutcome::UnknownUser)
+                .await?;
+            return Err(AuthServiceError::InvalidCredentials.into());
+        }
+    };
+
+    if let Some(outcome) = failure {
+        throttle::record_attempt(db, &req.user_name, Some(user.id), client, outcome).await?;
+        return Err(AuthServiceError::InvalidCredentials.into());
+    }
+    throttle::forgive(db, &keys).await?;
+
+    if let Err(e) = ensure_email_verified(&user) {
+        throttle::record_attempt(
+            db,
+            &req.user_name,
+            Some(user.id),
+            client,
+            LoginOutcome::EmailNotVerified,
+        )
+        .await?;
+        return Err(e);
+    }
+
+    // The counter is only cleared once the second factor has been passed too,
+    // otherwise knowing the password would allow unlimited MFA guesses
+    if mfa::is_enabled(db, user.id).await? {
+        throttle::record_attempt(db, &req.user_name, Some(user.id), client, LoginOutcome::MfaRequired)
+            .await?;
+        return Ok(LoginResponse::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token: generate_mfa_token(user.id),
+        }));
+    }
+
+    let mut tx = db.begin().await?;
+    let mut tokens = start_session(&mut tx, user.id, client).await?;
+    tx.commit().await?;
+    tokens.password_change_required = password_policy::must_change(&user);
+
+    throttle::reset(db, &ThrottleKey::user(&user.user_name)).await?;
+    throttle::record_attempt(db, &req.user_name, Some(user.id), client, LoginOutcome::Success).await?;
+
+    Ok(LoginResponse::Tokens(tokens))
+}
+
+/// Second login step: exchanges the MFA token and a TOTP or recovery code for
+/// a session.
+pub async fn login_mfa(db: &Db, req: MfaLoginRequest, client: &ClientInfo) -> Result<AuthResponse> {
+    let claims = validate_token(&req.mfa_token)
+        .filter(|c| c.scope.as_deref() == Some(MFA_PENDING_SCOPE))
+        .ok_or(AuthServiceError::InvalidToken)?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
         .fetch_one(db)
         .await?;
+    ensure_email_verified(&user)?;
 
-    verify_password(&user.password_hash, &req.password)
-        .map_err(|e| anyhow!("Password verification failed: {}", e))?;
+    let keys = throttle::login_keys(&user.user_name, client);
+    if let Some(retry_after) = throttle::begin_attempt(db, &keys).await? {
+        throttle::record_attempt(db, &user.user_name, Some(user.id), client, LoginOutcome::Throttled)
+            .await?;
+        return Err(AuthServiceError::TooManyAttempts { retry_after }.into());
+    }
 
-    let access_token = generate_access_token(user.id);
-    let refresh_token = generate_refresh_token(user.id);
-    let expires = (Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc();
+    let mut tx = db.begin().await?;
+    if let Err(e) = verify_second_factor(&mut tx, user.id, &req.code).await {
+        drop(tx);
+        if matches!(
+            e.downcast_ref::<AuthServiceError>(),
+            Some(AuthServiceError::InvalidMfaCode)
+        ) {
+            throttle::record_attempt(
+                db,
+                &user.user_name,
+                Some(user.id),
+                client,
+                LoginOutcome::InvalidMfaCode,
+            )
+            .await?;
+        } else {
+            throttle::forgive(db, &keys).await?;
+        }
+        return Err(e);
+    }
+    let mut tokens = start_session(&mut tx, user.id, client).await?;
+    tx.commit().await?;
+    tokens.password_change_required = password_policy::must_change(&user);
 
-    sqlx::query("INSERT INTO refresh_tokens (id, user_id, token, expires_at) VALUES ($1,$2,$3,$4)")
-        .bind(Uuid::new_v4())
-        .bind(user.id)
-        .bind(&refresh_token)
-        .bind(expires)
-        .execute(db)
+    throttle::forgive(db, &keys).await?;
+    throttle::reset(db, &ThrottleKey::user(&user.user_name)).await?;
+    throttle::record_attempt(db, &user.user_name, Some(user.id), client, LoginOutcome::Success)
         .await?;
 
-    Ok(AuthResponse {
-        access_token,
-        refresh_token,
+    Ok(tokens)
+}
+
+pub fn oidc_providers() -> Vec<OidcProviderResponse> {
+    oidc::providers()
+        .iter()
+        .map(|p| OidcProviderResponse { name: p.name.clone() })
+        .collect()
+}
+
+pub async fn oidc_authorize(db: &Db, provider_name: &str) -> Result<OidcAuthorizeResponse> {
+    let provider = oidc::provider(provider_name)?;
+    Ok(OidcAuthorizeResponse {
+        authorization_url: oidc::authorization_url(db, provider, None).await?,
+    })
+}
+
+enum OidcMatch {
+    Found(Box<User>),
+    // The email belongs to an account that has to link the identity itself
+    LinkRequired,
+    NotFound,
+}
+
+// The user an identity provider account belongs to: the linked user if it has
+// signed in before, otherwise the one user with the same email. Email matches
+// are only trusted for non-admin accounts without a local password; taking
+// over anything else needs the owner to link the identity while signed in.
+async fn find_oidc_user(
+    conn: &mut PgConnection,
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<OidcMatch> {
+    let linked = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        JOIN user_identities i ON i.user_id = u.id
+        WHERE i.provider = $1 AND i.subject = $2
+        "#,
+    )
+    .bind(provider)
+    .bind(&claims.sub)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(user) = linked {
+        return Ok(OidcMatch::Found(Box::new(user)));
+    }
+
+    // An unverified email could have been typed in by anyone, so it never
+    // matches an existing account
+    let Some(email) = claims.email.as_deref().filter(|_| claims.email_verified()) else {
+        return Ok(OidcMatch::NotFound);
+    };
+
+    let mut users = sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        WHERE NOT u.is_service_account
+          AND (LOWER(u.email) = LOWER($1) OR EXISTS (
+              SELECT 1 FROM person_contacts pc
+              WHERE pc.person_id = u.person_id AND LOWER(pc.email) = LOWER($1)
+          ))
+        LIMIT 2
+        "#,
+    )
+    .bind(email)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(match users.len() {
+        0 => OidcMatch::NotFound,
+        1 if !users[0].is_admin && users[0].password_hash == NO_PASSWORD => {
+            OidcMatch::Found(Box::new(users.remove(0)))
+        }
+        _ => OidcMatch::LinkRequired,
     })
 }
 
-pub async fn refresh(db: &Db, req: RefreshRequest) -> Result<AuthResponse> {
-    let token_row =
-        sqlx::query_as::<_, RefreshToken>("SELECT * FROM refresh_tokens WHERE token = $1")
-            .bind(&req.refresh_token)
-            .fetch_one(db)
+// A free username based on the provider's preferred username or the email
+async fn unique_user_name(conn: &mut PgConnection, claims: &IdTokenClaims, email: &str) -> Result<String> {
+    let base: String = claims
+        .preferred_username
+        .as_deref()
+        .unwrap_or_else(|| email.split('@').next().unwrap_or_default())
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        .collect::<String>()
+        .to_lowercase();
+    let base = if base.is_empty() { "user".to_string() } else { base };
+
+    for n in 1..=100 {
+        let candidate = if n == 1 { base.clone() } else { format!("{}{}", base, n) };
+        let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_name = $1)")
+            .bind(&candidate)
+            .fetch_one(&mut *conn)
+            .await?;
+        if !taken {
+            return Ok(candidate);
+        }
+    }
+    Ok(format!("{}-{}", base, &Uuid::new_v4().simple().to_string()[..8]))
+}
+
+// Creates the user for a first sign-in, reusing the person when the email is
+// already on file as a contact. The account has no local password.
+async fn provision_oidc_user(conn: &mut PgConnection, claims: &IdTokenClaims, email: &str) -> Result<User> {
+    let existing_person = sqlx::query_scalar::<_, Uuid>(
+        "SELECT person_id FROM person_contacts WHERE LOWER(email) = LOWER($1) AND person_id IS NOT NULL LIMIT 1",
+    )
+    .bind(email)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let person_id = match existing_person {
+        Some(person_id) => person_id,
+        None => {
+            let (first_name, last_name) = match (&claims.given_name, &claims.family_name, &claims.name) {
+                (Some(first), last, _) => (first.clone(), last.clone().unwrap_or_default()),
+                (None, _, Some(name)) => match name.trim().split_once(' ') {
+                    Some((first, last)) => (first.to_string(), last.trim().to_string()),
+                    None => (name.trim().to_string(), String::new()),
+                },
+                _ => (email.split('@').next().unwrap_or_default().to_string(), String::new()),
+            };
+
+            let person_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO persons (id, first_name, middle_name, last_name) VALUES ($1,$2,'',$3)",
+            )
+            .bind(person_id)
+            .bind(first_name)
+            .bind(last_name)
+            .execute(&mut *conn)
+            .await?;
+
+            sqlx::query("INSERT INTO person_contacts (id, person_id, email) VALUES ($1,$2,$3)")
+                .bind(Uuid::new_v4())
+                .bind(person_id)
+                .bind(email)
+                .execute(&mut *conn)
+                .await?;
+            person_id
+        }
+    };
+
+    let user_name = unique_user_name(conn, claims, email).await?;
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (id, user_name, email, password_hash, person_id, email_verified_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_name)
+    .bind(email)
+    .bind(NO_PASSWORD)
+    .bind(person_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(user)
+}
+
+/// Signs in through an identity provider: validates the callback, finds the
+/// matching user (creating one if the provider allows it) and starts a
+/// session. Accounts with MFA enabled still get the second-factor challenge.
+pub async fn oidc_login(
+    db: &Db,
+    provider_name: &str,
+    req: OidcCallbackRequest,
+    client: &ClientInfo,
+) -> Result<LoginResponse> {
+    let provider = oidc::provider(provider_name)?;
+    let claims = oidc::complete(db, provider, &req.code, &req.state, None).await?;
+    let login_name = claims.email.clone().unwrap_or_else(|| claims.sub.clone());
+
+    let mut tx = db.begin().await?;
+    let found = find_oidc_user(&mut tx, &provider.name, &claims).await?;
+    let provisionable = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified() && provider.auto_provision);
+    let user = match (found, provisionable) {
+        (OidcMatch::Found(user), _) => *user,
+        (OidcMatch::NotFound, Some(email)) => provision_oidc_user(&mut tx, &claims, email).await?,
+        (OidcMatch::LinkRequired, _) => {
+            drop(tx);
+            throttle::record_attempt(db, &login_name, None, client, LoginOutcome::UnknownUser).await?;
+            return Err(AuthServiceError::SsoLinkRequired.into());
+        }
+        (OidcMatch::NotFound, None) => {
+            drop(tx);
+            throttle::record_attempt(db, &login_name, None, client, LoginOutcome::UnknownUser).await?;
+            return Err(AuthServiceError::SsoAccountNotFound.into());
+        }
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email, last_login_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (provider, subject) DO UPDATE
+        SET email = EXCLUDED.email, last_login_at = NOW()
+        "#,
+    )
+    .bind(user.id)
+    .bind(&provider.name)
+    .bind(&claims.sub)
+    .bind(&claims.email)
+    .execute(&mut *tx)
+    .await?;
+
+    // The provider has verified the address the account is registered with
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET email_verified_at = COALESCE(email_verified_at, NOW())
+        WHERE id = $1 AND LOWER(email) = LOWER($2) AND $3
+        RETURNING *
+        "#,
+    )
+    .bind(user.id)
+    .bind(&claims.email)
+    .bind(claims.email_verified())
+    .fetch_optional(&mut *tx)
+    .await?
+    .unwrap_or(user);
+    tx.commit().await?;
+
+    if let Err(e) = ensure_email_verified(&user) {
+        throttle::record_attempt(db, &user.user_name, Some(user.id), client, LoginOutcome::EmailNotVerified)
+            .await?;
+        return Err(e);
+    }
+
+    if mfa::is_enabled(db, user.id).await? {
+        throttle::record_attempt(db, &user.user_name, Some(user.id), client, LoginOutcome::MfaRequired)
             .await?;
+        return Ok(LoginResponse::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token: generate_mfa_token(user.id),
+        }));
+    }
+
+    let mut tx = db.begin().await?;
+    let mut tokens = start_session(&mut tx, user.id, client).await?;
+    tx.commit().await?;
+    tokens.password_change_required = password_policy::must_change(&user);
+
+    throttle::record_attempt(db, &user.user_name, Some(user.id), client, LoginOutcome::Success).await?;
+
+    Ok(LoginResponse::Tokens(tokens))
+}
+
+/// Starts linking an identity provider account to the signed-in user.
+pub async fn oidc_link_authorize(db: &Db, user_id: Uuid, provider_name: &str) -> Result<OidcAuthorizeResponse> {
+    let provider = oidc::provider(provider_name)?;
+    Ok(OidcAuthorizeResponse {
+        authorization_url: oidc::authorization_url(db, provider, Some(user_id)).await?,
+    })
+}
+
+/// Finishes a link started by `oidc_link_authorize`, after which the user can
+/// sign in with the provider account. Fails if the provider account already
+/// belongs to someone else.
+pub async fn link_oidc_identity(
+    db: &Db,
+    user_id: Uuid,
+    provider_name: &str,
+    req: OidcCallbackRequest,
+) -> Result<()> {
+    let provider = oidc::provider(provider_name)?;
+    let claims = oidc::complete(db, provider, &req.code, &req.state, Some(user_id)).await?;
+
+    let linked = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (provider, subject) DO UPDATE
+        SET email = EXCLUDED.email
+        WHERE user_identities.user_id = EXCLUDED.user_id
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&provider.name)
+    .bind(&claims.sub)
+    .bind(&claims.email)
+    .fetch_optional(db)
+    .await?;
+
+    if linked.is_none() {
+        return Err(AuthServiceError::SsoIdentityTaken.into());
+    }
+    Ok(())
+}
+
+// Accepts a TOTP code, or burns one of the user's recovery codes
+async fn verify_second_factor(conn: &mut PgConnection, user_id: Uuid, code: &str) -> Result<()> {
+    let user_mfa = sqlx::query_as::<_, UserMfa>(
+        "SELECT * FROM user_mfa WHERE user_id = $1 AND enabled_at IS NOT NULL FOR UPDATE",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AuthServiceError::MfaNotEnabled)?;
+
+    if mfa::is_totp_code(code) {
+        let step = mfa::verify_code(&user_mfa.secret, code, user_mfa.last_used_step)?
+            .ok_or(AuthServiceError::InvalidMfaCode)?;
+
+        sqlx::query("UPDATE user_mfa SET last_used_step = $1 WHERE user_id = $2")
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(());
+    }
+
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE mfa_recovery_codes
+        SET used_at = NOW()
+        WHERE id = (
+            SELECT id FROM mfa_recovery_codes
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            LIMIT 1
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(mfa::hash_recovery_code(code))
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AuthServiceError::InvalidMfaCode)?;
+
+    Ok(())
+}
+
+// Replaces all of the user's recovery codes and returns the new plain codes
+async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>> {
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes = mfa::generate_recovery_codes();
+    let hashes: Vec<String> = codes.iter().map(|c| mfa::hash_recovery_code(c)).collect();
+    sqlx::query(
+        "INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::varchar[])",
+    )
+    .bind(user_id)
+    .bind(&hashes)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(codes)
+}
+
+pub async fn mfa_status(db: &Db, user: &User) -> Result<MfaStatusResponse> {
+    let user_mfa = sqlx::query_as::<_, UserMfa>("SELECT * FROM user_mfa WHERE user_id = $1")
+        .bind(user.id)
+        .fetch_optional(db)
+        .await?;
+
+    let recovery_codes_remaining = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user.id)
+    .fetch_one(db)
+    .await?;
+
+    Ok(MfaStatusResponse {
+        enabled: user_mfa.as_ref().is_some_and(|m| m.enabled_at.is_some()),
+        pending_setup: user_mfa.as_ref().is_some_and(|m| m.enabled_at.is_none()),
+        required: mfa::is_required(db, user).await?,
+        recovery_codes_remaining,
+    })
+}
+
+/// Starts enrollment with a new secret. MFA isn't enforced until the first
+/// code has been confirmed through `enable_mfa`.
+pub async fn setup_mfa(db: &Db, user: &User) -> Result<MfaSetupResponse> {
+    let secret = mfa::generate_secret();
+    let otpauth_uri = mfa::otpauth_uri(&secret, &user.user_name)?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_mfa (user_id, secret) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_mfa.enabled_at IS NULL
+        "#,
+    )
+    .bind(user.id)
+    .bind(&secret)
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AuthServiceError::MfaAlreadyEnabled.into());
+    }
+
+    Ok(MfaSetupResponse {
+        secret,
+        otpauth_uri,
+    })
+}
+
+pub async fn enable_mfa(db: &Db, user_id: Uuid, req: MfaCodeRequest) -> Result<RecoveryCodesResponse> {
+    let mut tx = db.begin().await?;
+
+    let user_mfa = sqlx::query_as::<_, UserMfa>("SELECT * FROM user_mfa WHERE user_id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AuthServiceError::MfaNotEnabled)?;
+
+    if user_mfa.enabled_at.is_some() {
+        return Err(AuthServiceError::MfaAlreadyEnabled.into());
+    }
+
+    let step = mfa::verify_code(&user_mfa.secret, &req.code, None)?
+        .ok_or(AuthServiceError::InvalidMfaCode)?;
+
+    sqlx::query("UPDATE user_mfa SET enabled_at = NOW(), last_used_step = $1 WHERE user_id = $2")
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let recovery_codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+pub async fn disable_mfa(db: &Db, user: &User, req: MfaCodeRequest) -> Result<()> {
+    if mfa::is_required(db, user).await? {
+        return Err(AuthServiceError::MfaRequired.into());
+    }
+
+    let mut tx = db.begin().await?;
+    verify_second_factor(&mut tx, user.id, &req.code).await?;
+
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_mfa WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn regenerate_recovery_codes(
+    db: &Db,
+    user_id: Uuid,
+    req: MfaCodeRequest,
+) -> Result<RecoveryCodesResponse> {
+    let mut tx = db.begin().await?;
+    verify_second_factor(&mut tx, user_id, &req.code).await?;
+    let recovery_codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    Ok(RecoveryCodesResponse { recovery_codes })
+}
+
+pub async fn get_mfa_policy(db: &Db) -> Result<MfaPolicy> {
+    Ok(MfaPolicy {
+        required_for_delete: mfa::required_for_delete(db).await?,
+    })
+}
+
+pub async fn update_mfa_policy(db: &Db, admin_id: Uuid, policy: MfaPolicy) -> Result<MfaPolicy> {
+    sqlx::query(
+        r#"
+        INSERT INTO security_settings (key, value, updated_at, updated_by)
+        VALUES ($1, $2, NOW(), $3)
+        ON CONFLICT (key) DO UPDATE
+        SET value = EXCLUDED.value, updated_at = NOW(), updated_by = EXCLUDED.updated_by
+        "#,
+    )
+    .bind(mfa::MFA_REQUIRED_FOR_DELETE)
+    .bind(policy.required_for_delete.to_string())
+    .bind(admin_id)
+    .execute(db)
+    .await?;
+
+    Ok(policy)
+}
+
+/// Rotates a refresh token. The presented token is marked used and a new one
+/// is issued in the same session; presenting an already used token means it
+/// was copied, so the whole session is revoked.
+pub async fn refresh(db: &Db, req: RefreshRequest, client: &ClientInfo) -> Result<AuthResponse> {
+    validate_token(&req.refresh_token)
+        .filter(|c| c.scope.as_deref() == Some(REFRESH_SCOPE))
+        .ok_or(AuthServiceError::InvalidToken)?;
+
+    let mut tx = db.begin().await?;
+
+    let token_row = sqlx::query_as::<_, RefreshToken>(
+        "SELECT * FROM refresh_tokens WHERE token = $1 FOR UPDATE",
+    )
+    .bind(&req.refresh_token)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AuthServiceError::InvalidToken)?;
+
+    let session = sqlx::query_as::<_, UserSession>(
+        "SELECT * FROM user_sessions WHERE id = $1 FOR UPDATE",
+    )
+    .bind(token_row.session_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if session.revoked_at.is_some() {
+        return Err(AuthServiceError::InvalidToken.into());
+    }
+
+    if token_row.used_at.is_some() {
+        revoke_sessions(&mut *tx, session.user_id, Some(session.id), "token_reuse").await?;
+        tx.commit().await?;
+        tracing::warn!(
+            "Refresh token reuse detected for user {}, session {} revoked",
+            session.user_id,
+            session.id
+        );
+        return Err(AuthServiceError::TokenReuse.into());
+    }
 
     if token_row.expires_at < Utc::now().naive_utc() {
-        anyhow::bail!("Token expired");
+        return Err(AuthServiceError::InvalidToken.into());
     }
 
-    let access_token = generate_access_token(token_row.user_id);
-    let new_refresh_token = generate_refresh_token(token_row.user_id);
-    let expires = (Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)).naive_utc();
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(token_row.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    ensure_email_verified(&user)?;
 
-    sqlx::query("UPDATE refresh_tokens SET token = $1, expires_at = $2 WHERE id = $3")
-        .bind(&new_refresh_token)
-        .bind(expires)
+    sqlx::query("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1")
         .bind(token_row.id)
-        .execute(db)
+        .execute(&mut *tx)
         .await?;
 
-    Ok(AuthResponse {
-        access_token,
-        refresh_token: new_refresh_token,
-    })
+    sqlx::query(
+        r#"
+        UPDATE user_sessions
+        SET last_used_at = NOW(),
+            user_agent = COALESCE($2, user_agent),
+            ip_address = COALESCE($3, ip_address)
+        WHERE id = $1
+        "#,
+    )
+    .bind(session.id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .execute(&mut *tx)
+    .await?;
+
+    let tokens = issue_tokens(&mut tx, user.id, session.id).await?;
+    tx.commit().await?;
+
+    Ok(tokens)
+}
+
+pub async fn logout(db: &Db, user_id: Uuid, session_id: Uuid) -> Result<()> {
+    revoke_sessions(db, user_id, Some(session_id), "logout").await?;
+    Ok(())
+}
+
+pub async fn logout_all(db: &Db, user_id: Uuid) -> Result<u64> {
+    revoke_sessions(db, user_id, None, "logout_all").await
+}
+
+pub async fn list_sessions(db: &Db, user_id: Uuid) -> Result<Vec<UserSession>> {
+    let sessions = sqlx::query_as::<_, UserSession>(
+        r#"
+        SELECT * FROM user_sessions
+        WHERE user_id = $1
+          AND revoked_at IS NULL
+          AND EXISTS (
+            SELECT 1 FROM refresh_tokens rt
+            WHERE rt.session_id = user_sessions.id AND rt.expires_at > NOW()
+          )
+        ORDER BY last_used_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Revokes one of the user's sessions. Returns false if it doesn't exist or
+/// is already revoked.
+pub async fn revoke_session(db: &Db, user_id: Uuid, session_id: Uuid) -> Result<bool> {
+    let revoked = revoke_sessions(db, user_id, Some(session_id), "revoked").await?;
+    Ok(revoked > 0)
 }
 
 pub async fn forgot_password(db: &Db, req: ForgotPasswordRequest) -> Result<()> {
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
         .bind(&req.email)
-        .fetch_one(db)
+        .fetch_optional(db)
         .await?;
 
+    // Don't reveal whether the address belongs to an account. Directory users
+    // reset their password in the directory and invited users accept their
+    // invitation instead.
+    let Some(user) = user.filter(|u| {
+        u.ldap_dn.is_none() && (u.invited_at.is_none() || u.invitation_accepted_at.is_some())
+    }) else {
+        return Ok(());
+    };
+
     let token = generate_verification_token();
     let token_exp = token_expiry_time();
+    let mut tx = db.begin().await?;
 
     sqlx::query(
-        "INSERT INTO email_verification_tokens (id, user_id, token, expires_at) VALUES ($1,$2,$3,$4)",
+        "INSERT INTO email_verification_tokens (id, user_id, token, expires_at, purpose) VALUES ($1,$2,$3,$4,$5)",
     )
     .bind(Uuid::new_v4())
     .bind(user.id)
     .bind(&token)
     .bind(token_exp)
-    .execute(db)
+    .bind(TokenPurpose::ResetPassword.as_str())
+    .execute(&mut *tx)
+    .await?;
+
+    let email = EmailTemplate::PasswordReset { token: &token }.render(&req.email);
+    outbox::enqueue(&mut *tx, &email).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Marks the token used and returns its owner. Fails for tokens that are
+// unknown, expired, already used or issued for a different purpose.
+async fn consume_token(conn: &mut PgConnection, token: &str, purpose: TokenPurpose) -> Result<Uuid> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE email_verification_tokens
+        SET used_at = NOW()
+        WHERE token = $1
+          AND purpose = $2
+          AND used_at IS NULL
+          AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(token)
+    .bind(purpose.as_str())
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AuthServiceError::InvalidToken.into())
+}
+
+pub async fn verify_email(db: &Db, req: VerifyEmailRequest) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let user_id = consume_token(&mut tx, &req.token, TokenPurpose::VerifyEmail).await?;
+
+    sqlx::query("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn reset_password(db: &Db, req: ResetPasswordRequest) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let user_id = consume_token(&mut tx, &req.token, TokenPurpose::ResetPassword).await?;
+
+    // A rejected password rolls back, leaving the token usable for another try
+    let user_name = sqlx::query_scalar::<_, String>("SELECT user_name FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    password_policy::set_password(&mut tx, user_id, &user_name, &req.new_password).await?;
+
+    // Any other outstanding reset links are now stale
+    sqlx::query(
+        "UPDATE email_verification_tokens SET used_at = NOW() WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(TokenPurpose::ResetPassword.as_str())
+    .execute(&mut *tx)
     .await?;
 
-    send_email_stub(&req.email, &token);
+    // Sign out every existing session after a reset
+    revoke_sessions(&mut *tx, user_id, None, "password_reset").await?;
+
+    tx.commit().await?;
     Ok(())
 }
 
+pub async fn get_invitation(db: &Db, token: &str) -> Result<InvitationResponse> {
+    sqlx::query_as::<_, InvitationResponse>(
+        r#"
+        SELECT u.email, u.user_name, p.first_name, p.last_name, t.expires_at
+        FROM email_verification_tokens t
+        JOIN users u ON u.id = t.user_id
+        LEFT JOIN persons p ON p.id = u.person_id
+        WHERE t.token = $1
+          AND t.purpose = $2
+          AND t.used_at IS NULL
+          AND t.expires_at > NOW()
+          AND u.invitation_accepted_at IS NULL
+        "#,
+    )
+    .bind(token)
+    .bind(TokenPurpose::Invitation.as_str())
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AuthServiceError::InvalidToken.into())
+}
+
+/// Completes an invitation: the invitee's username and password are set, the
+/// address the link was sent to counts as verified, and they are signed in.
+pub async fn accept_invitation(
+    db: &Db,
+    req: AcceptInvitationRequest,
+    client: &ClientInfo,
+) -> Result<AuthResponse> {
+    let user_name = req.user_name.trim();
+    if user_name.is_empty() {
+        return Err(AuthServiceError::UserNameRequired.into());
+    }
+
+    // Any failure rolls back, leaving the link usable for another try
+    let mut tx = db.begin().await?;
+    let user_id = consume_token(&mut tx, &req.token, TokenPurpose::Invitation).await?;
+
+    let taken = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE user_name = $1 AND id != $2)",
+    )
+    .bind(user_name)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if taken {
+        return Err(AuthServiceError::UserNameTaken.into());
+    }
+
+    let accepted = sqlx::query(
+        r#"
+        UPDATE users
+        SET user_name = $1,
+            invitation_accepted_at = NOW(),
+            email_verified_at = COALESCE(email_verified_at, NOW())
+        WHERE id = $2 AND invitation_accepted_at IS NULL
+        "#,
+    )
+    .bind(user_name)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    if accepted.rows_affected() == 0 {
+        return Err(AuthServiceError::InvalidToken.into());
+    }
+
+    password_policy::set_password(&mut tx, user_id, user_name, &req.password).await?;
+    let tokens = start_session(&mut tx, user_id, client).await?;
+
+    tx.commit().await?;
+    Ok(tokens)
+}
+
 pub async fn change_password(db: &Db, user_id: Uuid, req: ChangePasswordRequest) -> Result<()> {
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
@@ -174,19 +1167,183 @@ pub async fn change_password(db: &Db, user_id: Uuid, req: ChangePasswordRequest)
         .await?;
 
     verify_password(&user.password_hash, &req.current_password)?;
-    let new_hash = hash_password(&req.new_password)?;
-    sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
-        .bind(new_hash)
+
+    let mut tx = db.begin().await?;
+    password_policy::set_password(&mut tx, user_id, &user.user_name, &req.new_password).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_profile(
+    db: &Db,
+    user_id: Uuid,
+    impersonation: Option<&Impersonation>,
+) -> Result<ProfileResponse> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_id)
+        .fetch_one(db)
+        .await?;
+
+    let impersonated_by = match impersonation {
+        Some(impersonation) => {
+            let admin_user_name =
+                sqlx::query_scalar::<_, String>("SELECT user_name FROM users WHERE id = $1")
+                    .bind(impersonation.admin_id)
+                    .fetch_one(db)
+                    .await?;
+            Some(ImpersonatorResponse {
+                admin_id: impersonation.admin_id,
+                admin_user_name,
+                expires_at: impersonation.expires_at,
+            })
+        }
+        None => None,
+    };
+
+    Ok(ProfileResponse {
+        user,
+        impersonating: impersonated_by.is_some(),
+        impersonated_by,
+    })
+}
+
+/// Starts an impersonation of `req.user_id` by `admin` from their session
+/// `admin_session_id`. Returns `None` if the user doesn't exist. Admins and
+/// service accounts can't be impersonated.
+pub async fn start_impersonation(
+    db: &Db,
+    admin: &User,
+    admin_session_id: Uuid,
+    req: StartImpersonationRequest,
+    client: &ClientInfo,
+) -> Result<Option<ImpersonationResponse>> {
+    let reason = req.reason.trim();
+    if reason.is_empty() {
+        return Err(AuthServiceError::ImpersonationReasonRequired.into());
+    }
+
+    let target = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(req.user_id)
+        .fetch_optional(db)
+        .await?;
+    let Some(target) = target else {
+        return Ok(None);
+    };
+    if target.id == admin.id || target.is_admin || target.is_service_account {
+        return Err(AuthServiceError::CannotImpersonate.into());
+    }
+
+    let expires = (Utc::now() + Duration::minutes(IMPERSONATION_TTL_MINUTES)).naive_utc();
+    let impersonation = sqlx::query_as::<_, Impersonation>(
+        r#"
+        INSERT INTO impersonations (admin_id, user_id, admin_session_id, reason, ip_address, user_agent, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(admin.id)
+    .bind(target.id)
+    .bind(admin_session_id)
+    .bind(reason)
+    .bind(&client.ip_address)
+    .bind(&client.user_agent)
+    .bind(expires)
+    .fetch_one(db)
+    .await?;
+
+    Ok(Some(ImpersonationResponse {
+        access_token: generate_impersonation_token(
+            target.id,
+            admin.id,
+            admin_session_id,
+            impersonation.id,
+        ),
+        impersonation,
+    }))
+}
+
+pub async fn end_impersonation(db: &Db, impersonation_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE impersonations SET ended_at = NOW() WHERE id = $1 AND ended_at IS NULL")
+        .bind(impersonation_id)
         .execute(db)
         .await?;
     Ok(())
 }
 
-pub async fn get_profile(db: &Db, user_id: Uuid) -> Result<User> {
-    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(user_id)
+pub async fn list_impersonations(db: &Db) -> Result<Vec<Impersonation>> {
+    let impersonations = sqlx::query_as::<_, Impersonation>(
+        "SELECT * FROM impersonations ORDER BY started_at DESC LIMIT 100",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(impersonations)
+}
+
+pub async fn list_impersonation_requests(
+    db: &Db,
+    impersonation_id: Uuid,
+) -> Result<Vec<ImpersonationRequest>> {
+    let requests = sqlx::query_as::<_, ImpersonationRequest>(
+        "SELECT * FROM impersonation_requests WHERE impersonation_id = $1 ORDER BY created_at",
+    )
+    .bind(impersonation_id)
+    .fetch_all(db)
+    .await?;
+    Ok(requests)
+}
+
+pub async fn list_ldap_group_mappings(db: &Db) -> Result<Vec<LdapGroupMapping>> {
+    let mappings = sqlx::query_as::<_, LdapGroupMapping>(
+        "SELECT * FROM ldap_group_mappings ORDER BY priority DESC, group_dn",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(mappings)
+}
+
+pub async fn create_ldap_group_mapping(
+    db: &Db,
+    req: CreateLdapGroupMappingRequest,
+) -> Result<LdapGroupMapping> {
+    let mapping = sqlx::query_as::<_, LdapGroupMapping>(
+        r#"
+        INSERT INTO ldap_group_mappings (group_dn, department_id, position_id, priority)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(req.group_dn.trim().to_lowercase())
+    .bind(req.department_id)
+    .bind(req.position_id)
+    .bind(req.priority.unwrap_or(0))
+    .fetch_one(db)
+    .await?;
+    Ok(mapping)
+}
+
+pub async fn delete_ldap_group_mapping(db: &Db, id: Uuid) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM ldap_group_mappings WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Runs a directory sync now and returns its record.
+pub async fn run_ldap_sync(db: &Db) -> Result<LdapSyncRun> {
+    let run_id = ldap::sync(db).await?;
+    let run = sqlx::query_as::<_, LdapSyncRun>("SELECT * FROM ldap_sync_runs WHERE id = $1")
+        .bind(run_id)
         .fetch_one(db)
-        .await
-        .map_err(Into::into)
+        .await?;
+    Ok(run)
+}
+
+pub async fn list_ldap_sync_runs(db: &Db) -> Result<Vec<LdapSyncRun>> {
+    let runs = sqlx::query_as::<_, LdapSyncRun>(
+        "SELECT * FROM ldap_sync_runs ORDER BY started_at DESC LIMIT 20",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(runs)
 }
@@ -0,0 +1,375 @@
+use std::{env, sync::OnceLock};
+
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{db::Db, extractors::ClientInfo};
+
+/// Limits on failed logins, read once from the environment.
+///
+/// - `LOGIN_MAX_FAILURES`: failures per username before it is locked (default 5)
+/// - `LOGIN_LOCKOUT_MINUTES`: how long a lockout lasts (default 15)
+/// - `LOGIN_BACKOFF_BASE_SECONDS` / `LOGIN_BACKOFF_MAX_SECONDS`: delay after
+///   the first failure, doubled after each further one up to the maximum
+///   (defaults 1 and 60)
+/// - `LOGIN_IP_MAX_FAILURES`: failures from one IP address, across all
+///   usernames, before the address is locked (default 50)
+/// - `LOGIN_FAILURE_WINDOW_MINUTES`: failures older than this are forgotten
+///   (default 15)
+#[derive(Debug, Clone)]
+pub struct ThrottleConfig {
+    pub max_failures: i32,
+    pub lockout_minutes: i64,
+    pub backoff_base_seconds: i64,
+    pub backoff_max_seconds: i64,
+    pub ip_max_failures: i32,
+    pub failure_window_minutes: i64,
+}
+
+static CONFIG: OnceLock<ThrottleConfig> = OnceLock::new();
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+pub fn config() -> &'static ThrottleConfig {
+    CONFIG.get_or_init(|| ThrottleConfig {
+        max_failures: env_or("LOGIN_MAX_FAILURES", 5),
+        lockout_minutes: env_or("LOGIN_LOCKOUT_MINUTES", 15),
+        backoff_base_seconds: env_or("LOGIN_BACKOFF_BASE_SECONDS", 1),
+        backoff_max_seconds: env_or("LOGIN_BACKOFF_MAX_SECONDS", 60),
+        ip_max_failures: env_or("LOGIN_IP_MAX_FAILURES", 50),
+        failure_window_minutes: env_or("LOGIN_FAILURE_WINDOW_MINUTES", 15),
+    })
+}
+
+/// A `login_throttles` counter. Usernames are counted whether or not the
+/// account exists, so throttling doesn't reveal which usernames are valid.
+#[derive(Debug, Clone)]
+pub enum ThrottleKey {
+    User(String),
+    Ip(String),
+}
+
+impl ThrottleKey {
+    pub fn user(user_name: &str) -> Self {
+        ThrottleKey::User(user_name.trim().to_lowercase())
+    }
+
+    fn key(&self) -> String {
+        match self {
+            ThrottleKey::User(name) => format!("user:{}", name),
+            ThrottleKey::Ip(ip) => format!("ip:{}", ip),
+        }
+    }
+
+    fn max_failures(&self) -> i32 {
+        match self {
+            ThrottleKey::User(_) => config().max_failures,
+            ThrottleKey::Ip(_) => config().ip_max_failures,
+        }
+    }
+
+    // Shared addresses (offices behind NAT) only get the hard lockout, not
+    // the per-failure delay
+    fn uses_backoff(&self) -> bool {
+        matches!(self, ThrottleKey::User(_))
+    }
+}
+
+/// The counters a login attempt for `user_name` from `client` is charged to.
+pub fn login_keys(user_name: &str, client: &ClientInfo) -> Vec<ThrottleKey> {
+    let mut keys = vec![ThrottleKey::user(user_name)];
+    if let Some(ip) = &client.ip_address {
+        keys.push(ThrottleKey::Ip(ip.clone()));
+    }
+    keys
+}
+
+#[derive(sqlx::FromRow)]
+struct ThrottleRow {
+    key: String,
+    failed_count: i32,
+    last_failed_at: chrono::NaiveDateTime,
+    locked_until: Option<chrono::NaiveDateTime>,
+}
+
+/// Seconds a key has to wait before its next attempt, 0 when it may try now.
+fn wait_seconds(
+    row: &ThrottleRow,
+    uses_backoff: bool,
+    now: chrono::NaiveDateTime,
+    config: &ThrottleConfig,
+) -> i64 {
+    if let Some(locked_until) = row.locked_until
+        && locked_until > now
+    {
+        return (locked_until - now).num_seconds() + 1;
+    }
+
+    let expired =
+        now - row.last_failed_at > chrono::Duration::minutes(config.failure_window_minutes);
+    if !uses_backoff || expired || row.failed_count < 1 {
+        return 0;
+    }
+
+    let delay = config
+        .backoff_base_seconds
+        .saturating_mul(1_i64 << (row.failed_count - 1).min(30))
+        .min(config.backoff_max_seconds);
+    (row.last_failed_at + chrono::Duration::seconds(delay) - now)
+        .num_seconds()
+        .max(0)
+}
+
+/// Charges a login attempt to each key before the credentials are checked,
+/// locking the keys that reach their limit, or returns the seconds to wait
+/// instead if any key is locked out or inside its backoff delay. Checking and
+/// counting happen under the rows' locks, so concurrent attempts can't all
+/// pass the check before any of them is counted. Call `forgive` once the
+/// credentials turn out to be right.
+pub async fn begin_attempt(db: &Db, keys: &[ThrottleKey]) -> Result<Option<i64>> {
+    let config = config();
+    let mut tx = db.begin().await?;
+
+    // Always lock in key order so concurrent attempts can't deadlock
+    let mut names: Vec<String> = keys.iter().map(ThrottleKey::key).collect();
+    names.sort();
+    names.dedup();
+    sqlx::query(
+        r#"
+        INSERT INTO login_throttles (key, failed_count)
+        SELECT unnest($1::TEXT[]), 0
+        ON CONFLICT (key) DO NOTHING
+        "#,
+    )
+    .bind(&names)
+    .execute(&mut *tx)
+    .await?;
+
+    let rows = sqlx::query_as::<_, ThrottleRow>(
+        r#"
+        SELECT key, failed_count, last_failed_at, locked_until FROM login_throttles
+        WHERE key = ANY($1)
+        ORDER BY key
+        FOR UPDATE
+        "#,
+    )
+    .bind(&names)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let now = Utc::now().naive_utc();
+    let wait = rows
+        .iter()
+        .map(|row| {
+            let uses_backoff = keys
+                .iter()
+                .find(|k| k.key() == row.key)
+                .is_some_and(ThrottleKey::uses_backoff);
+            wait_seconds(row, uses_backoff, now, config)
+        })
+        .max()
+        .unwrap_or(0);
+    if wait > 0 {
+        tx.rollback().await?;
+        return Ok(Some(wait));
+    }
+
+    for key in keys {
+        // Start counting again once the window has passed or a lockout ended
+        sqlx::query(
+            r#"
+            UPDATE login_throttles
+            SET failed_count = CASE
+                    WHEN last_failed_at < NOW() - make_interval(mins => $2)
+                      OR locked_until <= NOW()
+                    THEN 1
+                    ELSE failed_count + 1
+                END,
+                last_failed_at = NOW(),
+                locked_until = CASE
+                    WHEN locked_until <= NOW() THEN NULL
+                    ELSE locked_until
+                END
+            WHERE key = $1
+            "#,
+        )
+        .bind(key.key())
+        .bind(config.failure_window_minutes as i32)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE login_throttles
+            SET locked_until = NOW() + make_interval(mins => $3)
+            WHERE key = $1 AND failed_count >= $2 AND locked_until IS NULL
+            "#,
+        )
+        .bind(key.key())
+        .bind(key.max_failures())
+        .bind(config.lockout_minutes as i32)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(None)
+}
+
+/// Takes back the failure `begin_attempt` charged to an attempt whose
+/// credentials were right, lifting the lockout it may have set.
+pub async fn forgive(db: &Db, keys: &[ThrottleKey]) -> Result<()> {
+    for key in keys {
+        sqlx::query(
+            r#"
+            UPDATE login_throttles
+            SET failed_count = GREATEST(failed_count - 1, 0),
+                locked_until = CASE WHEN failed_count - 1 < $2 THEN NULL ELSE locked_until END
+            WHERE key = $1
+            "#,
+        )
+        .bind(key.key())
+        .bind(key.max_failures())
+        .execute(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Clears the counter, e.g. after a successful login or an admin unlock.
+pub async fn reset<'e, E: PgExecutor<'e>>(db: E, key: &ThrottleKey) -> Result<()> {
+    sqlx::query("DELETE FROM login_throttles WHERE key = $1")
+        .bind(key.key())
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// How a login attempt ended, as stored in `login_attempts.outcome`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginOutcome {
+    Success,
+    MfaRequired,
+    UnknownUser,
+    InvalidPassword,
+    InvalidMfaCode,
+    EmailNotVerified,
+    Throttled,
+}
+
+impl LoginOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginOutcome::Success => "success",
+            LoginOutcome::MfaRequired => "mfa_required",
+            LoginOutcome::UnknownUser => "unknown_user",
+            LoginOutcome::InvalidPassword => "invalid_password",
+            LoginOutcome::InvalidMfaCode => "invalid_mfa_code",
+            LoginOutcome::EmailNotVerified => "email_not_verified",
+            LoginOutcome::Throttled => "throttled",
+        }
+    }
+}
+
+/// Adds an entry to the `login_attempts` history.
+pub async fn record_attempt(
+    db: &Db,
+    user_name: &str,
+    user_id: Option<Uuid>,
+    client: &ClientInfo,
+    outcome: LoginOutcome,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO login_attempts (user_name, user_id, ip_address, user_agent, success, outcome)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(user_name)
+    .bind(user_id)
+    .bind(&client.ip_address)
+    .bind(&client.user_agent)
+    .bind(outcome == LoginOutcome::Success)
+    .bind(outcome.as_str())
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDateTime};
+
+    fn test_config() -> ThrottleConfig {
+        ThrottleConfig {
+            max_failures: 5,
+            lockout_minutes: 15,
+            backoff_base_seconds: 1,
+            backoff_max_seconds: 60,
+            ip_max_failures: 50,
+            failure_window_minutes: 15,
+        }
+    }
+
+    fn row(failed_count: i32, last_failed_at: NaiveDateTime, locked_until: Option<NaiveDateTime>) -> ThrottleRow {
+        ThrottleRow {
+            key: "user:alice".to_string(),
+            failed_count,
+            last_failed_at,
+            locked_until,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_per_failure_up_to_the_maximum() {
+        let config = test_config();
+        let now = Utc::now().naive_utc();
+        assert_eq!(wait_seconds(&row(0, now, None), true, now, &config), 0);
+        assert_eq!(wait_seconds(&row(1, now, None), true, now, &config), 1);
+        assert_eq!(wait_seconds(&row(3, now, None), true, now, &config), 4);
+        assert_eq!(wait_seconds(&row(20, now, None), true, now, &config), 60);
+    }
+
+    #[test]
+    fn backoff_elapses_and_is_skipped_for_shared_keys() {
+        let config = test_config();
+        let now = Utc::now().naive_utc();
+        assert_eq!(wait_seconds(&row(3, now - Duration::seconds(10), None), true, now, &config), 0);
+        assert_eq!(wait_seconds(&row(3, now, None), false, now, &config), 0);
+        assert_eq!(
+            wait_seconds(&row(3, now - Duration::minutes(20), None), true, now, &config),
+            0
+        );
+    }
+
+    #[test]
+    fn lockout_applies_to_every_key() {
+        let config = test_config();
+        let now = Utc::now().naive_utc();
+        let locked = row(5, now, Some(now + Duration::minutes(10)));
+        assert!(wait_seconds(&locked, false, now, &config) > 9 * 60);
+        let ended = row(5, now - Duration::minutes(20), Some(now - Duration::minutes(5)));
+        assert_eq!(wait_seconds(&ended, true, now, &config), 0);
+    }
+
+    #[test]
+    fn user_keys_are_normalized() {
+        let keys = login_keys(
+            "  Alice ",
+            &ClientInfo {
+                ip_address: Some("198.51.100.7".to_string()),
+                user_agent: None,
+            },
+        );
+        let names: Vec<String> = keys.iter().map(ThrottleKey::key).collect();
+        assert_eq!(names, vec!["user:alice", "ip:198.51.100.7"]);
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The file itself is the request body, with its content type.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDocumentQuery {
+    // Category code, e.g. "visa"
+    pub category: String,
+    pub file_name: String,
+    // The file name when not given
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub expires_on: Option<NaiveDate>,
+}
+
+/// A new file for an existing document, e.g. a renewed visa.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDocumentVersionQuery {
+    pub file_name: String,
+    // Becomes the document's expiry date
+    pub expires_on: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDocumentRequest {
+    pub category: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // Corrects the expiry date of the current version
+    pub expires_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDocumentsQuery {
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentResponse {
+    pub id: Uuid,
+    // "employee", "intern" or "person"
+    pub owner_type: String,
+    pub owner_id: Uuid,
+    pub category: String,
+    pub category_name: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub current_version: i32,
+    pub expires_on: Option<NaiveDate>,
+    pub expired: bool,
+    // Of the current version
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentVersionResponse {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    pub version: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub expires_on: Option<NaiveDate>,
+    pub note: Option<String>,
+    pub uploaded_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiringDocumentsQuery {
+    // Days ahead to look; each category's warning period when not given
+    pub days: Option<i32>,
+    pub category: Option<String>,
+    // Whether to list documents that have already expired, true by default
+    pub include_expired: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiringDocumentResponse {
+    pub id: Uuid,
+    pub owner_type: String,
+    pub owner_id: Uuid,
+    pub owner_name: Option<String>,
+    pub category: String,
+    pub category_name: String,
+    pub title: String,
+    pub expires_on: NaiveDate,
+    // Negative once expired
+    pub days_until_expiry: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDocumentCategoryRequest {
+    pub code: String,
+    pub name: String,
+    pub requires_expiry: Option<bool>,
+    pub expiry_warning_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDocumentCategoryRequest {
+    pub name: Option<String>,
+    pub requires_expiry: Option<bool>,
+    pub expiry_warning_days: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCategoryResponse {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub requires_expiry: bool,
+    pub expiry_warning_days: i32,
+    pub is_active: bool,
+}
@@ -0,0 +1,323 @@
+use std::sync::Arc;
+
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        document::{
+            dto::{
+                CreateDocumentCategoryRequest, ExpiringDocumentsQuery, ListDocumentsQuery,
+                UpdateDocumentCategoryRequest, UpdateDocumentRequest, UploadDocumentQuery,
+                UploadDocumentVersionQuery,
+            },
+            service::{self, DocumentOwner, Upload},
+        },
+    },
+    db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    storage::Storage,
+};
+use axum::{
+    body::Bytes,
+    extract::{Extension, FromRequestParts, Path, Query, RawPathParams},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+/// What a document handler works on: the owner from the `{id}` the routes
+/// are nested under, checked to exist and, when they are or are linked to an
+/// employee, to be within the caller's row scope, or 404.
+pub struct DocumentContext {
+    pub db: Db,
+    pub storage: Arc<dyn Storage>,
+    pub owner: DocumentOwner,
+    pub owner_id: Uuid,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for DocumentContext {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let (Some(db), Some(storage), Some(owner), Some(scope)) = (
+            parts.extensions.get::<Db>().cloned(),
+            parts.extensions.get::<Arc<dyn Storage>>().cloned(),
+            parts.extensions.get::<DocumentOwner>().copied(),
+            parts.extensions.get::<RowScope>().copied(),
+        ) else {
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        };
+        let params = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+        let owner_id = params
+            .iter()
+            .find(|(name, _)| *name == "id")
+            .and_then(|(_, value)| value.parse::<Uuid>().ok())
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+        let employee_ids = service::owner_employee_ids(&db, owner, owner_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        for employee_id in employee_ids {
+            if !scope
+                .includes(&db, employee_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            {
+                return Err(StatusCode::NOT_FOUND);
+            }
+        }
+        if !service::owner_exists(&db, owner, owner_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        Ok(DocumentContext {
+            db,
+            storage,
+            owner,
+            owner_id,
+        })
+    }
+}
+
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+}
+
+pub async fn list_documents_handler(
+    doc: DocumentContext,
+    Query(query): Query<ListDocumentsQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let documents = service::list_documents(&doc.db, doc.owner, doc.owner_id, query)
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing documents: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::OK, Json(json!(documents))))
+}
+
+/// Takes the file as the body, with its content type, and the rest in the
+/// query string.
+pub async fn upload_document_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Query(query): Query<UploadDocumentQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let upload = Upload {
+        content_type: content_type(&headers),
+        data: &body,
+        ctx: &ctx,
+    };
+    match service::upload_document(&doc.db, doc.storage.as_ref(), doc.owner, doc.owner_id, query, upload).await {
+        Ok(document) => Ok((StatusCode::CREATED, Json(json!(document)))),
+        Err(e) => {
+            eprintln!("Error uploading document: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn get_document_handler(
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let document = service::get_document(&doc.db, doc.owner, doc.owner_id, document_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((StatusCode::OK, Json(json!(document))))
+}
+
+pub async fn update_document_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateDocumentRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if service::get_document(&doc.db, doc.owner, doc.owner_id, document_id).await.is_err() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    match service::update_document(&doc.db, &ctx, doc.owner, doc.owner_id, document_id, payload).await {
+        Ok(document) => Ok((StatusCode::OK, Json(json!(document)))),
+        Err(e) => {
+            eprintln!("Error updating document: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn delete_document_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::delete_document(&doc.db, &ctx, doc.storage.as_ref(), doc.owner, doc.owner_id, document_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Document deleted successfully"})),
+    ))
+}
+
+pub async fn list_document_versions_handler(
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let versions = service::list_versions(&doc.db, doc.owner, doc.owner_id, document_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if versions.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok((StatusCode::OK, Json(json!(versions))))
+}
+
+pub async fn upload_document_version_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<UploadDocumentVersionQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if service::get_document(&doc.db, doc.owner, doc.owner_id, document_id).await.is_err() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let upload = Upload {
+        content_type: content_type(&headers),
+        data: &body,
+        ctx: &ctx,
+    };
+    match service::upload_version(&doc.db, doc.storage.as_ref(), doc.owner, doc.owner_id, document_id, query, upload)
+        .await
+    {
+        Ok(version) => Ok((StatusCode::CREATED, Json(json!(version)))),
+        Err(e) => {
+            eprintln!("Error uploading document version: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+async fn download(
+    ctx: &AuditContext,
+    doc: &DocumentContext,
+    document_id: Uuid,
+    version: Option<i32>,
+) -> Result<Response, StatusCode> {
+    let (version, data) = service::download_version(
+        &doc.db,
+        doc.storage.as_ref(),
+        doc.owner,
+        doc.owner_id,
+        document_id,
+        version,
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Error downloading document: {}", e);
+        StatusCode::NOT_FOUND
+    })?;
+    audit::record(
+        &doc.db,
+        ctx,
+        AuditEntry::new("document", "download", Some(document_id))
+            .after(&json!({ "version": version.version })),
+    )
+    .await?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, version.content_type),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", version.file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// The current version's file.
+pub async fn download_document_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Path((_, document_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, StatusCode> {
+    download(&ctx, &doc, document_id, None).await
+}
+
+pub async fn download_document_version_handler(
+    ctx: AuditContext,
+    doc: DocumentContext,
+    Path((_, document_id, version)): Path<(Uuid, Uuid, i32)>,
+) -> Result<Response, StatusCode> {
+    download(&ctx, &doc, document_id, Some(version)).await
+}
+
+pub async fn list_expiring_documents_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Extension(owner): Extension<DocumentOwner>,
+    Query(query): Query<ExpiringDocumentsQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match service::list_expiring(&db, scope, owner, query).await {
+        Ok(documents) => Ok((StatusCode::OK, Json(json!(documents)))),
+        Err(e) => {
+            eprintln!("Error listing expiring documents: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn list_document_categories_handler(
+    Extension(db): Extension<Db>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let categories = service::list_categories(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!(categories))))
+}
+
+pub async fn create_document_category_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<CreateDocumentCategoryRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match service::create_category(&db, &ctx, payload).await {
+        Ok(category) => Ok((StatusCode::CREATED, Json(json!(category)))),
+        Err(e) => {
+            eprintln!("Error creating document category: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn update_document_category_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateDocumentCategoryRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match service::update_category(&db, &ctx, id, payload).await {
+        Ok(category) => Ok((StatusCode::OK, Json(json!(category)))),
+        Err(e) => {
+            eprintln!("Error updating document category: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
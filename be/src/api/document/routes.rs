@@ -0,0 +1,36 @@
+use crate::api::document::{handlers, service::{max_upload_bytes, DocumentOwner}};
+use axum::{extract::DefaultBodyLimit, routing::{delete, get, post, put}, Extension, Router};
+
+/// Documents of one employee, intern or person, nested at `/{id}/documents`
+/// in the owner's routes so the owner's permissions apply.
+pub fn document_routes(owner: DocumentOwner) -> Router {
+    Router::new()
+        .route("/", get(handlers::list_documents_handler))
+        .route("/", post(handlers::upload_document_handler))
+        .route("/{document_id}", get(handlers::get_document_handler))
+        .route("/{document_id}", put(handlers::update_document_handler))
+        .route("/{document_id}", delete(handlers::delete_document_handler))
+        .route("/{document_id}/download", get(handlers::download_document_handler))
+        .route("/{document_id}/versions", get(handlers::list_document_versions_handler))
+        .route("/{document_id}/versions", post(handlers::upload_document_version_handler))
+        .route(
+            "/{document_id}/versions/{version}/download",
+            get(handlers::download_document_version_handler),
+        )
+        .layer(DefaultBodyLimit::max(max_upload_bytes()))
+        .layer(Extension(owner))
+}
+
+/// Reports across every owner of a kind, nested at `/documents`.
+pub fn document_report_routes(owner: DocumentOwner) -> Router {
+    Router::new()
+        .route("/expiring", get(handlers::list_expiring_documents_handler))
+        .layer(Extension(owner))
+}
+
+pub fn document_category_routes() -> Router {
+    Router::new()
+        .route("/", get(handlers::list_document_categories_handler))
+        .route("/", post(handlers::create_document_category_handler))
+        .route("/{id}", put(handlers::update_document_category_handler))
+}
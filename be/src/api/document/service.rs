@@ -0,0 +1,839 @@
+use std::env;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        document::dto::{
+            CreateDocumentCategoryRequest, DocumentCategoryResponse, DocumentResponse,
+            DocumentVersionResponse, ExpiringDocumentResponse, ExpiringDocumentsQuery,
+            ListDocumentsQuery, UpdateDocumentCategoryRequest, UpdateDocumentRequest,
+            UploadDocumentQuery, UploadDocumentVersionQuery,
+        },
+        employee::history::today,
+    },
+    db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    models::document::{Document, DocumentCategory, DocumentVersion},
+    storage::Storage,
+};
+
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 20 * 1024 * 1024;
+const MAX_FILE_NAME_LENGTH: usize = 255;
+
+/// What a document is attached to. Document routes are nested under the
+/// owner's routes, so the owner's permissions govern its documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentOwner {
+    Employee,
+    Intern,
+    Person,
+}
+
+impl DocumentOwner {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentOwner::Employee => "employee",
+            DocumentOwner::Intern => "intern",
+            DocumentOwner::Person => "person",
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            DocumentOwner::Employee => "employee_id",
+            DocumentOwner::Intern => "intern_id",
+            DocumentOwner::Person => "person_id",
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            DocumentOwner::Employee => "employees",
+            DocumentOwner::Intern => "interns",
+            DocumentOwner::Person => "persons",
+        }
+    }
+
+    /// Joins the owner's person as `p` to documents `d`.
+    fn person_join(&self) -> &'static str {
+        match self {
+            DocumentOwner::Employee => {
+                "JOIN employees o ON o.id = d.employee_id JOIN persons p ON p.id = o.person_id"
+            }
+            DocumentOwner::Intern => {
+                "JOIN interns o ON o.id = d.intern_id LEFT JOIN persons p ON p.id = o.person_id"
+            }
+            DocumentOwner::Person => "JOIN persons p ON p.id = d.person_id",
+        }
+    }
+}
+
+/// Largest accepted upload, from `DOCUMENT_MAX_BYTES`.
+pub fn max_upload_bytes() -> usize {
+    env::var("DOCUMENT_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+}
+
+pub async fn owner_exists(db: &Db, owner: DocumentOwner, owner_id: Uuid) -> Result<bool> {
+    let exists = sqlx::query_scalar::<_, bool>(&format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)",
+        owner.table()
+    ))
+    .bind(owner_id)
+    .fetch_one(db)
+    .await?;
+    Ok(exists)
+}
+
+/// The employee records of the owner: the employee itself, or those of the
+/// person, or of the intern's person, if they are also an employee.
+pub async fn owner_employee_ids(db: &Db, owner: DocumentOwner, owner_id: Uuid) -> Result<Vec<Uuid>> {
+    let query = match owner {
+        DocumentOwner::Employee => return Ok(vec![owner_id]),
+        DocumentOwner::Person => "SELECT id FROM employees WHERE person_id = $1",
+        DocumentOwner::Intern => {
+            "SELECT e.id FROM employees e JOIN interns i ON i.person_id = e.person_id WHERE i.id = $1"
+        }
+    };
+    let ids = sqlx::query_scalar::<_, Uuid>(query)
+        .bind(owner_id)
+        .fetch_all(db)
+        .await?;
+    Ok(ids)
+}
+
+pub async fn list_categories(db: &Db) -> Result<Vec<DocumentCategoryResponse>> {
+    let categories = sqlx::query_as::<_, DocumentCategory>(
+        "SELECT * FROM document_categories ORDER BY name",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(categories.into_iter().map(map_category_to_response).collect())
+}
+
+pub async fn create_category(
+    db: &Db,
+    ctx: &AuditContext,
+    req: CreateDocumentCategoryRequest,
+) -> Result<DocumentCategoryResponse> {
+    let code = req.code.trim().to_lowercase();
+    if code.is_empty() || req.name.trim().is_empty() {
+        return Err(anyhow!("Code and name are required"));
+    }
+    if req.expiry_warning_days.is_some_and(|d| d < 0) {
+        return Err(anyhow!("expiryWarningDays can't be negative"));
+    }
+
+    let mut tx = db.begin().await?;
+    let category = sqlx::query_as::<_, DocumentCategory>(
+        r#"
+        INSERT INTO document_categories (code, name, requires_expiry, expiry_warning_days)
+        VALUES ($1, $2, COALESCE($3, FALSE), COALESCE($4, 30))
+        RETURNING *
+        "#,
+    )
+    .bind(&code)
+    .bind(req.name.trim())
+    .bind(req.requires_expiry)
+    .bind(req.expiry_warning_days)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref d) if d.is_unique_violation() => {
+            anyhow!("Category {} already exists", code)
+        }
+        e => e.into(),
+    })?;
+    let category = map_category_to_response(category);
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("document_category", "create", Some(category.id)).after(&category),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(category)
+}
+
+pub async fn update_category(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    req: UpdateDocumentCategoryRequest,
+) -> Result<DocumentCategoryResponse> {
+    if req.expiry_warning_days.is_some_and(|d| d < 0) {
+        return Err(anyhow!("expiryWarningDays can't be negative"));
+    }
+
+    let mut tx = db.begin().await?;
+    let category = sqlx::query_as::<_, DocumentCategory>(
+        r#"
+        UPDATE document_categories
+        SET name = COALESCE($2, name),
+            requires_expiry = COALESCE($3, requires_expiry),
+            expiry_warning_days = COALESCE($4, expiry_warning_days),
+            is_active = COALESCE($5, is_active),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(req.name.as_deref().map(str::trim))
+    .bind(req.requires_expiry)
+    .bind(req.expiry_warning_days)
+    .bind(req.is_active)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("Category not found"))?;
+    let category = map_category_to_response(category);
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("document_category", "update", Some(id)).after(&category),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(category)
+}
+
+async fn active_category(conn: &mut PgConnection, code: &str) -> Result<DocumentCategory> {
+    sqlx::query_as::<_, DocumentCategory>(
+        "SELECT * FROM document_categories WHERE code = $1 AND is_active = TRUE",
+    )
+    .bind(code)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| anyhow!("Unknown document category: {}", code))
+}
+
+#[derive(Debug, FromRow)]
+struct DocumentRow {
+    #[sqlx(flatten)]
+    document: Document,
+    category: String,
+    category_name: String,
+    file_name: String,
+    content_type: String,
+    size_bytes: i64,
+}
+
+const SELECT_DOCUMENTS: &str = r#"
+    SELECT d.*, c.code AS category, c.name AS category_name,
+           v.file_name, v.content_type, v.size_bytes
+    FROM documents d
+    JOIN document_categories c ON c.id = d.category_id
+    JOIN document_versions v ON v.document_id = d.id AND v.version = d.current_version
+"#;
+
+pub async fn list_documents(
+    db: &Db,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    query: ListDocumentsQuery,
+) -> Result<Vec<DocumentResponse>> {
+    let rows = sqlx::query_as::<_, DocumentRow>(&format!(
+        "{} WHERE d.{} = $1 AND ($2::TEXT IS NULL OR c.code = $2) ORDER BY c.name, d.title",
+        SELECT_DOCUMENTS,
+        owner.column()
+    ))
+    .bind(owner_id)
+    .bind(query.category)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| map_document_to_response(owner, row))
+        .collect())
+}
+
+pub async fn get_document<'e, E: PgExecutor<'e>>(
+    db: E,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+) -> Result<DocumentResponse> {
+    let row = sqlx::query_as::<_, DocumentRow>(&format!(
+        "{} WHERE d.id = $1 AND d.{} = $2",
+        SELECT_DOCUMENTS,
+        owner.column()
+    ))
+    .bind(document_id)
+    .bind(owner_id)
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| anyhow!("Document not found"))?;
+
+    Ok(map_document_to_response(owner, row))
+}
+
+/// An uploaded file and the request that uploaded it.
+pub struct Upload<'a> {
+    pub content_type: &'a str,
+    pub data: &'a [u8],
+    pub ctx: &'a AuditContext,
+}
+
+/// Stores the file first and then records it, removing the file again if
+/// recording fails, so the database never points at a missing file.
+async fn store_version(
+    conn: &mut PgConnection,
+    storage: &dyn Storage,
+    document_id: Uuid,
+    version: i32,
+    meta: &UploadDocumentVersionQuery,
+    upload: &Upload<'_>,
+) -> Result<DocumentVersion> {
+    if upload.data.is_empty() {
+        return Err(anyhow!("The file is empty"));
+    }
+    let file_name = clean_file_name(&meta.file_name)?;
+    let id = Uuid::new_v4();
+    let storage_key = format!("documents/{}/{}", document_id, id);
+    let sha256: String = Sha256::digest(upload.data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    storage
+        .put(&storage_key, upload.data, upload.content_type)
+        .await?;
+
+    let inserted = sqlx::query_as::<_, DocumentVersion>(
+        r#"
+        INSERT INTO document_versions (
+            id, document_id, version, file_name, content_type, size_bytes,
+            sha256, storage_key, expires_on, note, uploaded_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(document_id)
+    .bind(version)
+    .bind(&file_name)
+    .bind(upload.content_type)
+    .bind(upload.data.len() as i64)
+    .bind(&sha256)
+    .bind(&storage_key)
+    .bind(meta.expires_on)
+    .bind(&meta.note)
+    .bind(upload.ctx.actor_id)
+    .fetch_one(&mut *conn)
+    .await;
+
+    match inserted {
+        Ok(version) => Ok(version),
+        Err(e) => {
+            if let Err(e) = storage.delete(&storage_key).await {
+                tracing::warn!("Failed to remove unrecorded file {}: {}", storage_key, e);
+            }
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn upload_document(
+    db: &Db,
+    storage: &dyn Storage,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    query: UploadDocumentQuery,
+    upload: Upload<'_>,
+) -> Result<DocumentResponse> {
+    let mut tx = db.begin().await?;
+
+    let category = active_category(&mut tx, &query.category).await?;
+    if category.requires_expiry && query.expires_on.is_none() {
+        return Err(anyhow!("{} documents need an expiry date", category.name));
+    }
+    let title = match query.title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_string(),
+        _ => clean_file_name(&query.file_name)?,
+    };
+
+    let document = sqlx::query_as::<_, Document>(&format!(
+        r#"
+        INSERT INTO documents ({}, category_id, title, description, expires_on, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+        owner.column()
+    ))
+    .bind(owner_id)
+    .bind(category.id)
+    .bind(&title)
+    .bind(&query.description)
+    .bind(query.expires_on)
+    .bind(upload.ctx.actor_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let meta = UploadDocumentVersionQuery {
+        file_name: query.file_name,
+        expires_on: query.expires_on,
+        note: None,
+    };
+    let version = store_version(&mut tx, storage, document.id, 1, &meta, &upload).await?;
+
+    let result: Result<DocumentResponse> = async {
+        let document = get_document(&mut *tx, owner, owner_id, document.id).await?;
+        audit::record_in(
+            &mut tx,
+            upload.ctx,
+            AuditEntry::new("document", "upload", Some(document.id)).after(&document),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(document)
+    }
+    .await;
+    if result.is_err() {
+        let _ = storage.delete(&version.storage_key).await;
+    }
+    result
+}
+
+/// Adds a file as the document's new current version, taking its expiry
+/// date. Earlier versions stay downloadable.
+pub async fn upload_version(
+    db: &Db,
+    storage: &dyn Storage,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+    query: UploadDocumentVersionQuery,
+    upload: Upload<'_>,
+) -> Result<DocumentVersionResponse> {
+    let mut tx = db.begin().await?;
+
+    let (current_version, requires_expiry, category_name) =
+        sqlx::query_as::<_, (i32, bool, String)>(&format!(
+            r#"
+            SELECT d.current_version, c.requires_expiry, c.name
+            FROM documents d
+            JOIN document_categories c ON c.id = d.category_id
+            WHERE d.id = $1 AND d.{} = $2
+            FOR UPDATE OF d
+            "#,
+            owner.column()
+        ))
+        .bind(document_id)
+        .bind(owner_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("Document not found"))?;
+
+    if requires_expiry && query.expires_on.is_none() {
+        return Err(anyhow!("{} documents need an expiry date", category_name));
+    }
+    let before = get_document(&mut *tx, owner, owner_id, document_id).await?;
+
+    let version = store_version(
+        &mut tx,
+        storage,
+        document_id,
+        current_version + 1,
+        &query,
+        &upload,
+    )
+    .await?;
+
+    let storage_key = version.storage_key.clone();
+    let result: Result<DocumentVersionResponse> = async {
+        sqlx::query(
+            r#"
+            UPDATE documents
+            SET current_version = $2, expires_on = $3, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(document_id)
+        .bind(version.version)
+        .bind(query.expires_on)
+        .execute(&mut *tx)
+        .await?;
+
+        let version = map_version_to_response(version);
+        audit::record_in(
+            &mut tx,
+            upload.ctx,
+            AuditEntry::new("document", "upload_version", Some(document_id))
+                .before(&before)
+                .after(&version),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(version)
+    }
+    .await;
+    if result.is_err() {
+        let _ = storage.delete(&storage_key).await;
+    }
+    result
+}
+
+/// Newest first.
+pub async fn list_versions(
+    db: &Db,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+) -> Result<Vec<DocumentVersionResponse>> {
+    let versions = sqlx::query_as::<_, DocumentVersion>(&format!(
+        r#"
+        SELECT v.* FROM document_versions v
+        JOIN documents d ON d.id = v.document_id
+        WHERE d.id = $1 AND d.{} = $2
+        ORDER BY v.version DESC
+        "#,
+        owner.column()
+    ))
+    .bind(document_id)
+    .bind(owner_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(versions.into_iter().map(map_version_to_response).collect())
+}
+
+/// A version of the document and its file, the current one when `version`
+/// isn't given.
+pub async fn download_version(
+    db: &Db,
+    storage: &dyn Storage,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+    version: Option<i32>,
+) -> Result<(DocumentVersion, Vec<u8>)> {
+    let version = sqlx::query_as::<_, DocumentVersion>(&format!(
+        r#"
+        SELECT v.* FROM document_versions v
+        JOIN documents d ON d.id = v.document_id
+        WHERE d.id = $1 AND d.{} = $2 AND v.version = COALESCE($3, d.current_version)
+        "#,
+        owner.column()
+    ))
+    .bind(document_id)
+    .bind(owner_id)
+    .bind(version)
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| anyhow!("Document not found"))?;
+
+    let data = storage.get(&version.storage_key).await?;
+    Ok((version, data))
+}
+
+pub async fn update_document(
+    db: &Db,
+    ctx: &AuditContext,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+    req: UpdateDocumentRequest,
+) -> Result<DocumentResponse> {
+    let mut tx = db.begin().await?;
+
+    let document = sqlx::query_as::<_, Document>(&format!(
+        "SELECT * FROM documents WHERE id = $1 AND {} = $2 FOR UPDATE",
+        owner.column()
+    ))
+    .bind(document_id)
+    .bind(owner_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("Document not found"))?;
+    let before = get_document(&mut *tx, owner, owner_id, document_id).await?;
+
+    let category = match &req.category {
+        Some(code) => active_category(&mut tx, code).await?,
+        None => sqlx::query_as::<_, DocumentCategory>(
+            "SELECT * FROM document_categories WHERE id = $1",
+        )
+        .bind(document.category_id)
+        .fetch_one(&mut *tx)
+        .await?,
+    };
+    let expires_on = req.expires_on.or(document.expires_on);
+    if category.requires_expiry && expires_on.is_none() {
+        return Err(anyhow!("{} documents need an expiry date", category.name));
+    }
+    if req.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err(anyhow!("Title can't be empty"));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE documents
+        SET category_id = $2,
+            title = COALESCE($3, title),
+            description = COALESCE($4, description),
+            expires_on = $5,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(document_id)
+    .bind(category.id)
+    .bind(req.title.as_deref().map(str::trim))
+    .bind(&req.description)
+    .bind(expires_on)
+    .execute(&mut *tx)
+    .await?;
+
+    // The document's expiry date is that of its current version
+    sqlx::query("UPDATE document_versions SET expires_on = $3 WHERE document_id = $1 AND version = $2")
+        .bind(document_id)
+        .bind(document.current_version)
+        .bind(expires_on)
+        .execute(&mut *tx)
+        .await?;
+
+    let after = get_document(&mut *tx, owner, owner_id, document_id).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("document", "update", Some(document_id))
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(after)
+}
+
+/// Deletes the document with every version. Files that can't be removed
+/// from storage are only logged, as the document is already gone.
+pub async fn delete_document(
+    db: &Db,
+    ctx: &AuditContext,
+    storage: &dyn Storage,
+    owner: DocumentOwner,
+    owner_id: Uuid,
+    document_id: Uuid,
+) -> Result<DocumentResponse> {
+    let mut tx = db.begin().await?;
+    let document = get_document(&mut *tx, owner, owner_id, document_id).await?;
+
+    let keys = sqlx::query_scalar::<_, String>(
+        r#"
+        WITH deleted AS (DELETE FROM documents WHERE id = $1 RETURNING id)
+        SELECT storage_key FROM document_versions WHERE document_id IN (SELECT id FROM deleted)
+        "#,
+    )
+    .bind(document_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("document", "delete", Some(document_id)).before(&document),
+    )
+    .await?;
+    tx.commit().await?;
+
+    for key in keys {
+        if let Err(e) = storage.delete(&key).await {
+            tracing::warn!("Failed to remove file {} of deleted document: {}", key, e);
+        }
+    }
+
+    Ok(document)
+}
+
+#[derive(Debug, FromRow)]
+struct ExpiringRow {
+    id: Uuid,
+    owner_id: Uuid,
+    owner_name: Option<String>,
+    category: String,
+    category_name: String,
+    title: String,
+    expires_on: NaiveDate,
+}
+
+/// Documents of `owner`s expiring within `days`, or within their category's
+/// warning period, soonest first. Employee documents are limited to `scope`.
+pub async fn list_expiring(
+    db: &Db,
+    scope: RowScope,
+    owner: DocumentOwner,
+    query: ExpiringDocumentsQuery,
+) -> Result<Vec<ExpiringDocumentResponse>> {
+    if query.days.is_some_and(|d| d < 0) {
+        return Err(anyhow!("days can't be negative"));
+    }
+
+    let today = today();
+    let mut conditions = vec![
+        "d.expires_on IS NOT NULL".to_string(),
+        "d.expires_on <= $1 + COALESCE($2, c.expiry_warning_days)".to_string(),
+        "($3::TEXT IS NULL OR c.code = $3)".to_string(),
+    ];
+    if !query.include_expired.unwrap_or(true) {
+        conditions.push("d.expires_on >= $1".to_string());
+    }
+    let scoped = match owner {
+        DocumentOwner::Employee => scope.condition("d.employee_id", 4),
+        _ => None,
+    };
+    if let Some(condition) = &scoped {
+        conditions.push(condition.clone());
+    }
+
+    let select_query = format!(
+        r#"
+        SELECT d.id, d.{0} AS owner_id, NULLIF(CONCAT_WS(' ', p.first_name, p.last_name), '') AS owner_name,
+               c.code AS category, c.name AS category_name, d.title, d.expires_on
+        FROM documents d
+        JOIN document_categories c ON c.id = d.category_id
+        {1}
+        WHERE {2}
+        ORDER BY d.expires_on, d.title
+        "#,
+        owner.column(),
+        owner.person_join(),
+        conditions.join(" AND ")
+    );
+
+    let mut select_q = sqlx::query_as::<_, ExpiringRow>(&select_query)
+        .bind(today)
+        .bind(query.days)
+        .bind(query.category);
+    if scoped.is_some() {
+        select_q = select_q.bind(scope.user_id);
+    }
+
+    let rows = select_q.fetch_all(db).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ExpiringDocumentResponse {
+            id: row.id,
+            owner_type: owner.as_str().to_string(),
+            owner_id: row.owner_id,
+            owner_name: row.owner_name,
+            category: row.category,
+            category_name: row.category_name,
+            title: row.title,
+            days_until_expiry: (row.expires_on - today).num_days() as i32,
+            expires_on: row.expires_on,
+        })
+        .collect())
+}
+
+/// The base name of what the client called the file, without anything that
+/// could break a `Content-Disposition` header.
+fn clean_file_name(name: &str) -> Result<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILE_NAME_LENGTH)
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        return Err(anyhow!("A file name is required"));
+    }
+    Ok(cleaned.to_string())
+}
+
+fn map_document_to_response(owner: DocumentOwner, row: DocumentRow) -> DocumentResponse {
+    let document = row.document;
+    let owner_id = match owner {
+        DocumentOwner::Employee => document.employee_id,
+        DocumentOwner::Intern => document.intern_id,
+        DocumentOwner::Person => document.person_id,
+    };
+    DocumentResponse {
+        id: document.id,
+        owner_type: owner.as_str().to_string(),
+        owner_id: owner_id.unwrap_or_default(),
+        category: row.category,
+        category_name: row.category_name,
+        title: document.title,
+        description: document.description,
+        current_version: document.current_version,
+        expired: document.expires_on.is_some_and(|d| d < today()),
+        expires_on: document.expires_on,
+        file_name: row.file_name,
+        content_type: row.content_type,
+        size_bytes: row.size_bytes,
+        created_by: document.created_by,
+        created_at: document.created_at,
+        updated_at: document.updated_at,
+    }
+}
+
+fn map_version_to_response(version: DocumentVersion) -> DocumentVersionResponse {
+    DocumentVersionResponse {
+        id: version.id,
+        document_id: version.document_id,
+        version: version.version,
+        file_name: version.file_name,
+        content_type: version.content_type,
+        size_bytes: version.size_bytes,
+        sha256: version.sha256,
+        expires_on: version.expires_on,
+        note: version.note,
+        uploaded_by: version.uploaded_by,
+        created_at: version.created_at,
+    }
+}
+
+fn map_category_to_response(category: DocumentCategory) -> DocumentCategoryResponse {
+    DocumentCategoryResponse {
+        id: category.id,
+        code: category.code,
+        name: category.name,
+        requires_expiry: category.requires_expiry,
+        expiry_warning_days: category.expiry_warning_days,
+        is_active: category.is_active,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_file_name_keeps_only_the_base_name() {
+        assert_eq!(clean_file_name("contract.pdf").unwrap(), "contract.pdf");
+        assert_eq!(clean_file_name("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(clean_file_name("C:\\Users\\me\\scan.png").unwrap(), "scan.png");
+    }
+
+    #[test]
+    fn clean_file_name_strips_header_breaking_characters() {
+        assert_eq!(
+            clean_file_name("a\"b\r\nContent-Type: x.pdf").unwrap(),
+            "abContent-Type: x.pdf"
+        );
+        assert_eq!(clean_file_name("  report.pdf ").unwrap(), "report.pdf");
+        let long = "x".repeat(MAX_FILE_NAME_LENGTH + 10);
+        assert_eq!(clean_file_name(&long).unwrap().len(), MAX_FILE_NAME_LENGTH);
+    }
+
+    #[test]
+    fn clean_file_name_rejects_empty_names() {
+        for name in ["", "dir/", "..", "./.", " \" ", "\u{7}"] {
+            assert!(clean_file_name(name).is_err(), "{:?}", name);
+        }
+    }
+}
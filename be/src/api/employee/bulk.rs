@@ -0,0 +1,631 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+};
+
+use anyhow::{Result, anyhow};
+use calamine::{Data, DataType, Reader, Xlsx, open_workbook_from_rs};
+use chrono::NaiveDate;
+use rust_xlsxwriter::{Format, Workbook};
+use sqlx::{Connection, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        employee::{
+            dto::{
+                CreateEmployeeRequest, EmployeeResponse, ExportEmployeesQuery, ImportEmployeesResponse,
+                ImportRowError, ListEmployeesQuery,
+            },
+            service,
+        },
+    },
+    db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+};
+
+pub const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+// The columns of an import or export, in order. Imports match headers
+// ignoring case, spaces and underscores, and in any order.
+const COLUMNS: [&str; 12] = [
+    "employeeId",
+    "firstName",
+    "middleName",
+    "lastName",
+    "email",
+    "phone",
+    "department",
+    "position",
+    "hireDate",
+    "employmentType",
+    "salary",
+    "managerEmployeeId",
+];
+
+const EXPORT_PAGE_SIZE: i64 = 100;
+
+/// A file's cells as text, the header row first.
+type Table = Vec<Vec<String>>;
+
+fn normalize_header(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+pub fn table_from_csv(data: &[u8]) -> Result<Table> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data)
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            record
+                .map(|r| r.iter().map(|c| c.trim().to_string()).collect())
+                .map_err(|e| anyhow!("Row {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(s) => s.trim().to_string(),
+        // Whole numbers are ids and phone numbers as often as amounts
+        Data::Float(f) if f.fract() == 0.0 => format!("{:.0}", f),
+        Data::DateTime(_) | Data::DateTimeIso(_) => {
+            cell.as_date().map(|d| d.to_string()).unwrap_or_default()
+        }
+        other => other.to_string(),
+    }
+}
+
+/// The first worksheet.
+pub fn table_from_xlsx(data: &[u8]) -> Result<Table> {
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(data))
+        .map_err(|e| anyhow!("Not a valid XLSX file: {}", e))?;
+    let sheet = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| anyhow!("The workbook has no worksheets"))??;
+
+    Ok(sheet
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect())
+        .collect())
+}
+
+pub fn table_to_csv(table: &Table) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in table {
+        writer.write_record(row)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+pub fn table_to_xlsx(table: &Table) -> Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Employees")?;
+    let bold = Format::new().set_bold();
+
+    for (r, row) in table.iter().enumerate() {
+        for (c, value) in row.iter().enumerate() {
+            let (r, c) = (r as u32, c as u16);
+            if r == 0 {
+                sheet.write_string_with_format(r, c, value, &bold)?;
+            } else if COLUMNS[c as usize] == "salary"
+                && let Ok(salary) = value.parse::<f64>()
+            {
+                sheet.write_number(r, c, salary)?;
+            } else if !value.is_empty() {
+                sheet.write_string(r, c, value)?;
+            }
+        }
+    }
+    sheet.autofit();
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// A row of an import, checked on its own.
+#[derive(Debug)]
+struct ImportRow {
+    row: usize,
+    employee_id: String,
+    first_name: String,
+    middle_name: Option<String>,
+    last_name: String,
+    email: Option<String>,
+    phone: Option<String>,
+    department_id: Option<Uuid>,
+    position_id: Option<Uuid>,
+    hire_date: Option<NaiveDate>,
+    employment_type: Option<String>,
+    salary: Option<f64>,
+    manager_employee_id: Option<String>,
+    errors: Vec<String>,
+}
+
+async fn ids_by_name(conn: &mut PgConnection, table: &str) -> Result<HashMap<String, Uuid>> {
+    let rows = sqlx::query_as::<_, (String, Uuid)>(&format!("SELECT name, id FROM {}", table))
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(name, id)| (name.trim().to_lowercase(), id))
+        .collect())
+}
+
+async fn parse_rows(conn: &mut PgConnection, table: Table) -> Result<Vec<ImportRow>> {
+    let mut rows = table.into_iter();
+    let header = rows.next().ok_or_else(|| anyhow!("The file is empty"))?;
+
+    let positions_in_file: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (normalize_header(name), i))
+        .collect();
+    let index: HashMap<&str, usize> = COLUMNS
+        .iter()
+        .filter_map(|c| positions_in_file.get(&normalize_header(c)).map(|i| (*c, *i)))
+        .collect();
+    for required in ["employeeId", "firstName", "lastName", "hireDate"] {
+        if !index.contains_key(required) {
+            return Err(anyhow!("The file has no \"{}\" column", required));
+        }
+    }
+
+    let departments = ids_by_name(conn, "departments").await?;
+    let positions = ids_by_name(conn, "positions").await?;
+
+    let mut parsed = Vec::new();
+    for (i, cells) in rows.enumerate() {
+        if cells.iter().all(|c| c.is_empty()) {
+            continue;
+        }
+        let get = |column: &str| {
+            index
+                .get(column)
+                .and_then(|i| cells.get(*i))
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+        };
+
+        let mut errors = Vec::new();
+        let mut required = |column: &str, label: &str| {
+            get(column).unwrap_or_else(|| {
+                errors.push(format!("{} is required", label));
+                String::new()
+            })
+        };
+        let employee_id = required("employeeId", "Employee ID");
+        let first_name = required("firstName", "First name");
+        let last_name = required("lastName", "Last name");
+        let hire_date_text = required("hireDate", "Hire date");
+
+        let hire_date = if hire_date_text.is_empty() {
+            None
+        } else {
+            NaiveDate::parse_from_str(&hire_date_text, "%Y-%m-%d")
+                .map_err(|_| errors.push(format!("Invalid hire date \"{}\", use YYYY-MM-DD", hire_date_text)))
+                .ok()
+        };
+
+        let mut lookup = |column: &str, ids: &HashMap<String, Uuid>, kind: &str| {
+            get(column).and_then(|name| {
+                let id = ids.get(&name.to_lowercase()).copied();
+                if id.is_none() {
+                    errors.push(format!("Unknown {} \"{}\"", kind, name));
+                }
+                id
+            })
+        };
+        let department_id = lookup("department", &departments, "department");
+        let position_id = lookup("position", &positions, "position");
+
+        let salary = get("salary").and_then(|s| match s.replace(',', "").parse::<f64>() {
+            Ok(salary) if salary >= 0.0 => Some(salary),
+            _ => {
+                errors.push(format!("Invalid salary \"{}\"", s));
+                None
+            }
+        });
+
+        let email = get("email").map(|e| e.to_lowercase());
+        if let Some(email) = &email
+            && !email.contains('@')
+        {
+            errors.push(format!("Invalid email \"{}\"", email));
+        }
+        let phone = get("phone");
+        if phone.is_some() && email.is_none() {
+            errors.push("A phone number needs an email".to_string());
+        }
+
+        parsed.push(ImportRow {
+            row: i + 2,
+            employee_id,
+            first_name,
+            middle_name: get("middleName"),
+            last_name,
+            email,
+            phone,
+            department_id,
+            position_id,
+            hire_date,
+            employment_type: get("employmentType"),
+            salary,
+            manager_employee_id: get("managerEmployeeId"),
+            errors,
+        });
+    }
+
+    Ok(parsed)
+}
+
+// Flags values that must be unique but repeat in the file or are already
+// taken, the file's first occurrence being the one kept
+async fn check_unique(
+    conn: &mut PgConnection,
+    rows: &mut [ImportRow],
+    label: &str,
+    value: fn(&ImportRow) -> Option<&String>,
+    taken_query: &str,
+) -> Result<()> {
+    let values: Vec<String> = rows.iter().filter_map(|r| value(r).cloned()).collect();
+    let taken: HashSet<String> = sqlx::query_scalar::<_, String>(taken_query)
+        .bind(&values)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    for row in rows.iter_mut() {
+        let Some(v) = value(row).cloned() else {
+            continue;
+        };
+        if taken.contains(&v) {
+            row.errors.push(format!("{} \"{}\" is already in use", label, v));
+        } else if let Some(first) = first_seen.get(&v) {
+            row.errors
+                .push(format!("{} \"{}\" is already used on row {}", label, v, first));
+        } else {
+            first_seen.insert(v, row.row);
+        }
+    }
+    Ok(())
+}
+
+// Managers in the file before the employees reporting to them. Rows whose
+// managers can't come first, being in a reporting cycle, are flagged.
+fn order_by_manager(rows: &mut [ImportRow]) -> Vec<usize> {
+    let in_file: HashMap<String, usize> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.employee_id.clone(), i))
+        .collect();
+
+    let mut order = Vec::with_capacity(rows.len());
+    let mut placed = vec![false; rows.len()];
+    loop {
+        let before = order.len();
+        for i in 0..rows.len() {
+            let ready = rows[i]
+                .manager_employee_id
+                .as_ref()
+                .and_then(|m| in_file.get(m))
+                .is_none_or(|&m| m == i || placed[m]);
+            if !placed[i] && ready {
+                placed[i] = true;
+                order.push(i);
+            }
+        }
+        if order.len() == before {
+            break;
+        }
+    }
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        if !placed[i] {
+            row.errors
+                .push("The file's managers form a reporting cycle".to_string());
+            order.push(i);
+        }
+    }
+    order
+}
+
+async fn insert_person(conn: &mut PgConnection, row: &ImportRow) -> Result<Uuid> {
+    let person_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO persons (id, first_name, middle_name, last_name) VALUES ($1, $2, $3, $4)")
+        .bind(person_id)
+        .bind(&row.first_name)
+        .bind(&row.middle_name)
+        .bind(&row.last_name)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some(email) = &row.email {
+        sqlx::query("INSERT INTO person_contacts (id, person_id, email, phone) VALUES ($1, $2, $3, $4)")
+            .bind(Uuid::new_v4())
+            .bind(person_id)
+            .bind(email)
+            .bind(&row.phone)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(person_id)
+}
+
+/// Creates a person, contact and employee for every row of `table`, managers
+/// first, as `create_employee` does. Every row is checked and reported on;
+/// nothing is saved if any is invalid or on a dry run.
+pub async fn import_employees(
+    db: &Db,
+    ctx: &AuditContext,
+    table: Table,
+    dry_run: bool,
+) -> Result<ImportEmployeesResponse> {
+    let mut tx = db.begin().await?;
+
+    let mut rows = parse_rows(&mut tx, table).await?;
+    check_unique(
+        &mut tx,
+        &mut rows,
+        "Employee ID",
+        |r| Some(&r.employee_id).filter(|id| !id.is_empty()),
+        "SELECT employee_id FROM employees WHERE employee_id = ANY($1)",
+    )
+    .await?;
+    check_unique(
+        &mut tx,
+        &mut rows,
+        "Email",
+        |r| r.email.as_ref(),
+        "SELECT LOWER(email) FROM person_contacts WHERE LOWER(email) = ANY($1)",
+    )
+    .await?;
+    check_unique(
+        &mut tx,
+        &mut rows,
+        "Phone",
+        |r| r.phone.as_ref(),
+        "SELECT phone FROM person_contacts WHERE phone = ANY($1)",
+    )
+    .await?;
+
+    let total_rows = rows.len();
+    let codes_in_file: HashSet<String> = rows.iter().map(|r| r.employee_id.clone()).collect();
+    let order = order_by_manager(&mut rows);
+    let mut created: HashMap<String, Uuid> = HashMap::new();
+    let mut employees = Vec::new();
+    for i in order {
+        let row = &mut rows[i];
+
+        let manager_id = match &row.manager_employee_id {
+            None => None,
+            Some(code) if *code == row.employee_id => {
+                row.errors.push("An employee can't be their own manager".to_string());
+                None
+            }
+            Some(code) => match created.get(code) {
+                Some(id) => Some(*id),
+                None => {
+                    let existing = sqlx::query_scalar::<_, Uuid>("SELECT id FROM employees WHERE employee_id = $1")
+                        .bind(code)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    // A manager in the file who couldn't be imported has
+                    // their own errors, only worth pointing at from a row
+                    // that has none
+                    if existing.is_none() && !codes_in_file.contains(code) {
+                        row.errors.push(format!("Unknown manager \"{}\"", code));
+                    } else if existing.is_none() && row.errors.is_empty() {
+                        row.errors
+                            .push(format!("Manager \"{}\" couldn't be imported", code));
+                    }
+                    existing
+                }
+            },
+        };
+
+        let Some(hire_date) = row.hire_date else {
+            continue;
+        };
+        if !row.errors.is_empty() {
+            continue;
+        }
+
+        // Each row in a savepoint, so one failing doesn't stop the rest
+        // being checked
+        let mut savepoint = Connection::begin(&mut *tx).await?;
+        let result = async {
+            let person_id = insert_person(&mut savepoint, row).await?;
+            let req = CreateEmployeeRequest {
+                employee_id: row.employee_id.clone(),
+                person_id,
+                department: row.department_id,
+                position: row.position_id,
+                hire_date,
+                employment_type: row.employment_type.clone(),
+                salary: row.salary,
+                manager_id,
+            };
+            service::create_employee_in(&mut savepoint, req, ctx.actor_id).await
+        }
+        .await;
+
+        match result {
+            Ok(employee) => {
+                savepoint.commit().await?;
+                created.insert(employee.employee_id.clone(), employee.id);
+                employees.push(employee);
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                row.errors.push(e.to_string());
+            }
+        }
+    }
+
+    let errors: Vec<ImportRowError> = rows
+        .into_iter()
+        .filter(|r| !r.errors.is_empty())
+        .map(|r| ImportRowError {
+            row: r.row,
+            employee_id: Some(r.employee_id).filter(|id| !id.is_empty()),
+            errors: r.errors,
+        })
+        .collect();
+
+    let valid_rows = employees.len();
+    let commit = !dry_run && errors.is_empty();
+    if commit {
+        for employee in &employees {
+            audit::record_in(
+                &mut tx,
+                ctx,
+                AuditEntry::new("employee", "import", Some(employee.id)).after(employee),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+    }
+
+    Ok(ImportEmployeesResponse {
+        dry_run,
+        total_rows,
+        valid_rows,
+        imported: if commit { valid_rows } else { 0 },
+        errors,
+        employees: if commit { employees } else { Vec::new() },
+    })
+}
+
+async fn names_by_id(conn: &mut PgConnection, query: &str) -> Result<HashMap<Uuid, String>> {
+    let rows = sqlx::query_as::<_, (Uuid, String)>(query)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Every employee `list_employees` returns for the filters, in the import's
+/// format.
+pub async fn export_employees(db: &Db, scope: RowScope, query: ExportEmployeesQuery) -> Result<Table> {
+    let mut employees: Vec<EmployeeResponse> = Vec::new();
+    for page in 1.. {
+        let list = service::list_employees(
+            db,
+            scope,
+            ListEmployeesQuery {
+                page: Some(page),
+                page_size: Some(EXPORT_PAGE_SIZE),
+                search: query.search.clone(),
+                department: query.department.clone(),
+                status: query.status.clone(),
+            },
+        )
+        .await?;
+        let done = list.employees.len() < EXPORT_PAGE_SIZE as usize;
+        employees.extend(list.employees);
+        if done {
+            break;
+        }
+    }
+
+    let mut conn = db.acquire().await?;
+    let departments = names_by_id(&mut conn, "SELECT id, name FROM departments").await?;
+    let positions = names_by_id(&mut conn, "SELECT id, name FROM positions").await?;
+    let codes = names_by_id(&mut conn, "SELECT id, employee_id FROM employees").await?;
+
+    let name_of = |names: &HashMap<Uuid, String>, id: &Option<String>| {
+        id.as_deref()
+            .and_then(|id| id.parse::<Uuid>().ok())
+            .and_then(|id| names.get(&id).cloned())
+            .unwrap_or_default()
+    };
+
+    let mut table: Table = vec![COLUMNS.iter().map(|c| c.to_string()).collect()];
+    for e in employees {
+        table.push(vec![
+            e.employee_id,
+            e.first_name,
+            e.middle_name.unwrap_or_default(),
+            e.last_name,
+            e.email.unwrap_or_default(),
+            e.phone.unwrap_or_default(),
+            name_of(&departments, &e.department),
+            name_of(&positions, &e.position),
+            e.hire_date.to_string(),
+            e.employment_type.unwrap_or_default(),
+            e.salary.map(|s| s.to_string()).unwrap_or_default(),
+            e.manager_id
+                .and_then(|id| codes.get(&id).cloned())
+                .unwrap_or_default(),
+        ]);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(employee_id: &str, manager_employee_id: Option<&str>) -> ImportRow {
+        ImportRow {
+            row: 0,
+            employee_id: employee_id.to_string(),
+            first_name: "First".to_string(),
+            middle_name: None,
+            last_name: "Last".to_string(),
+            email: None,
+            phone: None,
+            department_id: None,
+            position_id: None,
+            hire_date: None,
+            employment_type: None,
+            salary: None,
+            manager_employee_id: manager_employee_id.map(String::from),
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn order_by_manager_puts_managers_first() {
+        let mut rows = vec![
+            row("E3", Some("E2")),
+            row("E2", Some("E1")),
+            row("E1", None),
+            // A manager outside the file doesn't hold the row back
+            row("E4", Some("EXISTING")),
+        ];
+
+        assert_eq!(order_by_manager(&mut rows), vec![2, 3, 1, 0]);
+        assert!(rows.iter().all(|r| r.errors.is_empty()));
+    }
+
+    #[test]
+    fn order_by_manager_flags_reporting_cycles() {
+        let mut rows = vec![
+            row("E1", Some("E2")),
+            row("E2", Some("E1")),
+            row("E3", None),
+            // Being their own manager is left to the row's other checks
+            row("E4", Some("E4")),
+        ];
+
+        assert_eq!(order_by_manager(&mut rows), vec![2, 3, 0, 1]);
+        assert_eq!(rows[0].errors.len(), 1);
+        assert_eq!(rows[1].errors.len(), 1);
+        assert!(rows[2].errors.is_empty() && rows[3].errors.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -24,6 +24,14 @@ pub struct UpdateEmployeeRequest {
     pub salary: Option<f64>,
     pub manager_id: Option<Uuid>,
     pub status: Option<String>,
+    // Fields to remove, by the names above: "department", "position",
+    // "managerId" or "salary"
+    #[serde(default)]
+    pub clear: Vec<String>,
+    // When department, position, manager, salary and status changes take
+    // effect, today by default. Later dates are scheduled.
+    pub effective_date: Option<NaiveDate>,
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -44,6 +52,8 @@ pub struct EmployeeResponse {
     pub salary: Option<f64>,
     pub manager_id: Option<Uuid>,
     pub status: String,
+    pub termination_date: Option<NaiveDate>,
+    pub termination_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,3 +80,279 @@ pub struct ListEmployeesResponse {
 pub struct UpdateFaceDescriptorRequest {
     pub descriptor: String,
 }
+
+/// An entry in an employee's history. Only the fields that changed are set.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmploymentEventResponse {
+    pub id: Uuid,
+    pub event_type: String,
+    pub effective_date: NaiveDate,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub manager_id: Option<Uuid>,
+    pub salary: Option<f64>,
+    pub status: Option<String>,
+    // Fields the event removed, by the names above
+    pub cleared_fields: Vec<String>,
+    pub reason: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub applied_at: Option<NaiveDateTime>,
+    pub pending: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsOfQuery {
+    // Today when not given
+    pub date: Option<NaiveDate>,
+    pub status: Option<String>,
+}
+
+/// An employee's assignment on a given date.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmploymentSnapshotResponse {
+    pub id: Uuid,
+    pub employee_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub manager_id: Option<Uuid>,
+    pub salary: Option<f64>,
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadcountQuery {
+    pub date: Option<NaiveDate>,
+    // "department" (the default) or "position"
+    pub group_by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadcountGroup {
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadcountResponse {
+    pub date: NaiveDate,
+    pub group_by: String,
+    pub total: i64,
+    pub groups: Vec<HeadcountGroup>,
+}
+
+/// An employee as shown on the org chart.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrgChartEmployee {
+    pub id: Uuid,
+    pub employee_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub department_id: Option<Uuid>,
+    pub department_name: Option<String>,
+    pub position_id: Option<Uuid>,
+    pub position_name: Option<String>,
+    pub manager_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrgChartNode {
+    #[serde(flatten)]
+    pub employee: OrgChartEmployee,
+    pub direct_reports: usize,
+    pub total_reports: usize,
+    pub children: Vec<OrgChartNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpanOfControl {
+    #[serde(flatten)]
+    pub employee: OrgChartEmployee,
+    pub direct_reports: usize,
+    pub total_reports: usize,
+    // Levels of management below them
+    pub depth: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrgChartStatsResponse {
+    pub total_employees: usize,
+    pub managers: usize,
+    pub average_span: f64,
+    pub max_span: usize,
+    // Levels of the chart, 1 when nobody has reports
+    pub max_depth: usize,
+    pub spans: Vec<SpanOfControl>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminateEmployeeRequest {
+    pub termination_date: NaiveDate,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTemplateTaskRequest {
+    pub title: String,
+    pub description: Option<String>,
+    // "hr", "it", "manager" or "employee"
+    pub assignee_role: String,
+    // Days after the hire or termination date, negative for before
+    pub due_offset_days: Option<i32>,
+}
+
+/// Creates a template or replaces one, tasks included, in the given order.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTemplateRequest {
+    // "onboarding" or "offboarding"
+    pub kind: String,
+    pub name: String,
+    // Every department when not given
+    pub department_id: Option<Uuid>,
+    pub is_active: Option<bool>,
+    pub tasks: Vec<ChecklistTemplateTaskRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListChecklistTemplatesQuery {
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTemplateTaskResponse {
+    pub id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_role: String,
+    pub due_offset_days: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTemplateResponse {
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    pub department_id: Option<Uuid>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub tasks: Vec<ChecklistTemplateTaskResponse>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTaskResponse {
+    pub id: Uuid,
+    pub checklist_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_role: String,
+    // The manager or employee for their tasks, none for HR and IT
+    pub assignee_employee_id: Option<Uuid>,
+    pub due_date: NaiveDate,
+    pub overdue: bool,
+    pub notes: Option<String>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub completed_by: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmployeeChecklistResponse {
+    pub id: Uuid,
+    pub employee_id: Uuid,
+    pub template_id: Option<Uuid>,
+    pub kind: String,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub tasks: Vec<ChecklistTaskResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateChecklistTaskRequest {
+    pub completed: bool,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListChecklistTasksQuery {
+    pub kind: Option<String>,
+    pub assignee_role: Option<String>,
+    // Only tasks falling to the caller as a manager or as the employee
+    pub mine: Option<bool>,
+    pub include_completed: Option<bool>,
+}
+
+/// A task in someone's checklist, with who it is for.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistTaskQueueItem {
+    #[serde(flatten)]
+    pub task: ChecklistTaskResponse,
+    pub kind: String,
+    pub employee_id: Uuid,
+    pub employee_code: String,
+    pub first_name: String,
+    pub last_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEmployeesQuery {
+    // Validates every row without saving anything
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    // As numbered in the file, the header being row 1
+    pub row: usize,
+    pub employee_id: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Nothing is saved unless every row is valid and it isn't a dry run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEmployeesResponse {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub imported: usize,
+    pub errors: Vec<ImportRowError>,
+    pub employees: Vec<EmployeeResponse>,
+}
+
+/// The filters of `ListEmployeesQuery`, with every page.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportEmployeesQuery {
+    // "csv" (the default) or "xlsx"
+    pub format: Option<String>,
+    pub search: Option<String>,
+    pub department: Option<String>,
+    pub status: Option<String>,
+}
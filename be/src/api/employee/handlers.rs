@@ -1,13 +1,25 @@
 use crate::{
-    api::employee::{
-        dto::{CreateEmployeeRequest, ListEmployeesQuery, UpdateEmployeeRequest, UpdateFaceDescriptorRequest},
-        service,
+    api::{
+        employee::{
+            bulk,
+            dto::{
+                AsOfQuery, ChecklistTemplateRequest, CreateEmployeeRequest, ExportEmployeesQuery,
+                HeadcountQuery, ImportEmployeesQuery, ListChecklistTasksQuery,
+                ListChecklistTemplatesQuery, ListEmployeesQuery, TerminateEmployeeRequest,
+                UpdateChecklistTaskRequest, UpdateEmployeeRequest, UpdateFaceDescriptorRequest,
+            },
+            history, lifecycle, org_chart, service,
+        },
     },
     db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
 };
 use axum::{
+    body::Bytes,
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
@@ -15,9 +27,10 @@ use uuid::Uuid;
 
 pub async fn create_employee_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Json(payload): Json<CreateEmployeeRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    match service::create_employee(&db, payload).await {
+    match service::create_employee(&db, &ctx, payload).await {
         Ok(employee) => Ok((StatusCode::CREATED, Json(json!(employee)))),
         Err(e) => {
             eprintln!("Error creating employee: {}", e);
@@ -27,10 +40,90 @@ pub async fn create_employee_handler(
     }
 }
 
+/// Takes a CSV file, or an XLSX one with its content type. Responds with
+/// the report of every row, as 400 when nothing was imported because of
+/// invalid rows.
+pub async fn import_employees_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Query(query): Query<ImportEmployeesQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let is_xlsx = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with(bulk::XLSX_CONTENT_TYPE));
+    let table = if is_xlsx {
+        bulk::table_from_xlsx(&body)
+    } else {
+        bulk::table_from_csv(&body)
+    };
+
+    let dry_run = query.dry_run.unwrap_or(false);
+    let report = match table {
+        Ok(table) => bulk::import_employees(&db, &ctx, table, dry_run).await,
+        Err(e) => Err(e),
+    };
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error importing employees: {}", e);
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))));
+        }
+    };
+
+    let status = if dry_run || report.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    Ok((status, Json(json!(report))))
+}
+
+/// `?format=xlsx` for an XLSX file, CSV otherwise, in the import's format.
+pub async fn export_employees_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Query(query): Query<ExportEmployeesQuery>,
+) -> Result<Response, StatusCode> {
+    let is_xlsx = query.format.as_deref() == Some("xlsx");
+    let table = bulk::export_employees(&db, scope, query)
+        .await
+        .map_err(|e| {
+            eprintln!("Error exporting employees: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let (file, content_type, filename) = if is_xlsx {
+        (bulk::table_to_xlsx(&table), bulk::XLSX_CONTENT_TYPE, "employees.xlsx")
+    } else {
+        (bulk::table_to_csv(&table), "text/csv", "employees.csv")
+    };
+    let file = file.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        file,
+    )
+        .into_response())
+}
+
 pub async fn get_employee_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
     let employee = service::get_employee(&db, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
@@ -39,9 +132,10 @@ pub async fn get_employee_handler(
 
 pub async fn list_employees_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Query(query): Query<ListEmployeesQuery>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let response = service::list_employees(&db, query)
+    let response = service::list_employees(&db, scope, query)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok((StatusCode::OK, Json(json!(response))))
@@ -49,20 +143,42 @@ pub async fn list_employees_handler(
 
 pub async fn update_employee_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateEmployeeRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let employee = service::update_employee(&db, id, payload)
+    if !scope
+        .includes(&db, id)
         .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let employee = match service::update_employee(&db, &ctx, id, payload).await {
+        Ok(employee) => employee,
+        Err(e) => {
+            eprintln!("Error updating employee: {}", e);
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))));
+        }
+    };
     Ok((StatusCode::OK, Json(json!(employee))))
 }
 
 pub async fn delete_employee_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::delete_employee(&db, id)
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    service::delete_employee(&db, &ctx, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((
@@ -73,10 +189,19 @@ pub async fn delete_employee_handler(
 
 pub async fn update_face_descriptor_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateFaceDescriptorRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::update_face_descriptor(&db, id, payload.descriptor)
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    service::update_face_descriptor(&db, &ctx, id, payload.descriptor)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((
@@ -94,3 +219,278 @@ pub async fn list_face_descriptors_handler(
     Ok((StatusCode::OK, Json(json!(descriptors))))
 }
 
+
+pub async fn get_employee_history_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let history = history::list_history(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!(history))))
+}
+
+pub async fn cancel_employment_event_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
+    Path((id, event_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    history::cancel_event(&db, &ctx, id, event_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Scheduled change cancelled successfully"})),
+    ))
+}
+
+pub async fn list_employees_as_of_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Query(query): Query<AsOfQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let employees = history::list_as_of(&db, scope, query)
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing employees as of date: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::OK, Json(json!(employees))))
+}
+
+pub async fn headcount_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Query(query): Query<HeadcountQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match history::headcount(&db, scope, query).await {
+        Ok(headcount) => Ok((StatusCode::OK, Json(json!(headcount)))),
+        Err(e) => {
+            eprintln!("Error counting employees: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn get_org_chart_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let chart = org_chart::get_org_chart(&db, &scope)
+        .await
+        .map_err(|e| {
+            eprintln!("Error building org chart: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::OK, Json(json!(chart))))
+}
+
+pub async fn get_org_chart_stats_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let stats = org_chart::get_org_chart_stats(&db, &scope)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!(stats))))
+}
+
+pub async fn get_org_subtree_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let subtree = org_chart::get_subtree(&db, id, &scope)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((StatusCode::OK, Json(json!(subtree))))
+}
+
+pub async fn get_management_chain_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let chain = org_chart::get_management_chain(&db, id, &scope)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((StatusCode::OK, Json(json!(chain))))
+}
+
+pub async fn terminate_employee_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TerminateEmployeeRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    match lifecycle::terminate_employee(&db, &ctx, id, payload).await {
+        Ok(employee) => Ok((StatusCode::OK, Json(json!(employee)))),
+        Err(e) => {
+            eprintln!("Error terminating employee: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn list_employee_checklists_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let checklists = lifecycle::list_checklists(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((StatusCode::OK, Json(json!(checklists))))
+}
+
+pub async fn update_checklist_task_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
+    Path((id, task_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateChecklistTaskRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let task = lifecycle::update_task(&db, &ctx, id, task_id, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error updating checklist task: {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+    Ok((StatusCode::OK, Json(json!(task))))
+}
+
+pub async fn list_checklist_tasks_handler(
+    Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    Query(query): Query<ListChecklistTasksQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match lifecycle::list_tasks(&db, scope, query).await {
+        Ok(tasks) => Ok((StatusCode::OK, Json(json!(tasks)))),
+        Err(e) => {
+            eprintln!("Error listing checklist tasks: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn list_checklist_templates_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<ListChecklistTemplatesQuery>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match lifecycle::list_templates(&db, query.kind).await {
+        Ok(templates) => Ok((StatusCode::OK, Json(json!(templates)))),
+        Err(e) => {
+            eprintln!("Error listing checklist templates: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn get_checklist_template_handler(
+    Extension(db): Extension<Db>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let template = lifecycle::get_template(&db, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((StatusCode::OK, Json(json!(template))))
+}
+
+pub async fn create_checklist_template_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<ChecklistTemplateRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    match lifecycle::create_template(&db, &ctx, payload).await {
+        Ok(template) => Ok((StatusCode::CREATED, Json(json!(template)))),
+        Err(e) => {
+            eprintln!("Error creating checklist template: {}", e);
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+pub async fn update_checklist_template_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ChecklistTemplateRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if lifecycle::get_template(&db, id).await.is_err() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let template = match lifecycle::update_template(&db, &ctx, id, payload).await {
+        Ok(template) => template,
+        Err(e) => {
+            eprintln!("Error updating checklist template: {}", e);
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))));
+        }
+    };
+    Ok((StatusCode::OK, Json(json!(template))))
+}
+
+pub async fn delete_checklist_template_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    lifecycle::delete_template(&db, &ctx, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Checklist template deleted successfully"})),
+    ))
+}
@@ -0,0 +1,399 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{NaiveDate, Utc};
+use sqlx::{FromRow, PgConnection, types::BigDecimal};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        employee::{
+            dto::{
+                AsOfQuery, EmploymentEventResponse, EmploymentSnapshotResponse, HeadcountGroup,
+                HeadcountQuery, HeadcountResponse,
+            },
+            lifecycle, org_chart,
+        },
+    },
+    db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    models::employment_event::EmploymentEvent,
+};
+
+const SCHEDULER_INTERVAL_SECONDS: u64 = 60 * 60;
+
+/// Columns an employment change can set to NULL. Status always has a value.
+pub const CLEARABLE_FIELDS: [&str; 4] = ["department_id", "position_id", "manager_id", "salary"];
+
+/// A change to an employee's assignment taking effect on `effective_date`.
+/// Fields left `None` stay as they are, unless their column is in
+/// `cleared_fields`.
+#[derive(Debug, Clone)]
+pub struct EmploymentChange {
+    pub effective_date: NaiveDate,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub manager_id: Option<Uuid>,
+    pub salary: Option<BigDecimal>,
+    pub status: Option<String>,
+    pub cleared_fields: Vec<String>,
+    pub reason: Option<String>,
+}
+
+impl EmploymentChange {
+    pub fn is_empty(&self) -> bool {
+        self.department_id.is_none()
+            && self.position_id.is_none()
+            && self.manager_id.is_none()
+            && self.salary.is_none()
+            && self.status.is_none()
+            && self.cleared_fields.is_empty()
+    }
+}
+
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// Records `change` in the employee's history, as applied when it is already
+/// in effect. A change can't be dated before one that has been applied,
+/// which would leave the employee's current record out of date.
+pub async fn record_event(
+    conn: &mut PgConnection,
+    employee_id: Uuid,
+    event_type: &str,
+    change: &EmploymentChange,
+    created_by: Option<Uuid>,
+) -> Result<EmploymentEvent> {
+    let last_applied = sqlx::query_scalar::<_, Option<NaiveDate>>(
+        "SELECT MAX(effective_date) FROM employment_events WHERE employee_id = $1 AND applied_at IS NOT NULL",
+    )
+    .bind(employee_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let Some(last) = last_applied
+        && change.effective_date < last
+    {
+        return Err(anyhow!(
+            "Changes can't take effect before the last recorded change on {}",
+            last
+        ));
+    }
+
+    let event = sqlx::query_as::<_, EmploymentEvent>(
+        r#"
+        INSERT INTO employment_events (
+            employee_id, event_type, effective_date,
+            department_id, position_id, manager_id, salary, status,
+            reason, created_by, applied_at, cleared_fields
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, CASE WHEN $3 <= $11 THEN NOW() END, $12)
+        RETURNING *
+        "#,
+    )
+    .bind(employee_id)
+    .bind(event_type)
+    .bind(change.effective_date)
+    .bind(change.department_id)
+    .bind(change.position_id)
+    .bind(change.manager_id)
+    .bind(&change.salary)
+    .bind(&change.status)
+    .bind(&change.reason)
+    .bind(created_by)
+    .bind(today())
+    .bind(&change.cleared_fields)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(event)
+}
+
+/// Oldest first, including scheduled changes.
+pub async fn list_history(db: &Db, employee_id: Uuid) -> Result<Vec<EmploymentEventResponse>> {
+    let events = sqlx::query_as::<_, EmploymentEvent>(
+        r#"
+        SELECT * FROM employment_events
+        WHERE employee_id = $1
+        ORDER BY effective_date, created_at
+        "#,
+    )
+    .bind(employee_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(events.into_iter().map(map_event_to_response).collect())
+}
+
+/// Cancels a scheduled change. Applied changes are history and stay.
+pub async fn cancel_event(
+    db: &Db,
+    ctx: &AuditContext,
+    employee_id: Uuid,
+    event_id: Uuid,
+) -> Result<EmploymentEventResponse> {
+    let mut tx = db.begin().await?;
+    let event = sqlx::query_as::<_, EmploymentEvent>(
+        r#"
+        DELETE FROM employment_events
+        WHERE id = $1 AND employee_id = $2 AND applied_at IS NULL
+        RETURNING *
+        "#,
+    )
+    .bind(event_id)
+    .bind(employee_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("Scheduled change not found"))?;
+    let event = map_event_to_response(event);
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "cancel_scheduled_change", Some(employee_id)).before(&event),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(event)
+}
+
+/// Copies the scheduled changes whose date has come onto the employees, in
+/// date order. Returns the changes applied. A change to a manager who has
+/// since left or would now create a reporting cycle stays pending, to be
+/// cancelled.
+pub async fn apply_due_events(db: &Db) -> Result<Vec<EmploymentEvent>> {
+    let mut tx = db.begin().await?;
+
+    let due = sqlx::query_as::<_, EmploymentEvent>(
+        r#"
+        SELECT * FROM employment_events
+        WHERE applied_at IS NULL AND effective_date <= $1
+        ORDER BY effective_date, created_at
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(today())
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut applied = Vec::with_capacity(due.len());
+    for event in due {
+        if let Some(manager_id) = event.manager_id
+            && let Err(e) =
+                org_chart::validate_manager(&mut tx, Some(event.employee_id), manager_id).await
+        {
+            tracing::warn!("Scheduled employment change {} not applied: {}", event.id, e);
+            continue;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE employees
+            SET department_id = CASE WHEN 'department_id' = ANY($7) THEN NULL ELSE COALESCE($2, department_id) END,
+                position_id = CASE WHEN 'position_id' = ANY($7) THEN NULL ELSE COALESCE($3, position_id) END,
+                manager_id = CASE WHEN 'manager_id' = ANY($7) THEN NULL ELSE COALESCE($4, manager_id) END,
+                salary = CASE WHEN 'salary' = ANY($7) THEN NULL ELSE COALESCE($5, salary) END,
+                status = COALESCE($6, status),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(event.employee_id)
+        .bind(event.department_id)
+        .bind(event.position_id)
+        .bind(event.manager_id)
+        .bind(&event.salary)
+        .bind(&event.status)
+        .bind(&event.cleared_fields)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE employment_events SET applied_at = NOW() WHERE id = $1")
+            .bind(event.id)
+            .execute(&mut *tx)
+            .await?;
+        audit::record_in(
+            &mut tx,
+            &AuditContext::default(),
+            AuditEntry::new("employee", "apply_scheduled_change", Some(event.employee_id))
+                .after(&map_event_to_response(event.clone())),
+        )
+        .await?;
+        applied.push(event);
+    }
+
+    tx.commit().await?;
+    Ok(applied)
+}
+
+pub fn spawn_scheduler(db: Db) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULER_INTERVAL_SECONDS));
+        loop {
+            interval.tick().await;
+            match apply_due_events(&db).await {
+                Ok(applied) if applied.is_empty() => {}
+                Ok(applied) => {
+                    tracing::info!("Applied {} scheduled employment changes", applied.len());
+                }
+                Err(e) => tracing::error!("Employment change scheduler error: {}", e),
+            }
+            match lifecycle::complete_due_offboardings(&db).await {
+                Ok(terminated) if terminated.is_empty() => {}
+                Ok(terminated) => {
+                    tracing::info!("Completed the offboarding of {} employees", terminated.len());
+                }
+                Err(e) => tracing::error!("Offboarding scheduler error: {}", e),
+            }
+        }
+    })
+}
+
+#[derive(Debug, FromRow)]
+struct EmploymentSnapshot {
+    id: Uuid,
+    employee_id: String,
+    first_name: String,
+    last_name: String,
+    department_id: Option<Uuid>,
+    position_id: Option<Uuid>,
+    manager_id: Option<Uuid>,
+    salary: Option<BigDecimal>,
+    status: String,
+}
+
+/// Everyone hired by the date with the department, position, manager,
+/// salary and status they had then, within `scope`. Gives the org
+/// structure on that date through the manager ids.
+pub async fn list_as_of(
+    db: &Db,
+    scope: RowScope,
+    query: AsOfQuery,
+) -> Result<Vec<EmploymentSnapshotResponse>> {
+    let mut conditions: Vec<String> = vec!["1=1".to_string()];
+    let mut param_index = 2;
+
+    let scoped = scope.condition("e.id", param_index);
+    if let Some(condition) = &scoped {
+        conditions.push(condition.clone());
+        param_index += 1;
+    }
+    if query.status.is_some() {
+        conditions.push(format!("a.status = ${}", param_index));
+    }
+
+    let select_query = format!(
+        r#"
+        SELECT e.id, e.employee_id, p.first_name, p.last_name,
+               a.department_id, a.position_id, a.manager_id, a.salary, a.status
+        FROM employment_as_of($1) a
+        JOIN employees e ON e.id = a.employee_id
+        JOIN persons p ON p.id = e.person_id
+        WHERE {}
+        ORDER BY p.last_name, p.first_name
+        "#,
+        conditions.join(" AND ")
+    );
+
+    let mut select_q = sqlx::query_as::<_, EmploymentSnapshot>(&select_query)
+        .bind(query.date.unwrap_or_else(today));
+    if scoped.is_some() {
+        select_q = select_q.bind(scope.user_id);
+    }
+    if let Some(status) = &query.status {
+        select_q = select_q.bind(status);
+    }
+
+    let rows = select_q.fetch_all(db).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| EmploymentSnapshotResponse {
+            id: row.id,
+            employee_id: row.employee_id,
+            first_name: row.first_name,
+            last_name: row.last_name,
+            department_id: row.department_id,
+            position_id: row.position_id,
+            manager_id: row.manager_id,
+            salary: row.salary.and_then(|s| s.to_string().parse().ok()),
+            status: row.status,
+        })
+        .collect())
+}
+
+/// Active employees on the date per department or position, within `scope`.
+pub async fn headcount(db: &Db, scope: RowScope, query: HeadcountQuery) -> Result<HeadcountResponse> {
+    let date = query.date.unwrap_or_else(today);
+    let group_by = query.group_by.unwrap_or_else(|| "department".to_string());
+    let (column, table) = match group_by.as_str() {
+        "department" => ("department_id", "departments"),
+        "position" => ("position_id", "positions"),
+        _ => return Err(anyhow!("groupBy must be \"department\" or \"position\"")),
+    };
+
+    let scoped = scope.condition("a.employee_id", 2);
+    let select_query = format!(
+        r#"
+        SELECT a.{0} AS id, g.name, COUNT(*) AS count
+        FROM employment_as_of($1) a
+        LEFT JOIN {1} g ON g.id = a.{0}
+        WHERE a.status = 'active' AND {2}
+        GROUP BY a.{0}, g.name
+        ORDER BY g.name NULLS LAST
+        "#,
+        column,
+        table,
+        scoped.as_deref().unwrap_or("1=1")
+    );
+
+    let mut select_q = sqlx::query_as::<_, (Option<Uuid>, Option<String>, i64)>(&select_query).bind(date);
+    if scoped.is_some() {
+        select_q = select_q.bind(scope.user_id);
+    }
+
+    let groups: Vec<HeadcountGroup> = select_q
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|(id, name, count)| HeadcountGroup { id, name, count })
+        .collect();
+
+    Ok(HeadcountResponse {
+        date,
+        group_by,
+        total: groups.iter().map(|g| g.count).sum(),
+        groups,
+    })
+}
+
+pub fn map_event_to_response(event: EmploymentEvent) -> EmploymentEventResponse {
+    EmploymentEventResponse {
+        id: event.id,
+        event_type: event.event_type,
+        effective_date: event.effective_date,
+        department_id: event.department_id,
+        position_id: event.position_id,
+        manager_id: event.manager_id,
+        salary: event.salary.and_then(|s| s.to_string().parse().ok()),
+        status: event.status,
+        cleared_fields: event
+            .cleared_fields
+            .iter()
+            .map(|field| match field.as_str() {
+                "department_id" => "departmentId".to_string(),
+                "position_id" => "positionId".to_string(),
+                "manager_id" => "managerId".to_string(),
+                other => other.to_string(),
+            })
+            .collect(),
+        reason: event.reason,
+        created_by: event.created_by,
+        created_at: event.created_at,
+        pending: event.applied_at.is_none(),
+        applied_at: event.applied_at,
+    }
+}
@@ -0,0 +1,768 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use sqlx::{FromRow, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        employee::{
+            dto::{
+                ChecklistTaskQueueItem, ChecklistTaskResponse, ChecklistTemplateRequest,
+                ChecklistTemplateResponse, ChecklistTemplateTaskResponse, EmployeeChecklistResponse,
+                EmployeeResponse, ListChecklistTasksQuery, TerminateEmployeeRequest,
+                UpdateChecklistTaskRequest,
+            },
+            history::{self, EmploymentChange},
+            service,
+        },
+        user,
+    },
+    db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    models::{
+        checklist_template::{ChecklistTemplate, ChecklistTemplateTask},
+        employee::Employee,
+        employee_checklist::{EmployeeChecklist, EmployeeChecklistTask},
+    },
+};
+
+const KINDS: [&str; 2] = ["onboarding", "offboarding"];
+const ASSIGNEE_ROLES: [&str; 4] = ["hr", "it", "manager", "employee"];
+
+fn validate_kind(kind: &str) -> Result<()> {
+    if !KINDS.contains(&kind) {
+        return Err(anyhow!("kind must be \"onboarding\" or \"offboarding\""));
+    }
+    Ok(())
+}
+
+fn validate_template(req: &ChecklistTemplateRequest) -> Result<()> {
+    validate_kind(&req.kind)?;
+    if req.name.trim().is_empty() {
+        return Err(anyhow!("Template name is required"));
+    }
+    for task in &req.tasks {
+        if task.title.trim().is_empty() {
+            return Err(anyhow!("Every task needs a title"));
+        }
+        if !ASSIGNEE_ROLES.contains(&task.assignee_role.as_str()) {
+            return Err(anyhow!(
+                "Task \"{}\" must be assigned to \"hr\", \"it\", \"manager\" or \"employee\"",
+                task.title
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn map_template_to_response(
+    template: ChecklistTemplate,
+    tasks: Vec<ChecklistTemplateTask>,
+) -> ChecklistTemplateResponse {
+    ChecklistTemplateResponse {
+        id: template.id,
+        kind: template.kind,
+        name: template.name,
+        department_id: template.department_id,
+        is_active: template.is_active,
+        created_at: template.created_at,
+        updated_at: template.updated_at,
+        tasks: tasks
+            .into_iter()
+            .map(|t| ChecklistTemplateTaskResponse {
+                id: t.id,
+                title: t.title,
+                description: t.description,
+                assignee_role: t.assignee_role,
+                due_offset_days: t.due_offset_days,
+            })
+            .collect(),
+    }
+}
+
+// The templates with their tasks, in the order given
+async fn with_tasks(
+    conn: &mut PgConnection,
+    templates: Vec<ChecklistTemplate>,
+) -> Result<Vec<ChecklistTemplateResponse>> {
+    let ids: Vec<Uuid> = templates.iter().map(|t| t.id).collect();
+    let mut tasks: HashMap<Uuid, Vec<ChecklistTemplateTask>> = HashMap::new();
+    for task in sqlx::query_as::<_, ChecklistTemplateTask>(
+        "SELECT * FROM checklist_template_tasks WHERE template_id = ANY($1) ORDER BY display_order",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        tasks.entry(task.template_id).or_default().push(task);
+    }
+
+    Ok(templates
+        .into_iter()
+        .map(|template| {
+            let template_tasks = tasks.remove(&template.id).unwrap_or_default();
+            map_template_to_response(template, template_tasks)
+        })
+        .collect())
+}
+
+async fn get_template_in(conn: &mut PgConnection, id: Uuid) -> Result<ChecklistTemplateResponse> {
+    let template = sqlx::query_as::<_, ChecklistTemplate>("SELECT * FROM checklist_templates WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow!("Checklist template not found"))?;
+
+    with_tasks(conn, vec![template])
+        .await?
+        .pop()
+        .ok_or_else(|| anyhow!("Checklist template not found"))
+}
+
+pub async fn list_templates(db: &Db, kind: Option<String>) -> Result<Vec<ChecklistTemplateResponse>> {
+    if let Some(kind) = &kind {
+        validate_kind(kind)?;
+    }
+
+    let mut conn = db.acquire().await?;
+    let templates = sqlx::query_as::<_, ChecklistTemplate>(
+        r#"
+        SELECT * FROM checklist_templates
+        WHERE $1::varchar IS NULL OR kind = $1
+        ORDER BY kind, department_id NULLS FIRST, name
+        "#,
+    )
+    .bind(kind)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    with_tasks(&mut conn, templates).await
+}
+
+pub async fn get_template(db: &Db, id: Uuid) -> Result<ChecklistTemplateResponse> {
+    let mut conn = db.acquire().await?;
+    get_template_in(&mut conn, id).await
+}
+
+async fn insert_template_tasks(
+    conn: &mut PgConnection,
+    template_id: Uuid,
+    req: &ChecklistTemplateRequest,
+) -> Result<()> {
+    for (index, task) in req.tasks.iter().enumerate() {
+        sqlx::query(
+            r#"
+            INSERT INTO checklist_template_tasks
+                (template_id, title, description, assignee_role, due_offset_days, display_order)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(template_id)
+        .bind(task.title.trim())
+        .bind(&task.description)
+        .bind(&task.assignee_role)
+        .bind(task.due_offset_days.unwrap_or(0))
+        .bind(index as i32 + 1)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn create_template(
+    db: &Db,
+    ctx: &AuditContext,
+    req: ChecklistTemplateRequest,
+) -> Result<ChecklistTemplateResponse> {
+    validate_template(&req)?;
+
+    let mut tx = db.begin().await?;
+    let template_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO checklist_templates (kind, name, department_id, is_active)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(&req.kind)
+    .bind(req.name.trim())
+    .bind(req.department_id)
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    insert_template_tasks(&mut tx, template_id, &req).await?;
+    let template = get_template_in(&mut tx, template_id).await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("checklist_template", "create", Some(template.id)).after(&template),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(template)
+}
+
+/// Replaces the template and its tasks. Checklists already created from it
+/// keep the tasks they had.
+pub async fn update_template(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    req: ChecklistTemplateRequest,
+) -> Result<ChecklistTemplateResponse> {
+    validate_template(&req)?;
+
+    let mut tx = db.begin().await?;
+    let before = get_template_in(&mut tx, id).await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE checklist_templates
+        SET kind = $2, name = $3, department_id = $4, is_active = $5, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(&req.kind)
+    .bind(req.name.trim())
+    .bind(req.department_id)
+    .bind(req.is_active.unwrap_or(true))
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(anyhow!("Checklist template not found"));
+    }
+
+    sqlx::query("DELETE FROM checklist_template_tasks WHERE template_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    insert_template_tasks(&mut tx, id, &req).await?;
+    let template = get_template_in(&mut tx, id).await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("checklist_template", "update", Some(id))
+            .before(&before)
+            .after(&template),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(template)
+}
+
+pub async fn delete_template(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<ChecklistTemplateResponse> {
+    let mut tx = db.begin().await?;
+    let template = get_template_in(&mut tx, id).await?;
+
+    sqlx::query("DELETE FROM checklist_templates WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("checklist_template", "delete", Some(id)).before(&template),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(template)
+}
+
+/// Gives the employee a checklist from every active `kind` template for
+/// their department or all departments, with tasks due relative to
+/// `start_date`. Manager tasks go to their manager at the time.
+pub async fn start_checklists(
+    conn: &mut PgConnection,
+    employee_id: Uuid,
+    kind: &str,
+    start_date: NaiveDate,
+    created_by: Option<Uuid>,
+) -> Result<()> {
+    let (department_id, manager_id) = sqlx::query_as::<_, (Option<Uuid>, Option<Uuid>)>(
+        "SELECT department_id, manager_id FROM employees WHERE id = $1",
+    )
+    .bind(employee_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| anyhow!("Employee not found"))?;
+
+    let templates = sqlx::query_as::<_, ChecklistTemplate>(
+        r#"
+        SELECT * FROM checklist_templates
+        WHERE kind = $1 AND is_active
+          AND (department_id IS NULL OR department_id = $2)
+        ORDER BY department_id NULLS FIRST, name
+        "#,
+    )
+    .bind(kind)
+    .bind(department_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for template in templates {
+        let checklist_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO employee_checklists (employee_id, template_id, kind, name, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(employee_id)
+        .bind(template.id)
+        .bind(kind)
+        .bind(&template.name)
+        .bind(created_by)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO employee_checklist_tasks
+                (checklist_id, title, description, assignee_role, assignee_employee_id,
+                 due_date, display_order)
+            SELECT $1, title, description, assignee_role,
+                   CASE assignee_role WHEN 'manager' THEN $3 WHEN 'employee' THEN $4 END,
+                   $5::date + due_offset_days, display_order
+            FROM checklist_template_tasks
+            WHERE template_id = $2
+            "#,
+        )
+        .bind(checklist_id)
+        .bind(template.id)
+        .bind(manager_id)
+        .bind(employee_id)
+        .bind(start_date)
+        .execute(&mut *conn)
+        .await?;
+
+        // A template without tasks has nothing left to do
+        sqlx::query(
+            r#"
+            UPDATE employee_checklists SET completed_at = NOW()
+            WHERE id = $1
+              AND NOT EXISTS (SELECT 1 FROM employee_checklist_tasks WHERE checklist_id = $1)
+            "#,
+        )
+        .bind(checklist_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn map_task_to_response(task: EmployeeChecklistTask) -> ChecklistTaskResponse {
+    ChecklistTaskResponse {
+        id: task.id,
+        checklist_id: task.checklist_id,
+        title: task.title,
+        description: task.description,
+        assignee_role: task.assignee_role,
+        assignee_employee_id: task.assignee_employee_id,
+        overdue: task.completed_at.is_none() && task.due_date < history::today(),
+        due_date: task.due_date,
+        notes: task.notes,
+        completed_at: task.completed_at,
+        completed_by: task.completed_by,
+    }
+}
+
+/// The employee's checklists, oldest first, with their tasks in order.
+pub async fn list_checklists(db: &Db, employee_id: Uuid) -> Result<Vec<EmployeeChecklistResponse>> {
+    let checklists = sqlx::query_as::<_, EmployeeChecklist>(
+        "SELECT * FROM employee_checklists WHERE employee_id = $1 ORDER BY created_at, name",
+    )
+    .bind(employee_id)
+    .fetch_all(db)
+    .await?;
+
+    let mut tasks: HashMap<Uuid, Vec<ChecklistTaskResponse>> = HashMap::new();
+    for task in sqlx::query_as::<_, EmployeeChecklistTask>(
+        r#"
+        SELECT t.* FROM employee_checklist_tasks t
+        JOIN employee_checklists c ON c.id = t.checklist_id
+        WHERE c.employee_id = $1
+        ORDER BY t.display_order
+        "#,
+    )
+    .bind(employee_id)
+    .fetch_all(db)
+    .await?
+    {
+        tasks
+            .entry(task.checklist_id)
+            .or_default()
+            .push(map_task_to_response(task));
+    }
+
+    Ok(checklists
+        .into_iter()
+        .map(|c| EmployeeChecklistResponse {
+            tasks: tasks.remove(&c.id).unwrap_or_default(),
+            id: c.id,
+            employee_id: c.employee_id,
+            template_id: c.template_id,
+            kind: c.kind,
+            name: c.name,
+            created_at: c.created_at,
+            completed_at: c.completed_at,
+        })
+        .collect())
+}
+
+#[derive(Debug, FromRow)]
+struct QueueRow {
+    #[sqlx(flatten)]
+    task: EmployeeChecklistTask,
+    kind: String,
+    employee_uuid: Uuid,
+    employee_code: String,
+    first_name: String,
+    last_name: String,
+}
+
+/// Checklist tasks across employees within `scope`, soonest due first. Open
+/// ones only unless `include_completed`.
+pub async fn list_tasks(
+    db: &Db,
+    scope: RowScope,
+    query: ListChecklistTasksQuery,
+) -> Result<Vec<ChecklistTaskQueueItem>> {
+    if let Some(kind) = &query.kind {
+        validate_kind(kind)?;
+    }
+
+    let mut conditions: Vec<String> = vec!["1=1".to_string()];
+    let mut param_index = 1;
+
+    let scoped = scope.condition("c.employee_id", param_index);
+    if let Some(condition) = &scoped {
+        conditions.push(condition.clone());
+        param_index += 1;
+    }
+    if !query.include_completed.unwrap_or(false) {
+        conditions.push("t.completed_at IS NULL".to_string());
+    }
+    if query.kind.is_some() {
+        conditions.push(format!("c.kind = ${}", param_index));
+        param_index += 1;
+    }
+    if query.assignee_role.is_some() {
+        conditions.push(format!("t.assignee_role = ${}", param_index));
+        param_index += 1;
+    }
+    if query.mine.unwrap_or(false) {
+        conditions.push(format!(
+            r#"t.assignee_employee_id IN (
+                SELECT me.id FROM employees me
+                JOIN users u ON u.person_id = me.person_id
+                WHERE u.id = ${}
+            )"#,
+            param_index
+        ));
+    }
+
+    let select_query = format!(
+        r#"
+        SELECT t.*, c.kind, e.id AS employee_uuid, e.employee_id AS employee_code,
+               p.first_name, p.last_name
+        FROM employee_checklist_tasks t
+        JOIN employee_checklists c ON c.id = t.checklist_id
+        JOIN employees e ON e.id = c.employee_id
+        JOIN persons p ON p.id = e.person_id
+        WHERE {}
+        ORDER BY t.due_date, p.last_name, p.first_name, t.display_order
+        "#,
+        conditions.join(" AND ")
+    );
+
+    let mut select_q = sqlx::query_as::<_, QueueRow>(&select_query);
+    if scoped.is_some() {
+        select_q = select_q.bind(scope.user_id);
+    }
+    if let Some(kind) = &query.kind {
+        select_q = select_q.bind(kind);
+    }
+    if let Some(role) = &query.assignee_role {
+        select_q = select_q.bind(role);
+    }
+    if query.mine.unwrap_or(false) {
+        select_q = select_q.bind(scope.user_id);
+    }
+
+    let rows = select_q.fetch_all(db).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ChecklistTaskQueueItem {
+            task: map_task_to_response(row.task),
+            kind: row.kind,
+            employee_id: row.employee_uuid,
+            employee_code: row.employee_code,
+            first_name: row.first_name,
+            last_name: row.last_name,
+        })
+        .collect())
+}
+
+/// Marks the task done, or not done again, and its checklist with it. The
+/// last offboarding task completes the employee's offboarding when their
+/// termination date has come.
+pub async fn update_task(
+    db: &Db,
+    ctx: &AuditContext,
+    employee_id: Uuid,
+    task_id: Uuid,
+    req: UpdateChecklistTaskRequest,
+) -> Result<ChecklistTaskResponse> {
+    let mut tx = db.begin().await?;
+
+    let task = sqlx::query_as::<_, EmployeeChecklistTask>(
+        r#"
+        UPDATE employee_checklist_tasks t
+        SET completed_at = CASE WHEN $3 THEN COALESCE(t.completed_at, NOW()) END,
+            completed_by = CASE WHEN $3 THEN CASE WHEN t.completed_at IS NULL THEN $4 ELSE t.completed_by END END,
+            notes = COALESCE($5, t.notes)
+        FROM employee_checklists c
+        WHERE t.id = $1 AND c.id = t.checklist_id AND c.employee_id = $2
+        RETURNING t.*
+        "#,
+    )
+    .bind(task_id)
+    .bind(employee_id)
+    .bind(req.completed)
+    .bind(ctx.actor_id)
+    .bind(&req.notes)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("Checklist task not found"))?;
+
+    let kind = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE employee_checklists c
+        SET completed_at = CASE
+            WHEN EXISTS (
+                SELECT 1 FROM employee_checklist_tasks
+                WHERE checklist_id = c.id AND completed_at IS NULL
+            ) THEN NULL
+            ELSE COALESCE(c.completed_at, NOW())
+        END
+        WHERE c.id = $1
+        RETURNING c.kind
+        "#,
+    )
+    .bind(task.checklist_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let offboarded = kind == "offboarding" && complete_offboarding(&mut tx, employee_id, ctx.actor_id).await?;
+    let task = map_task_to_response(task);
+
+    let action = if task.completed_at.is_some() {
+        "complete_checklist_task"
+    } else {
+        "reopen_checklist_task"
+    };
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", action, Some(employee_id)).after(&task),
+    )
+    .await?;
+    if offboarded {
+        audit::record_in(
+            &mut tx,
+            ctx,
+            AuditEntry::new("employee", "complete_offboarding", Some(employee_id)),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(task)
+}
+
+/// Sets the employee's termination date and reason and starts their
+/// offboarding checklists, with tasks due relative to the termination date.
+/// Offboarding is completed straight away when there are no tasks and the
+/// date has come.
+pub async fn terminate_employee(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    req: TerminateEmployeeRequest,
+) -> Result<EmployeeResponse> {
+    let mut tx = db.begin().await?;
+
+    let employee = sqlx::query_as::<_, Employee>("SELECT * FROM employees WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("Employee not found"))?;
+
+    if employee.status == "terminated" {
+        return Err(anyhow!("The employee has already been terminated"));
+    }
+    if employee.termination_date.is_some() {
+        return Err(anyhow!("The employee is already being offboarded"));
+    }
+    if req.termination_date < employee.hire_date {
+        return Err(anyhow!("The termination date can't be before the hire date"));
+    }
+    let before = service::get_employee(&mut *tx, id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE employees
+        SET termination_date = $2, termination_reason = $3, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(req.termination_date)
+    .bind(req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty()))
+    .execute(&mut *tx)
+    .await?;
+
+    start_checklists(&mut tx, id, "offboarding", req.termination_date, ctx.actor_id).await?;
+    let offboarded = complete_offboarding(&mut tx, id, ctx.actor_id).await?;
+    let employee = service::get_employee(&mut *tx, id).await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "terminate", Some(id))
+            .before(&before)
+            .after(&employee),
+    )
+    .await?;
+    if offboarded {
+        audit::record_in(
+            &mut tx,
+            ctx,
+            AuditEntry::new("employee", "complete_offboarding", Some(id)),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(employee)
+}
+
+/// Terminates the employee once their termination date has come and every
+/// offboarding task is done: their status changes, recorded in their
+/// history, their user account is deactivated and their pending leave
+/// requests are cancelled. Returns `false`, changing nothing, before then.
+pub async fn complete_offboarding(
+    conn: &mut PgConnection,
+    employee_id: Uuid,
+    changed_by: Option<Uuid>,
+) -> Result<bool> {
+    let employee = sqlx::query_as::<_, Employee>("SELECT * FROM employees WHERE id = $1 FOR UPDATE")
+        .bind(employee_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow!("Employee not found"))?;
+
+    let today = history::today();
+    if employee.status == "terminated" || employee.termination_date.is_none_or(|d| d > today) {
+        return Ok(false);
+    }
+
+    let open_tasks = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM employee_checklist_tasks t
+            JOIN employee_checklists c ON c.id = t.checklist_id
+            WHERE c.employee_id = $1 AND c.kind = 'offboarding' AND t.completed_at IS NULL
+        )
+        "#,
+    )
+    .bind(employee_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if open_tasks {
+        return Ok(false);
+    }
+
+    // Dated today rather than on the termination date, which may be before
+    // changes recorded while the offboarding was underway
+    let change = EmploymentChange {
+        effective_date: today,
+        department_id: None,
+        position_id: None,
+        manager_id: None,
+        salary: None,
+        status: Some("terminated".to_string()),
+        cleared_fields: Vec::new(),
+        reason: employee.termination_reason.clone(),
+    };
+    history::record_event(conn, employee_id, "change", &change, changed_by).await?;
+
+    sqlx::query("UPDATE employees SET status = 'terminated', updated_at = NOW() WHERE id = $1")
+        .bind(employee_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let user_ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM users WHERE person_id = $1 AND deactivated_at IS NULL",
+    )
+    .bind(employee.person_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    for user_id in user_ids {
+        user::service::deactivate_user(conn, user_id).await?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE leave_requests
+        SET status = 'cancelled', notes = 'Cancelled on termination', updated_at = NOW()
+        WHERE employee_id = $1 AND status = 'pending'
+        "#,
+    )
+    .bind(employee_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(true)
+}
+
+/// Completes the offboarding of everyone whose termination date has come
+/// with all their tasks done. Returns who was terminated. One that fails is
+/// left for the next run.
+pub async fn complete_due_offboardings(db: &Db) -> Result<Vec<Uuid>> {
+    let due = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM employees WHERE termination_date <= $1 AND status <> 'terminated'",
+    )
+    .bind(history::today())
+    .fetch_all(db)
+    .await?;
+
+    let mut terminated = Vec::new();
+    for employee_id in due {
+        let mut tx = db.begin().await?;
+        match complete_offboarding(&mut tx, employee_id, None).await {
+            Ok(true) => {
+                audit::record_in(
+                    &mut tx,
+                    &AuditContext::default(),
+                    AuditEntry::new("employee", "complete_offboarding", Some(employee_id)),
+                )
+                .await?;
+                tx.commit().await?;
+                terminated.push(employee_id);
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Offboarding of employee {} not completed: {}", employee_id, e),
+        }
+    }
+    Ok(terminated)
+}
@@ -1,4 +1,8 @@
+pub mod bulk;
 pub mod dto;
 pub mod handlers;
+pub mod history;
+pub mod lifecycle;
+pub mod org_chart;
 pub mod routes;
 pub mod service;
@@ -0,0 +1,377 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, anyhow};
+use sqlx::{FromRow, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    api::employee::dto::{OrgChartEmployee, OrgChartNode, OrgChartStatsResponse, SpanOfControl},
+    db::Db,
+    middlewares::authorize::RowScope,
+};
+
+#[derive(Debug, FromRow)]
+struct OrgChartRow {
+    id: Uuid,
+    employee_id: String,
+    first_name: String,
+    last_name: String,
+    department_id: Option<Uuid>,
+    department_name: Option<String>,
+    position_id: Option<Uuid>,
+    position_name: Option<String>,
+    manager_id: Option<Uuid>,
+}
+
+impl From<OrgChartRow> for OrgChartEmployee {
+    fn from(row: OrgChartRow) -> Self {
+        OrgChartEmployee {
+            id: row.id,
+            employee_id: row.employee_id,
+            first_name: row.first_name,
+            last_name: row.last_name,
+            department_id: row.department_id,
+            department_name: row.department_name,
+            position_id: row.position_id,
+            position_name: row.position_name,
+            manager_id: row.manager_id,
+        }
+    }
+}
+
+/// Checks that `manager_id` can manage the employee (`None` for one being
+/// created): they must be another, active employee who doesn't already
+/// report to them, directly or not.
+pub async fn validate_manager(
+    conn: &mut PgConnection,
+    employee_id: Option<Uuid>,
+    manager_id: Uuid,
+) -> Result<()> {
+    if employee_id == Some(manager_id) {
+        return Err(anyhow!("An employee can't be their own manager"));
+    }
+
+    let status = sqlx::query_scalar::<_, Option<String>>("SELECT status FROM employees WHERE id = $1")
+        .bind(manager_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| anyhow!("Manager not found"))?;
+
+    if status.as_deref() != Some("active") {
+        return Err(anyhow!("The manager must be an active employee"));
+    }
+
+    let Some(employee_id) = employee_id else {
+        return Ok(());
+    };
+
+    // UNION rather than UNION ALL so an existing cycle can't loop forever
+    let reports_to_employee = sqlx::query_scalar::<_, bool>(
+        r#"
+        WITH RECURSIVE chain AS (
+            SELECT id, manager_id FROM employees WHERE id = $1
+            UNION
+            SELECT e.id, e.manager_id FROM employees e
+            JOIN chain c ON e.id = c.manager_id
+        )
+        SELECT EXISTS(SELECT 1 FROM chain WHERE id = $2)
+        "#,
+    )
+    .bind(manager_id)
+    .bind(employee_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if reports_to_employee {
+        return Err(anyhow!(
+            "The manager reports to this employee, which would create a reporting cycle"
+        ));
+    }
+
+    Ok(())
+}
+
+// The active employees under `root` (included whatever their status), or
+// the whole chart, whose roots are the active employees without an active
+// manager. With a scope only the employees within it are kept.
+async fn load_chart(
+    db: &Db,
+    root: Option<Uuid>,
+    scope: Option<&RowScope>,
+) -> Result<Vec<OrgChartEmployee>> {
+    let scoped = scope.and_then(|scope| scope.condition("e.id", 2));
+    let select_query = format!(
+        r#"
+        WITH RECURSIVE chart AS (
+            SELECT e.id, ARRAY[e.id] AS path
+            FROM employees e
+            WHERE CASE
+                WHEN $1::uuid IS NULL THEN
+                    e.status = 'active'
+                    AND NOT EXISTS (
+                        SELECT 1 FROM employees m
+                        WHERE m.id = e.manager_id AND m.status = 'active'
+                    )
+                ELSE e.id = $1
+            END
+            UNION ALL
+            SELECT c.id, chart.path || c.id
+            FROM employees c
+            JOIN chart ON c.manager_id = chart.id
+            WHERE c.status = 'active' AND NOT c.id = ANY(chart.path)
+        )
+        SELECT e.id, e.employee_id, p.first_name, p.last_name,
+               e.department_id, d.name AS department_name,
+               e.position_id, pos.name AS position_name,
+               e.manager_id
+        FROM chart
+        JOIN employees e ON e.id = chart.id
+        JOIN persons p ON p.id = e.person_id
+        LEFT JOIN departments d ON d.id = e.department_id
+        LEFT JOIN positions pos ON pos.id = e.position_id
+        WHERE {}
+        ORDER BY p.last_name, p.first_name
+        "#,
+        scoped.as_deref().unwrap_or("1=1")
+    );
+
+    let mut select_q = sqlx::query_as::<_, OrgChartRow>(&select_query).bind(root);
+    if let (Some(scope), Some(_)) = (scope, &scoped) {
+        select_q = select_q.bind(scope.user_id);
+    }
+    let rows = select_q.fetch_all(db).await?;
+
+    Ok(rows.into_iter().map(OrgChartEmployee::from).collect())
+}
+
+fn build_node(
+    employee: OrgChartEmployee,
+    children: &mut HashMap<Uuid, Vec<OrgChartEmployee>>,
+) -> OrgChartNode {
+    let nodes: Vec<OrgChartNode> = children
+        .remove(&employee.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| build_node(child, children))
+        .collect();
+
+    OrgChartNode {
+        employee,
+        direct_reports: nodes.len(),
+        total_reports: nodes.iter().map(|n| n.total_reports + 1).sum(),
+        children: nodes,
+    }
+}
+
+// Nests the employees under their managers. Anyone whose manager isn't in
+// the list is a root, except under `root` which is the only one.
+fn build_tree(employees: Vec<OrgChartEmployee>, root: Option<Uuid>) -> Vec<OrgChartNode> {
+    let ids: HashSet<Uuid> = employees.iter().map(|e| e.id).collect();
+    let mut roots = Vec::new();
+    let mut children: HashMap<Uuid, Vec<OrgChartEmployee>> = HashMap::new();
+
+    for employee in employees {
+        let is_root = match root {
+            Some(root) => employee.id == root,
+            None => employee.manager_id.is_none_or(|m| !ids.contains(&m)),
+        };
+        if is_root {
+            roots.push(employee);
+        } else if let Some(manager_id) = employee.manager_id {
+            children.entry(manager_id).or_default().push(employee);
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|employee| build_node(employee, &mut children))
+        .collect()
+}
+
+/// Every active employee within the scope nested under their manager. Those
+/// whose manager is out of scope are roots.
+pub async fn get_org_chart(db: &Db, scope: &RowScope) -> Result<Vec<OrgChartNode>> {
+    let employees = load_chart(db, None, Some(scope)).await?;
+    Ok(build_tree(employees, None))
+}
+
+/// The employee with everyone within the scope reporting to them, directly
+/// or through managers also within it.
+pub async fn get_subtree(db: &Db, id: Uuid, scope: &RowScope) -> Result<OrgChartNode> {
+    let employees = load_chart(db, Some(id), Some(scope)).await?;
+    build_tree(employees, Some(id))
+        .pop()
+        .ok_or_else(|| anyhow!("Employee not found"))
+}
+
+/// The employee's managers within the scope, from their direct manager up to
+/// the top.
+pub async fn get_management_chain(db: &Db, id: Uuid, scope: &RowScope) -> Result<Vec<OrgChartEmployee>> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM employees WHERE id = $1)")
+        .bind(id)
+        .fetch_one(db)
+        .await?;
+    if !exists {
+        return Err(anyhow!("Employee not found"));
+    }
+
+    let scoped = scope.condition("e.id", 2);
+    let select_query = format!(
+        r#"
+        WITH RECURSIVE chain AS (
+            SELECT m.id, 1 AS level, ARRAY[e.id, m.id] AS path
+            FROM employees e
+            JOIN employees m ON m.id = e.manager_id
+            WHERE e.id = $1
+            UNION ALL
+            SELECT m.id, chain.level + 1, chain.path || m.id
+            FROM chain
+            JOIN employees e ON e.id = chain.id
+            JOIN employees m ON m.id = e.manager_id
+            WHERE NOT m.id = ANY(chain.path)
+        )
+        SELECT e.id, e.employee_id, p.first_name, p.last_name,
+               e.department_id, d.name AS department_name,
+               e.position_id, pos.name AS position_name,
+               e.manager_id
+        FROM chain
+        JOIN employees e ON e.id = chain.id
+        JOIN persons p ON p.id = e.person_id
+        LEFT JOIN departments d ON d.id = e.department_id
+        LEFT JOIN positions pos ON pos.id = e.position_id
+        WHERE {}
+        ORDER BY chain.level
+        "#,
+        scoped.as_deref().unwrap_or("1=1")
+    );
+
+    let mut select_q = sqlx::query_as::<_, OrgChartRow>(&select_query).bind(id);
+    if scoped.is_some() {
+        select_q = select_q.bind(scope.user_id);
+    }
+    let rows = select_q.fetch_all(db).await?;
+
+    Ok(rows.into_iter().map(OrgChartEmployee::from).collect())
+}
+
+// Adds a span for every manager in the tree, returning its depth
+fn collect_spans(node: &OrgChartNode, spans: &mut Vec<SpanOfControl>) -> usize {
+    let depth = node
+        .children
+        .iter()
+        .map(|child| collect_spans(child, spans) + 1)
+        .max()
+        .unwrap_or(0);
+
+    if node.direct_reports > 0 {
+        spans.push(SpanOfControl {
+            employee: node.employee.clone(),
+            direct_reports: node.direct_reports,
+            total_reports: node.total_reports,
+            depth,
+        });
+    }
+    depth
+}
+
+/// Span of control of every manager on the scoped chart, widest first.
+pub async fn get_org_chart_stats(db: &Db, scope: &RowScope) -> Result<OrgChartStatsResponse> {
+    let tree = get_org_chart(db, scope).await?;
+
+    let mut spans = Vec::new();
+    let max_depth = tree
+        .iter()
+        .map(|root| collect_spans(root, &mut spans) + 1)
+        .max()
+        .unwrap_or(0);
+    spans.sort_by(|a, b| {
+        b.direct_reports
+            .cmp(&a.direct_reports)
+            .then(b.total_reports.cmp(&a.total_reports))
+    });
+
+    let total_employees = tree.iter().map(|root| root.total_reports + 1).sum();
+    let direct_reports: usize = spans.iter().map(|s| s.direct_reports).sum();
+    let average_span = if spans.is_empty() {
+        0.0
+    } else {
+        direct_reports as f64 / spans.len() as f64
+    };
+
+    Ok(OrgChartStatsResponse {
+        total_employees,
+        managers: spans.len(),
+        average_span,
+        max_span: spans.first().map_or(0, |s| s.direct_reports),
+        max_depth,
+        spans,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn employee(id: u128, manager_id: Option<u128>) -> OrgChartEmployee {
+        OrgChartEmployee {
+            id: Uuid::from_u128(id),
+            employee_id: format!("E-{}", id),
+            first_name: "First".to_string(),
+            last_name: "Last".to_string(),
+            department_id: None,
+            department_name: None,
+            position_id: None,
+            position_name: None,
+            manager_id: manager_id.map(Uuid::from_u128),
+        }
+    }
+
+    #[test]
+    fn build_tree_nests_reports_and_counts_them() {
+        let tree = build_tree(
+            vec![employee(1, None), employee(2, Some(1)), employee(3, Some(2)), employee(4, Some(1))],
+            None,
+        );
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].employee.id, Uuid::from_u128(1));
+        assert_eq!(tree[0].direct_reports, 2);
+        assert_eq!(tree[0].total_reports, 3);
+        let middle = tree[0].children.iter().find(|n| n.employee.id == Uuid::from_u128(2)).unwrap();
+        assert_eq!((middle.direct_reports, middle.total_reports), (1, 1));
+    }
+
+    #[test]
+    fn build_tree_makes_roots_of_employees_whose_manager_is_missing() {
+        // 9 is out of scope or inactive, so 2 heads its own tree
+        let tree = build_tree(vec![employee(1, None), employee(2, Some(9)), employee(3, Some(2))], None);
+
+        let mut roots: Vec<Uuid> = tree.iter().map(|n| n.employee.id).collect();
+        roots.sort();
+        assert_eq!(roots, vec![Uuid::from_u128(1), Uuid::from_u128(2)]);
+        assert_eq!(tree.iter().map(|n| n.total_reports + 1).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn build_tree_with_a_root_returns_only_that_subtree() {
+        let tree = build_tree(vec![employee(2, Some(1)), employee(3, Some(2))], Some(Uuid::from_u128(2)));
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].employee.id, Uuid::from_u128(2));
+        assert_eq!(tree[0].total_reports, 1);
+    }
+
+    #[test]
+    fn build_tree_with_a_root_drops_reports_outside_the_scope() {
+        // 2 is out of scope, so neither they nor 3 under them are shown
+        let tree = build_tree(
+            vec![employee(1, None), employee(3, Some(2)), employee(4, Some(1))],
+            Some(Uuid::from_u128(1)),
+        );
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].employee.id, Uuid::from_u128(1));
+        assert_eq!(tree[0].total_reports, 1);
+        assert_eq!(tree[0].children[0].employee.id, Uuid::from_u128(4));
+    }
+}
@@ -1,13 +1,41 @@
-use crate::api::employee::handlers;
+use crate::api::{
+    document::{
+        routes::{document_category_routes, document_report_routes, document_routes},
+        service::DocumentOwner,
+    },
+    employee::handlers,
+};
 use axum::{routing::{delete, get, post, put}, Router};
 
 pub fn employee_routes() -> Router {
     Router::new()
         .route("/", post(handlers::create_employee_handler))
         .route("/", get(handlers::list_employees_handler))
+        .route("/import", post(handlers::import_employees_handler))
+        .route("/export", get(handlers::export_employees_handler))
         .route("/config/descriptors", get(handlers::list_face_descriptors_handler))
+        .route("/as-of", get(handlers::list_employees_as_of_handler))
+        .route("/headcount", get(handlers::headcount_handler))
+        .route("/org-chart", get(handlers::get_org_chart_handler))
+        .route("/org-chart/stats", get(handlers::get_org_chart_stats_handler))
+        .route("/org-chart/{id}", get(handlers::get_org_subtree_handler))
+        .route("/checklist-tasks", get(handlers::list_checklist_tasks_handler))
+        .route("/checklist-templates", get(handlers::list_checklist_templates_handler))
+        .route("/checklist-templates", post(handlers::create_checklist_template_handler))
+        .route("/checklist-templates/{id}", get(handlers::get_checklist_template_handler))
+        .route("/checklist-templates/{id}", put(handlers::update_checklist_template_handler))
+        .route("/checklist-templates/{id}", delete(handlers::delete_checklist_template_handler))
+        .nest("/documents", document_report_routes(DocumentOwner::Employee))
+        .nest("/document-categories", document_category_routes())
         .route("/{id}/face-descriptor", post(handlers::update_face_descriptor_handler))
         .route("/{id}", get(handlers::get_employee_handler))
         .route("/{id}", put(handlers::update_employee_handler))
         .route("/{id}", delete(handlers::delete_employee_handler))
+        .route("/{id}/history", get(handlers::get_employee_history_handler))
+        .route("/{id}/management-chain", get(handlers::get_management_chain_handler))
+        .route("/{id}/history/{event_id}", delete(handlers::cancel_employment_event_handler))
+        .route("/{id}/terminate", post(handlers::terminate_employee_handler))
+        .route("/{id}/checklists", get(handlers::list_employee_checklists_handler))
+        .route("/{id}/checklists/tasks/{task_id}", put(handlers::update_checklist_task_handler))
+        .nest("/{id}/documents", document_routes(DocumentOwner::Employee))
 }
@@ -1,24 +1,57 @@
 use crate::{
-    api::employee::dto::{
-        CreateEmployeeRequest, EmployeeResponse, ListEmployeesQuery, ListEmployeesResponse,
-        UpdateEmployeeRequest,
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        employee::{
+            dto::{
+                CreateEmployeeRequest, EmployeeResponse, ListEmployeesQuery, ListEmployeesResponse,
+                UpdateEmployeeRequest,
+            },
+            history::{self, EmploymentChange},
+            lifecycle, org_chart,
+        },
     },
     db::Db,
-    models::employee::EmployeeWithPerson,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    models::employee::{Employee, EmployeeWithPerson},
 };
 use anyhow::{anyhow, Result};
 
-use sqlx::types::BigDecimal;
+use sqlx::{PgConnection, PgExecutor, types::BigDecimal};
 use std::str::FromStr;
 use uuid::Uuid;
 
-pub async fn create_employee(db: &Db, req: CreateEmployeeRequest) -> Result<EmployeeResponse> {
+/// Starts the employee's history with a hire event on `hire_date`, and
+/// their onboarding checklists.
+pub async fn create_employee(
+    db: &Db,
+    ctx: &AuditContext,
+    req: CreateEmployeeRequest,
+) -> Result<EmployeeResponse> {
+    let mut tx = db.begin().await?;
+    let employee = create_employee_in(&mut tx, req, ctx.actor_id).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "create", Some(employee.id)).after(&employee),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(employee)
+}
+
+/// `create_employee` within the caller's transaction.
+pub async fn create_employee_in(
+    conn: &mut PgConnection,
+    req: CreateEmployeeRequest,
+    created_by: Option<Uuid>,
+) -> Result<EmployeeResponse> {
     // Verify person exists
     let person_exists = sqlx::query_scalar::<_, bool>(
         "SELECT EXISTS(SELECT 1 FROM persons WHERE id = $1)"
     )
     .bind(req.person_id)
-    .fetch_one(db)
+    .fetch_one(&mut *conn)
     .await?;
 
     if !person_exists {
@@ -30,7 +63,7 @@ pub async fn create_employee(db: &Db, req: CreateEmployeeRequest) -> Result<Empl
         "SELECT EXISTS(SELECT 1 FROM employees WHERE employee_id = $1)"
     )
     .bind(&req.employee_id)
-    .fetch_one(db)
+    .fetch_one(&mut *conn)
     .await?;
 
     if employee_id_exists {
@@ -39,6 +72,10 @@ pub async fn create_employee(db: &Db, req: CreateEmployeeRequest) -> Result<Empl
 
     let salary = req.salary.map(|s| BigDecimal::from_str(&s.to_string()).unwrap());
 
+    if let Some(manager_id) = req.manager_id {
+        org_chart::validate_manager(conn, None, manager_id).await?;
+    }
+
     let employee = sqlx::query_as::<_, EmployeeWithPerson>(
         r#"
         WITH new_emp AS (
@@ -52,7 +89,8 @@ pub async fn create_employee(db: &Db, req: CreateEmployeeRequest) -> Result<Empl
                pc.email, pc.phone,
                e.department_id, e.position_id, e.hire_date,
                e.employment_type, e.salary, e.manager_id,
-               e.status, e.created_at, e.updated_at
+               e.status, e.termination_date, e.termination_reason,
+               e.created_at, e.updated_at
         FROM new_emp e
         JOIN persons p ON p.id = e.person_id
         LEFT JOIN person_contacts pc ON pc.person_id = p.id
@@ -60,19 +98,32 @@ pub async fn create_employee(db: &Db, req: CreateEmployeeRequest) -> Result<Empl
     )
     .bind(&req.employee_id)
     .bind(req.person_id)
-    .bind(&req.department)
-    .bind(&req.position)
+    .bind(req.department)
+    .bind(req.position)
     .bind(req.hire_date)
     .bind(&req.employment_type)
-    .bind(salary)
+    .bind(&salary)
     .bind(req.manager_id)
-    .fetch_one(db)
+    .fetch_one(&mut *conn)
     .await?;
 
+    let hire = EmploymentChange {
+        effective_date: req.hire_date,
+        department_id: req.department,
+        position_id: req.position,
+        manager_id: req.manager_id,
+        salary,
+        status: Some(employee.status.clone()),
+        cleared_fields: Vec::new(),
+        reason: None,
+    };
+    history::record_event(conn, employee.id, "hire", &hire, created_by).await?;
+    lifecycle::start_checklists(conn, employee.id, "onboarding", req.hire_date, created_by).await?;
+
     Ok(map_employee_to_response(employee))
 }
 
-pub async fn get_employee(db: &Db, id: Uuid) -> Result<EmployeeResponse> {
+pub async fn get_employee<'e, E: PgExecutor<'e>>(db: E, id: Uuid) -> Result<EmployeeResponse> {
     let employee = sqlx::query_as::<_, EmployeeWithPerson>(
         r#"
         SELECT e.id, e.employee_id, e.person_id, 
@@ -80,7 +131,8 @@ pub async fn get_employee(db: &Db, id: Uuid) -> Result<EmployeeResponse> {
                pc.email, pc.phone,
                e.department_id, e.position_id, e.hire_date,
                e.employment_type, e.salary, e.manager_id,
-               e.status, e.created_at, e.updated_at
+               e.status, e.termination_date, e.termination_reason,
+               e.created_at, e.updated_at
         FROM employees e
         JOIN persons p ON p.id = e.person_id
         LEFT JOIN person_contacts pc ON pc.person_id = p.id
@@ -95,8 +147,10 @@ pub async fn get_employee(db: &Db, id: Uuid) -> Result<EmployeeResponse> {
     Ok(map_employee_to_response(employee))
 }
 
+/// Only returns records within `scope`.
 pub async fn list_employees(
     db: &Db,
+    scope: RowScope,
     query: ListEmployeesQuery,
 ) -> Result<ListEmployeesResponse> {
     let page = query.page.unwrap_or(1).max(1);
@@ -106,6 +160,12 @@ pub async fn list_employees(
     let mut conditions: Vec<String> = vec!["1=1".to_string()];
     let mut param_index = 1;
 
+    let scoped = scope.condition("e.id", param_index);
+    if let Some(condition) = &scoped {
+        conditions.push(condition.clone());
+        param_index += 1;
+    }
+
     if query.department.is_some() {
         conditions.push(format!("e.department_id = ${}", param_index));
         param_index += 1;
@@ -144,12 +204,13 @@ pub async fn list_employees(
                pc.email, pc.phone,
                e.department_id, e.position_id, e.hire_date,
                e.employment_type, e.salary, e.manager_id,
-               e.status, e.created_at, e.updated_at
+               e.status, e.termination_date, e.termination_reason,
+               e.created_at, e.updated_at
         FROM employees e
         JOIN persons p ON p.id = e.person_id
         LEFT JOIN person_contacts pc ON pc.person_id = p.id
         WHERE {}
-        ORDER BY e.created_at DESC
+        ORDER BY e.created_at DESC, e.id
         LIMIT ${} OFFSET ${}
         "#,
         where_clause, param_index, param_index + 1
@@ -158,6 +219,11 @@ pub async fn list_employees(
     let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
     let mut select_q = sqlx::query_as::<_, EmployeeWithPerson>(&select_query);
 
+    if scoped.is_some() {
+        count_q = count_q.bind(scope.user_id);
+        select_q = select_q.bind(scope.user_id);
+    }
+
     if let Some(dept) = &query.department {
         count_q = count_q.bind(dept);
         select_q = select_q.bind(dept);
@@ -188,21 +254,98 @@ pub async fn list_employees(
     })
 }
 
+// The columns named by the request's `clear`, which uses the request's own
+// field names. A field can't be both set and cleared.
+fn cleared_columns(req: &UpdateEmployeeRequest) -> Result<Vec<&'static str>> {
+    let mut columns = Vec::new();
+    for field in &req.clear {
+        let (column, set) = match field.as_str() {
+            "department" => ("department_id", req.department.is_some()),
+            "position" => ("position_id", req.position.is_some()),
+            "managerId" => ("manager_id", req.manager_id.is_some()),
+            "salary" => ("salary", req.salary.is_some()),
+            other => return Err(anyhow!("{} can't be cleared", other)),
+        };
+        if set {
+            return Err(anyhow!("{} can't be both set and cleared", field));
+        }
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    Ok(columns)
+}
+
+/// Department, position, manager, salary and status changes are recorded
+/// in the employee's history. Dated in the future, they are only scheduled
+/// and the employee keeps their current values until then.
 pub async fn update_employee(
     db: &Db,
+    ctx: &AuditContext,
     id: Uuid,
     req: UpdateEmployeeRequest,
 ) -> Result<EmployeeResponse> {
     let salary = req.salary.map(|s| BigDecimal::from_str(&s.to_string()).unwrap());
 
+    let clear = cleared_columns(&req)?;
+
+    let mut tx = db.begin().await?;
+
+    let current = sqlx::query_as::<_, Employee>("SELECT * FROM employees WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("Employee not found"))?;
+    let before = get_employee(&mut *tx, id).await?;
+
+    // Only what actually changes goes into the history
+    let is_set = |column: &str| match column {
+        "department_id" => current.department_id.is_some(),
+        "position_id" => current.position_id.is_some(),
+        "manager_id" => current.manager_id.is_some(),
+        _ => current.salary.is_some(),
+    };
+    let change = EmploymentChange {
+        effective_date: req.effective_date.unwrap_or_else(history::today),
+        department_id: req.department.filter(|d| current.department_id != Some(*d)),
+        position_id: req.position.filter(|p| current.position_id != Some(*p)),
+        manager_id: req.manager_id.filter(|m| current.manager_id != Some(*m)),
+        salary: salary.filter(|s| current.salary.as_ref() != Some(s)),
+        status: req.status.filter(|s| current.status != *s),
+        cleared_fields: clear.into_iter().filter(|c| is_set(c)).map(String::from).collect(),
+        reason: req.reason,
+    };
+
+    if let Some(manager_id) = change.manager_id {
+        org_chart::validate_manager(&mut tx, Some(id), manager_id).await?;
+    }
+
+    let scheduled = change.effective_date > history::today();
+    if !change.is_empty() {
+        history::record_event(&mut tx, id, "change", &change, ctx.actor_id).await?;
+    }
+    let applied = if scheduled {
+        EmploymentChange {
+            department_id: None,
+            position_id: None,
+            manager_id: None,
+            salary: None,
+            status: None,
+            cleared_fields: Vec::new(),
+            ..change
+        }
+    } else {
+        change
+    };
+
     let employee = sqlx::query_as::<_, EmployeeWithPerson>(
         r#"
         UPDATE employees e
-        SET department_id = COALESCE($2, e.department_id),
-            position_id = COALESCE($3, e.position_id),
+        SET department_id = CASE WHEN 'department_id' = ANY($8) THEN NULL ELSE COALESCE($2, e.department_id) END,
+            position_id = CASE WHEN 'position_id' = ANY($8) THEN NULL ELSE COALESCE($3, e.position_id) END,
             employment_type = COALESCE($4, e.employment_type),
-            salary = COALESCE($5, e.salary),
-            manager_id = COALESCE($6, e.manager_id),
+            salary = CASE WHEN 'salary' = ANY($8) THEN NULL ELSE COALESCE($5, e.salary) END,
+            manager_id = CASE WHEN 'manager_id' = ANY($8) THEN NULL ELSE COALESCE($6, e.manager_id) END,
             status = COALESCE($7, e.status),
             updated_at = NOW()
         FROM persons p
@@ -213,33 +356,72 @@ pub async fn update_employee(
                   pc.email, pc.phone,
                   e.department_id, e.position_id, e.hire_date,
                   e.employment_type, e.salary, e.manager_id,
-                  e.status, e.created_at, e.updated_at
+                  e.status, e.termination_date, e.termination_reason,
+                  e.created_at, e.updated_at
         "#,
     )
     .bind(id)
-    .bind(&req.department)
-    .bind(&req.position)
+    .bind(applied.department_id)
+    .bind(applied.position_id)
     .bind(&req.employment_type)
-    .bind(salary)
-    .bind(req.manager_id)
-    .bind(&req.status)
-    .fetch_optional(db)
+    .bind(applied.salary)
+    .bind(applied.manager_id)
+    .bind(&applied.status)
+    .bind(&applied.cleared_fields)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| anyhow!("Employee not found"))?;
-
-    Ok(map_employee_to_response(employee))
+    let employee = map_employee_to_response(employee);
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "update", Some(id))
+            .before(&before)
+            .after(&employee),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(employee)
 }
 
-pub async fn delete_employee(db: &Db, id: Uuid) -> Result<()> {
-    let result = sqlx::query("UPDATE employees SET status = 'inactive', updated_at = NOW() WHERE id = $1")
-        .bind(id)
-        .execute(db)
-        .await?;
+/// Deactivates the employee from today, recorded in their history.
+pub async fn delete_employee(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
 
-    if result.rows_affected() == 0 {
-        return Err(anyhow!("Employee not found"));
+    let status = sqlx::query_scalar::<_, Option<String>>("SELECT status FROM employees WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("Employee not found"))?;
+    let before = get_employee(&mut *tx, id).await?;
+
+    if status.as_deref() != Some("inactive") {
+        let change = EmploymentChange {
+            effective_date: history::today(),
+            department_id: None,
+            position_id: None,
+            manager_id: None,
+            salary: None,
+            status: Some("inactive".to_string()),
+            cleared_fields: Vec::new(),
+            reason: None,
+        };
+        history::record_event(&mut tx, id, "change", &change, ctx.actor_id).await?;
     }
 
+    sqlx::query("UPDATE employees SET status = 'inactive', updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "delete", Some(id)).before(&before),
+    )
+    .await?;
+    tx.commit().await?;
     Ok(())
 }
 
@@ -260,20 +442,37 @@ fn map_employee_to_response(emp: EmployeeWithPerson) -> EmployeeResponse {
         salary: emp.salary.and_then(|s| s.to_string().parse().ok()),
         manager_id: emp.manager_id,
         status: emp.status,
+        termination_date: emp.termination_date,
+        termination_reason: emp.termination_reason,
     }
 }
 
-pub async fn update_face_descriptor(db: &Db, id: Uuid, descriptor: String) -> Result<()> {
+pub async fn update_face_descriptor(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    descriptor: String,
+) -> Result<()> {
+    let mut tx = db.begin().await?;
     let result = sqlx::query("UPDATE employees SET face_descriptor = $1, updated_at = NOW() WHERE id = $2")
         .bind(descriptor)
         .bind(id)
-        .execute(db)
+        .execute(&mut *tx)
         .await?;
 
     if result.rows_affected() == 0 {
         return Err(anyhow!("Employee not found"));
     }
 
+    // The descriptor itself is biometric data and stays out of the log
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("employee", "update_face_descriptor", Some(id)),
+    )
+    .await?;
+    tx.commit().await?;
+
     Ok(())
 }
 
@@ -286,3 +485,29 @@ pub async fn get_all_face_descriptors(db: &Db) -> Result<Vec<(String, String)>>
     Ok(rows)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(body: serde_json::Value) -> UpdateEmployeeRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn cleared_columns_maps_request_names_to_columns() {
+        let req = request(serde_json::json!({"clear": ["managerId", "department", "managerId"]}));
+        assert_eq!(cleared_columns(&req).unwrap(), vec!["manager_id", "department_id"]);
+        assert!(cleared_columns(&request(serde_json::json!({}))).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cleared_columns_rejects_unknown_and_set_fields() {
+        assert!(cleared_columns(&request(serde_json::json!({"clear": ["status"]}))).is_err());
+        let req = request(serde_json::json!({
+            "clear": ["managerId"],
+            "managerId": "00000000-0000-0000-0000-0000000000e1"
+        }));
+        assert!(cleared_columns(&req).is_err());
+    }
+}
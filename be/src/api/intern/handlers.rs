@@ -1,9 +1,12 @@
 use crate::{
-    api::intern::{
-        dto::{CreateInternRequest, ListInternsQuery, UpdateInternRequest},
-        service,
+    api::{
+        intern::{
+            dto::{CreateInternRequest, ListInternsQuery, UpdateInternRequest},
+            service,
+        },
     },
     db::Db,
+    extractors::AuditContext,
 };
 use axum::{
     extract::{Extension, Path, Query},
@@ -15,9 +18,10 @@ use uuid::Uuid;
 
 pub async fn create_intern_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Json(payload): Json<CreateInternRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let intern = service::create_intern(&db, payload)
+    let intern = service::create_intern(&db, &ctx, payload)
         .await
         .map_err(|_| StatusCode::BAD_REQUEST)?;
     Ok((StatusCode::CREATED, Json(json!(intern))))
@@ -45,10 +49,11 @@ pub async fn list_interns_handler(
 
 pub async fn update_intern_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateInternRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let intern = service::update_intern(&db, id, payload)
+    let intern = service::update_intern(&db, &ctx, id, payload)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((StatusCode::OK, Json(json!(intern))))
@@ -56,9 +61,10 @@ pub async fn update_intern_handler(
 
 pub async fn delete_intern_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::delete_intern(&db, id)
+    service::delete_intern(&db, &ctx, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((
@@ -1,11 +1,19 @@
-use crate::api::intern::handlers;
+use crate::api::{
+    document::{
+        routes::{document_report_routes, document_routes},
+        service::DocumentOwner,
+    },
+    intern::handlers,
+};
 use axum::{routing::{delete, get, post, put}, Router};
 
 pub fn intern_routes() -> Router {
     Router::new()
         .route("/", post(handlers::create_intern_handler))
         .route("/", get(handlers::list_interns_handler))
+        .nest("/documents", document_report_routes(DocumentOwner::Intern))
         .route("/{id}", get(handlers::get_intern_handler))
         .route("/{id}", put(handlers::update_intern_handler))
         .route("/{id}", delete(handlers::delete_intern_handler))
+        .nest("/{id}/documents", document_routes(DocumentOwner::Intern))
 }
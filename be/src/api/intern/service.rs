@@ -3,15 +3,17 @@ use crate::{
         CreateInternRequest, InternResponse, ListInternsQuery, ListInternsResponse,
         UpdateInternRequest,
     },
+    api::audit::service::{self as audit, AuditEntry},
     db::Db,
+    extractors::AuditContext,
     models::intern::InternWithPerson,
 };
 use anyhow::{anyhow, Result};
-use sqlx::types::BigDecimal;
+use sqlx::{types::BigDecimal, PgExecutor};
 use std::str::FromStr;
 use uuid::Uuid;
 
-pub async fn create_intern(db: &Db, req: CreateInternRequest) -> Result<InternResponse> {
+pub async fn create_intern(db: &Db, ctx: &AuditContext, req: CreateInternRequest) -> Result<InternResponse> {
     // Verify person exists
     let person_exists = sqlx::query_scalar::<_, bool>(
         "SELECT EXISTS(SELECT 1 FROM persons WHERE id = $1)"
@@ -38,6 +40,7 @@ pub async fn create_intern(db: &Db, req: CreateInternRequest) -> Result<InternRe
 
     let stipend = req.stipend.map(|s| BigDecimal::from_str(&s.to_string()).unwrap());
 
+    let mut tx = db.begin().await?;
     let intern = sqlx::query_as::<_, InternWithPerson>(
         r#"
         WITH new_int AS (
@@ -65,13 +68,22 @@ pub async fn create_intern(db: &Db, req: CreateInternRequest) -> Result<InternRe
     .bind(req.end_date)
     .bind(stipend)
     .bind(&req.university)
-    .fetch_one(db)
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok(map_intern_to_response(intern))
+    let intern = map_intern_to_response(intern);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("intern", "create", Some(intern.id)).after(&intern),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(intern)
 }
 
-pub async fn get_intern(db: &Db, id: Uuid) -> Result<InternResponse> {
+pub async fn get_intern<'e, E: PgExecutor<'e>>(db: E, id: Uuid) -> Result<InternResponse> {
     let intern = sqlx::query_as::<_, InternWithPerson>(
         r#"
         SELECT i.id, i.intern_id, i.person_id, 
@@ -101,8 +113,7 @@ pub async fn list_interns(db: &Db, query: ListInternsQuery) -> Result<ListIntern
 
     let mut conditions: Vec<String> = vec!["1=1".to_string()];
     let mut count_query = "SELECT COUNT(*) FROM interns i".to_string();
-    let mut select_query = format!(
-        r#"
+    let mut select_query = r#"
         SELECT i.id, i.intern_id, i.person_id, 
                p.first_name, p.middle_name, p.last_name,
                pc.email, pc.phone,
@@ -114,7 +125,7 @@ pub async fn list_interns(db: &Db, query: ListInternsQuery) -> Result<ListIntern
         LEFT JOIN person_contacts pc ON pc.person_id = p.id
         WHERE 1=1
         "#
-    );
+    .to_string();
 
     if query.department.is_some() {
         conditions.push("i.department = $3".to_string());
@@ -176,9 +187,16 @@ pub async fn list_interns(db: &Db, query: ListInternsQuery) -> Result<ListIntern
     })
 }
 
-pub async fn update_intern(db: &Db, id: Uuid, req: UpdateInternRequest) -> Result<InternResponse> {
+pub async fn update_intern(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    req: UpdateInternRequest,
+) -> Result<InternResponse> {
     let stipend = req.stipend.map(|s| BigDecimal::from_str(&s.to_string()).unwrap());
 
+    let mut tx = db.begin().await?;
+    let before = get_intern(&mut *tx, id).await?;
     let intern = sqlx::query_as::<_, InternWithPerson>(
         r#"
         UPDATE interns i
@@ -207,22 +225,39 @@ pub async fn update_intern(db: &Db, id: Uuid, req: UpdateInternRequest) -> Resul
     .bind(stipend)
     .bind(&req.university)
     .bind(&req.status)
-    .fetch_optional(db)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| anyhow!("Intern not found"))?;
 
-    Ok(map_intern_to_response(intern))
+    let intern = map_intern_to_response(intern);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("intern", "update", Some(id))
+            .before(&before)
+            .after(&intern),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(intern)
 }
 
-pub async fn delete_intern(db: &Db, id: Uuid) -> Result<()> {
-    let result = sqlx::query("UPDATE interns SET status = 'terminated', updated_at = NOW() WHERE id = $1")
+pub async fn delete_intern(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let before = get_intern(&mut *tx, id).await?;
+    sqlx::query("UPDATE interns SET status = 'terminated', updated_at = NOW() WHERE id = $1")
         .bind(id)
-        .execute(db)
+        .execute(&mut *tx)
         .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(anyhow!("Intern not found"));
-    }
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("intern", "delete", Some(id)).before(&before),
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok(())
 }
@@ -1,9 +1,13 @@
 use crate::{
-    api::leave::{
-        dto::{ApproveRejectLeaveRequest, CreateLeaveRequestRequest, ListLeaveRequestsQuery},
-        service,
+    api::{
+        leave::{
+            dto::{ApproveRejectLeaveRequest, CreateLeaveRequestRequest, ListLeaveRequestsQuery},
+            service,
+        },
     },
     db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
     models::user::User,
 };
 use axum::{
@@ -16,9 +20,18 @@ use uuid::Uuid;
 
 pub async fn create_leave_request_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Json(payload): Json<CreateLeaveRequestRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let leave_request = service::create_leave_request(&db, payload)
+    if !scope
+        .includes(&db, payload.employee_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let leave_request = service::create_leave_request(&db, &ctx, payload)
         .await
         .map_err(|_| StatusCode::BAD_REQUEST)?;
     Ok((StatusCode::CREATED, Json(json!(leave_request))))
@@ -26,19 +39,28 @@ pub async fn create_leave_request_handler(
 
 pub async fn get_leave_request_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
     let leave_request = service::get_leave_request(&db, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scope
+        .includes(&db, leave_request.employee_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
     Ok((StatusCode::OK, Json(json!(leave_request))))
 }
 
 pub async fn list_leave_requests_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Query(query): Query<ListLeaveRequestsQuery>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let response = service::list_leave_requests(&db, query)
+    let response = service::list_leave_requests(&db, scope, query)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok((StatusCode::OK, Json(json!(response))))
@@ -47,10 +69,22 @@ pub async fn list_leave_requests_handler(
 pub async fn approve_leave_handler(
     Extension(db): Extension<Db>,
     Extension(user): Extension<User>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<ApproveRejectLeaveRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let leave_request = service::approve_leave(&db, id, user.id, payload)
+    let existing = service::get_leave_request(&db, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scope
+        .includes(&db, existing.employee_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let leave_request = service::approve_leave(&db, &ctx, id, user.id, payload)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((StatusCode::OK, Json(json!(leave_request))))
@@ -59,10 +93,22 @@ pub async fn approve_leave_handler(
 pub async fn reject_leave_handler(
     Extension(db): Extension<Db>,
     Extension(user): Extension<User>,
+    Extension(scope): Extension<RowScope>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<ApproveRejectLeaveRequest>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let leave_request = service::reject_leave(&db, id, user.id, payload)
+    let existing = service::get_leave_request(&db, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scope
+        .includes(&db, existing.employee_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let leave_request = service::reject_leave(&db, &ctx, id, user.id, payload)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     Ok((StatusCode::OK, Json(json!(leave_request))))
@@ -79,8 +125,16 @@ pub async fn get_leave_types_handler(
 
 pub async fn get_leave_balance_handler(
     Extension(db): Extension<Db>,
+    Extension(scope): Extension<RowScope>,
     Path(employee_id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    if !scope
+        .includes(&db, employee_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
     let balances = service::get_leave_balance(&db, employee_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -4,17 +4,22 @@ use crate::{
         LeaveRequestResponse, LeaveTypeResponse, ListLeaveRequestsQuery,
         ListLeaveRequestsResponse,
     },
+    api::audit::service::{self as audit, AuditEntry},
     db::Db,
+    extractors::AuditContext,
+    middlewares::authorize::RowScope,
+    mail::{outbox, templates::EmailTemplate},
     models::leave::{LeaveRequestWithDetails, LeaveType},
 };
 use anyhow::{anyhow, Result};
 
-use sqlx::types::BigDecimal;
+use sqlx::{types::BigDecimal, PgExecutor};
 use std::str::FromStr;
 use uuid::Uuid;
 
 pub async fn create_leave_request(
     db: &Db,
+    ctx: &AuditContext,
     req: CreateLeaveRequestRequest,
 ) -> Result<LeaveRequestResponse> {
     // Validate dates
@@ -25,6 +30,7 @@ pub async fn create_leave_request(
     // Calculate total days
     let total_days = (req.end_date - req.start_date).num_days() as f64 + 1.0;
 
+    let mut tx = db.begin().await?;
     let leave_id: Uuid = sqlx::query_scalar(
         r#"
         INSERT INTO leave_requests (employee_id, leave_type_id, start_date, end_date, 
@@ -39,14 +45,23 @@ pub async fn create_leave_request(
     .bind(req.end_date)
     .bind(BigDecimal::from_str(&total_days.to_string()).unwrap())
     .bind(&req.reason)
-    .fetch_one(db)
+    .fetch_one(&mut *tx)
     .await?;
 
     // Fetch the complete leave request with details
-    get_leave_request(db, leave_id).await
+    let leave_request = get_leave_request(&mut *tx, leave_id).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("leave_request", "create", Some(leave_id)).after(&leave_request),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(leave_request)
 }
 
-pub async fn get_leave_request(db: &Db, id: Uuid) -> Result<LeaveRequestResponse> {
+pub async fn get_leave_request<'e, E: PgExecutor<'e>>(db: E, id: Uuid) -> Result<LeaveRequestResponse> {
     let leave_request = sqlx::query_as::<_, LeaveRequestWithDetails>(
         r#"
         SELECT lr.id, lr.employee_id,
@@ -73,8 +88,10 @@ pub async fn get_leave_request(db: &Db, id: Uuid) -> Result<LeaveRequestResponse
     Ok(map_leave_request_to_response(leave_request))
 }
 
+/// Only returns records within `scope`.
 pub async fn list_leave_requests(
     db: &Db,
+    scope: RowScope,
     query: ListLeaveRequestsQuery,
 ) -> Result<ListLeaveRequestsResponse> {
     let page = query.page.unwrap_or(1).max(1);
@@ -84,6 +101,12 @@ pub async fn list_leave_requests(
     let mut where_clauses: Vec<String> = vec!["1=1".to_string()];
     let mut param_index = 1;
 
+    let scoped = scope.condition("lr.employee_id", param_index);
+    if let Some(condition) = &scoped {
+        where_clauses.push(condition.clone());
+        param_index += 1;
+    }
+
     if query.employee_id.is_some() {
         where_clauses.push(format!("lr.employee_id = ${}", param_index));
         param_index += 1;
@@ -132,6 +155,11 @@ pub async fn list_leave_requests(
     let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
     let mut select_q = sqlx::query_as::<_, LeaveRequestWithDetails>(&select_query);
 
+    if scoped.is_some() {
+        count_q = count_q.bind(scope.user_id);
+        select_q = select_q.bind(scope.user_id);
+    }
+
     if let Some(emp_id) = query.employee_id {
         count_q = count_q.bind(emp_id);
         select_q = select_q.bind(emp_id);
@@ -168,10 +196,13 @@ pub async fn list_leave_requests(
 
 pub async fn approve_leave(
     db: &Db,
+    ctx: &AuditContext,
     id: Uuid,
     approver_id: Uuid,
     req: ApproveRejectLeaveRequest,
 ) -> Result<LeaveRequestResponse> {
+    let mut tx = db.begin().await?;
+    let before = get_leave_request(&mut *tx, id).await?;
     let leave_request = sqlx::query_as::<_, LeaveRequestWithDetails>(
         r#"
         UPDATE leave_requests lr
@@ -199,19 +230,34 @@ pub async fn approve_leave(
     .bind(id)
     .bind(approver_id)
     .bind(&req.notes)
-    .fetch_optional(db)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| anyhow!("Leave request not found"))?;
 
-    Ok(map_leave_request_to_response(leave_request))
+    let response = map_leave_request_to_response(leave_request);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("leave_request", "approve", Some(id))
+            .before(&before)
+            .after(&response),
+    )
+    .await?;
+    tx.commit().await?;
+
+    notify_leave_decision(db, &response).await;
+    Ok(response)
 }
 
 pub async fn reject_leave(
     db: &Db,
+    ctx: &AuditContext,
     id: Uuid,
     approver_id: Uuid,
     req: ApproveRejectLeaveRequest,
 ) -> Result<LeaveRequestResponse> {
+    let mut tx = db.begin().await?;
+    let before = get_leave_request(&mut *tx, id).await?;
     let leave_request = sqlx::query_as::<_, LeaveRequestWithDetails>(
         r#"
         UPDATE leave_requests lr
@@ -239,11 +285,23 @@ pub async fn reject_leave(
     .bind(id)
     .bind(approver_id)
     .bind(&req.notes)
-    .fetch_optional(db)
+    .fetch_optional(&mut *tx)
     .await?
     .ok_or_else(|| anyhow!("Leave request not found"))?;
 
-    Ok(map_leave_request_to_response(leave_request))
+    let response = map_leave_request_to_response(leave_request);
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("leave_request", "reject", Some(id))
+            .before(&before)
+            .after(&response),
+    )
+    .await?;
+    tx.commit().await?;
+
+    notify_leave_decision(db, &response).await;
+    Ok(response)
 }
 
 pub async fn get_leave_types(db: &Db) -> Result<Vec<LeaveTypeResponse>> {
@@ -294,6 +352,53 @@ pub async fn get_leave_balance(db: &Db, employee_id: Uuid) -> Result<Vec<LeaveBa
         .collect())
 }
 
+// Queues the approved/rejected email for the employee. A missing contact or
+// queue failure is logged but never undoes the decision itself.
+async fn notify_leave_decision(db: &Db, leave: &LeaveRequestResponse) {
+    let email = match sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT pc.email
+        FROM employees e
+        JOIN person_contacts pc ON pc.person_id = e.person_id
+        WHERE e.id = $1
+        LIMIT 1
+        "#,
+    )
+    .bind(leave.employee_id)
+    .fetch_optional(db)
+    .await
+    {
+        Ok(Some(email)) => email,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to look up email for leave {}: {}", leave.id, e);
+            return;
+        }
+    };
+
+    let template = if leave.status == "approved" {
+        EmailTemplate::LeaveApproved {
+            employee_name: &leave.employee_name,
+            leave_type: &leave.leave_type_name,
+            start_date: leave.start_date,
+            end_date: leave.end_date,
+            notes: leave.notes.as_deref(),
+        }
+    } else {
+        EmailTemplate::LeaveRejected {
+            employee_name: &leave.employee_name,
+            leave_type: &leave.leave_type_name,
+            start_date: leave.start_date,
+            end_date: leave.end_date,
+            notes: leave.notes.as_deref(),
+        }
+    };
+
+    if let Err(e) = outbox::enqueue(db, &template.render(&email)).await {
+        tracing::warn!("Failed to queue email for leave {}: {}", leave.id, e);
+    }
+}
+
 fn map_leave_request_to_response(req: LeaveRequestWithDetails) -> LeaveRequestResponse {
     LeaveRequestResponse {
         id: req.id,
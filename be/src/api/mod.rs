@@ -1,6 +1,8 @@
 pub mod attendance;
+pub mod audit;
 pub mod auth;
 pub mod department;
+pub mod document;
 pub mod employee;
 pub mod home;
 pub mod intern;
@@ -9,4 +11,5 @@ pub mod navigation;
 pub mod permissions;
 pub mod person;
 pub mod position;
+pub mod role;
 pub mod user;
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateNavigationItemDto {
@@ -49,3 +50,42 @@ pub struct UserNavigationItemDto {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<UserNavigationItemDto>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct MoveNavigationItemDto {
+    // None moves the item to the top level
+    pub parent_id: Option<Uuid>,
+    // Zero-based place among the new siblings; the end when omitted
+    pub position: Option<usize>,
+}
+
+/// A node of the full tree sent to reorder the menu. Siblings are ordered as
+/// listed.
+#[derive(Debug, Deserialize)]
+pub struct NavigationOrderNodeDto {
+    pub id: Uuid,
+    #[serde(default)]
+    pub children: Vec<NavigationOrderNodeDto>,
+}
+
+/// A node of the exported menu tree. Nodes are matched by path on import, so
+/// the same file can seed any environment and be checked against the
+/// frontend menu.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NavigationTreeNodeDto {
+    pub name: String,
+    pub path: String,
+    pub icon: Option<String>,
+    // Active when omitted
+    pub is_active: Option<bool>,
+    // Names by locale, e.g. {"ne": "कर्मचारी"}
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<NavigationTreeNodeDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetNavigationTranslationDto {
+    pub name: String,
+}
@@ -1,8 +1,14 @@
-use crate::{api::navigation::{dto::*, service}, db::Db};
-use crate::models::user::User;
+use crate::{
+    api::{
+        navigation::{dto::*, service},
+    },
+    db::Db,
+    extractors::AuditContext,
+};
+use crate::{i18n::Locale, models::user::User};
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{header, StatusCode},
     Json,
 };
 use serde_json::json;
@@ -11,9 +17,10 @@ use uuid::Uuid;
 
 pub async fn create_navigation_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Json(payload): Json<CreateNavigationItemDto>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let item = service::create_navigation_item(&db, payload)
+    let item = service::create_navigation_item(&db, &ctx, payload)
         .await
         .map_err(|_| StatusCode::BAD_REQUEST)?;
     
@@ -63,15 +70,20 @@ pub async fn get_navigation_items_handler(
 pub async fn get_user_navigation_handler(
     Extension(db): Extension<Db>,
     Extension(user): Extension<User>,
-) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let nav_items = service::get_user_navigation(&db, user.id)
+    locale: Locale,
+) -> Result<(StatusCode, [(header::HeaderName, &'static str); 1], Json<serde_json::Value>), StatusCode> {
+    let nav_items = service::get_user_navigation(&db, user.id, locale)
         .await
         .map_err(|e| {
             eprintln!("Error getting user navigation: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
     
-    Ok((StatusCode::OK, Json(json!(nav_items))))
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_LANGUAGE, locale.as_str())],
+        Json(json!(nav_items)),
+    ))
 }
 
 pub async fn get_navigation_handler(
@@ -99,10 +111,11 @@ pub async fn get_navigation_handler(
 
 pub async fn update_navigation_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateNavigationItemDto>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    let item = service::update_navigation_item(&db, id, payload)
+    let item = service::update_navigation_item(&db, &ctx, id, payload)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     
@@ -123,9 +136,10 @@ pub async fn update_navigation_handler(
 
 pub async fn delete_navigation_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::delete_navigation_item(&db, id)
+    service::delete_navigation_item(&db, &ctx, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     
@@ -134,3 +148,122 @@ pub async fn delete_navigation_handler(
         Json(json!({"message": "Navigation item deleted successfully"})),
     ))
 }
+
+pub async fn move_navigation_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<MoveNavigationItemDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let item = service::move_navigation_item(&db, &ctx, id, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error moving navigation item: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    let response = NavigationItemResponseDto {
+        id: item.id,
+        name: item.name,
+        path: item.path,
+        icon: item.icon,
+        parent_id: item.parent_id,
+        display_order: item.display_order,
+        is_active: item.is_active,
+        created_at: item.created_at,
+        updated_at: item.updated_at,
+    };
+
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+pub async fn get_navigation_tree_handler(
+    Extension(db): Extension<Db>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let tree = service::export_navigation_tree(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::OK, Json(json!(tree))))
+}
+
+pub async fn reorder_navigation_tree_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<Vec<NavigationOrderNodeDto>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::reorder_navigation_tree(&db, &ctx, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error reordering navigation: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Navigation reordered successfully"})),
+    ))
+}
+
+pub async fn import_navigation_tree_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Query(query): Query<HashMap<String, String>>,
+    Json(payload): Json<Vec<NavigationTreeNodeDto>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let deactivate_missing = query.get("deactivate_missing").is_some_and(|v| v == "true");
+
+    let imported = service::import_navigation_tree(&db, &ctx, payload, deactivate_missing)
+        .await
+        .map_err(|e| {
+            eprintln!("Error importing navigation: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Navigation imported successfully", "imported": imported})),
+    ))
+}
+
+pub async fn get_navigation_translations_handler(
+    Extension(db): Extension<Db>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let translations = service::get_navigation_translations(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::OK, Json(json!(translations))))
+}
+
+pub async fn set_navigation_translation_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path((id, locale)): Path<(Uuid, String)>,
+    Json(payload): Json<SetNavigationTranslationDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let translation = service::set_navigation_translation(&db, &ctx, id, &locale, &payload.name)
+        .await
+        .map_err(|e| {
+            eprintln!("Error setting navigation translation: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((StatusCode::OK, Json(json!(translation))))
+}
+
+pub async fn delete_navigation_translation_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path((id, locale)): Path<(Uuid, String)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::delete_navigation_translation(&db, &ctx, id, &locale)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Translation deleted successfully"})),
+    ))
+}
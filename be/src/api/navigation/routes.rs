@@ -8,11 +8,25 @@ use axum::{
 
 pub fn navigation_routes() -> Router {
     Router::new()
-        .route("/user", get(handlers::get_user_navigation_handler)
-            .layer(axum_middleware::from_fn(authenticate)))
         .route("/", post(handlers::create_navigation_handler))
         .route("/", get(handlers::get_navigation_items_handler))
+        .route("/tree", get(handlers::get_navigation_tree_handler))
+        .route("/tree", put(handlers::reorder_navigation_tree_handler))
+        .route("/tree/import", post(handlers::import_navigation_tree_handler))
         .route("/{id}", get(handlers::get_navigation_handler))
         .route("/{id}", put(handlers::update_navigation_handler))
         .route("/{id}", delete(handlers::delete_navigation_handler))
+        .route("/{id}/move", post(handlers::move_navigation_handler))
+        .route("/{id}/translations", get(handlers::get_navigation_translations_handler))
+        .route("/{id}/translations/{locale}", put(handlers::set_navigation_translation_handler))
+        .route("/{id}/translations/{locale}", delete(handlers::delete_navigation_translation_handler))
+}
+
+// Every authenticated user may read their own menu, so this is kept apart from
+// the management routes that are guarded by the navigation permission.
+pub fn user_navigation_routes() -> Router {
+    Router::new().route(
+        "/user",
+        get(handlers::get_user_navigation_handler).layer(axum_middleware::from_fn(authenticate)),
+    )
 }
@@ -1,16 +1,29 @@
-use anyhow::Result;
-use crate::models::navigation_item::NavigationItem;
+use anyhow::{anyhow, Result};
+use crate::{
+    api::audit::service::{self as audit, AuditEntry},
+    extractors::AuditContext,
+    i18n::Locale,
+    models::{
+        navigation_item::NavigationItem, navigation_item_translation::NavigationItemTranslation,
+    },
+};
 use chrono::Utc;
-use sqlx::PgPool;
-use std::collections::HashMap;
+use serde::Serialize;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
-use super::dto::{CreateNavigationItemDto, UpdateNavigationItemDto, UserNavigationItemDto};
+use super::dto::{
+    CreateNavigationItemDto, MoveNavigationItemDto, NavigationOrderNodeDto, NavigationTreeNodeDto,
+    UpdateNavigationItemDto, UserNavigationItemDto,
+};
 
 pub async fn create_navigation_item(
     pool: &PgPool,
+    ctx: &AuditContext,
     dto: CreateNavigationItemDto,
 ) -> Result<NavigationItem> {
+    let mut tx = pool.begin().await?;
     let nav_item = sqlx::query_as::<_, NavigationItem>(
         r#"
         INSERT INTO navigation_items (name, path, icon, parent_id, display_order, created_at, updated_at)
@@ -21,18 +34,26 @@ pub async fn create_navigation_item(
     .bind(&dto.name)
     .bind(&dto.path)
     .bind(&dto.icon)
-    .bind(&dto.parent_id)
+    .bind(dto.parent_id)
     .bind(dto.display_order.unwrap_or(0))
     .bind(Utc::now().naive_utc())
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
     ?;
 
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_item", "create", Some(nav_item.id)).after(&nav_item),
+    )
+    .await?;
+    tx.commit().await?;
+
     Ok(nav_item)
 }
 
-pub async fn get_navigation_items(
-    pool: &PgPool,
+pub async fn get_navigation_items<'e, E: PgExecutor<'e>>(
+    pool: E,
     is_active: Option<bool>,
 ) -> Result<Vec<NavigationItem>> {
     let query = match is_active {
@@ -53,8 +74,8 @@ pub async fn get_navigation_items(
     Ok(items)
 }
 
-pub async fn get_navigation_item_by_id(
-    pool: &PgPool,
+pub async fn get_navigation_item_by_id<'e, E: PgExecutor<'e>>(
+    pool: E,
     id: Uuid,
 ) -> Result<NavigationItem> {
     let item = sqlx::query_as::<_, NavigationItem>("SELECT * FROM navigation_items WHERE id = $1")
@@ -66,12 +87,23 @@ pub async fn get_navigation_item_by_id(
     Ok(item)
 }
 
+/// Updates an item. Moving it under itself or one of its children is
+/// rejected, and deactivating it deactivates its children too.
 pub async fn update_navigation_item(
     pool: &PgPool,
+    ctx: &AuditContext,
     id: Uuid,
     dto: UpdateNavigationItemDto,
 ) -> Result<NavigationItem> {
-    let current = get_navigation_item_by_id(pool, id).await?;
+    let mut tx = pool.begin().await?;
+    let current = get_navigation_item_by_id(&mut *tx, id).await?;
+    let entry = AuditEntry::new("navigation_item", "update", Some(id)).before(&current);
+
+    if let Some(parent_id) = dto.parent_id
+        && Some(parent_id) != current.parent_id
+    {
+        check_parent(&mut tx, id, parent_id).await?;
+    }
 
     let item = sqlx::query_as::<_, NavigationItem>(
         r#"
@@ -95,101 +127,777 @@ pub async fn update_navigation_item(
     .bind(dto.is_active.unwrap_or(current.is_active))
     .bind(Utc::now().naive_utc())
     .bind(id)
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await
     ?;
 
+    if !item.is_active {
+        deactivate_subtree(&mut tx, id).await?;
+    }
+
+    audit::record_in(&mut tx, ctx, entry.after(&item)).await?;
+    tx.commit().await?;
     Ok(item)
 }
 
-pub async fn delete_navigation_item(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("UPDATE navigation_items SET is_active = false, updated_at = $1 WHERE id = $2")
-        .bind(Utc::now().naive_utc())
+/// Soft-deletes an item and everything below it.
+pub async fn delete_navigation_item(pool: &PgPool, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let before = get_navigation_item_by_id(&mut *tx, id).await?;
+    deactivate_subtree(&mut tx, id).await?;
+    let after = get_navigation_item_by_id(&mut *tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_item", "delete", Some(id))
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn deactivate_subtree(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM navigation_items WHERE id = $2
+            UNION
+            SELECT n.id FROM navigation_items n
+            INNER JOIN subtree s ON n.parent_id = s.id
+        )
+        UPDATE navigation_items SET is_active = false, updated_at = $1
+        WHERE id IN (SELECT id FROM subtree)
+        "#,
+    )
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .execute(conn)
+    .await
+    ?;
+
+    Ok(())
+}
+
+/// Locks the menu against other parent changes until the transaction ends.
+/// Taken before checking a new parent, so two moves can't each pass the
+/// check and make a cycle together. Reads aren't blocked.
+async fn lock_tree(conn: &mut PgConnection) -> Result<()> {
+    sqlx::query("LOCK TABLE navigation_items IN SHARE ROW EXCLUSIVE MODE")
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Rejects `parent_id` as the new parent of `id` when it is `id` itself or
+/// one of its descendants, which would make a cycle. Locks the tree first.
+async fn check_parent(conn: &mut PgConnection, id: Uuid, parent_id: Uuid) -> Result<()> {
+    lock_tree(&mut *conn).await?;
+
+    let creates_cycle = sqlx::query_scalar::<_, bool>(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM navigation_items WHERE id = $1
+            UNION
+            SELECT n.id FROM navigation_items n
+            INNER JOIN subtree s ON n.parent_id = s.id
+        )
+        SELECT EXISTS (SELECT 1 FROM subtree WHERE id = $2)
+        "#,
+    )
+    .bind(id)
+    .bind(parent_id)
+    .fetch_one(conn)
+    .await
+    ?;
+
+    if creates_cycle {
+        return Err(anyhow!(
+            "A navigation item cannot be moved under itself or one of its children"
+        ));
+    }
+    Ok(())
+}
+
+/// Moves an item under `dto.parent_id` at `dto.position` among its new
+/// siblings, renumbering their `display_order`.
+pub async fn move_navigation_item(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    id: Uuid,
+    dto: MoveNavigationItemDto,
+) -> Result<NavigationItem> {
+    let mut tx = pool.begin().await?;
+    let before = get_navigation_item_by_id(&mut *tx, id).await?;
+
+    if let Some(parent_id) = dto.parent_id {
+        check_parent(&mut tx, id, parent_id).await?;
+    }
+
+    let mut siblings = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id FROM navigation_items
+        WHERE parent_id IS NOT DISTINCT FROM $1 AND id <> $2
+        ORDER BY display_order, name
+        "#,
+    )
+    .bind(dto.parent_id)
+    .bind(id)
+    .fetch_all(&mut *tx)
+    .await
+    ?;
+    let position = dto.position.unwrap_or(siblings.len()).min(siblings.len());
+    siblings.insert(position, id);
+
+    let now = Utc::now().naive_utc();
+    for (i, sibling_id) in siblings.iter().enumerate() {
+        sqlx::query(
+            "UPDATE navigation_items SET parent_id = $1, display_order = $2, updated_at = $3 WHERE id = $4",
+        )
+        .bind(dto.parent_id)
+        .bind(i as i32 + 1)
+        .bind(now)
+        .bind(sibling_id)
+        .execute(&mut *tx)
+        .await
+        ?;
+    }
+
+    let item = get_navigation_item_by_id(&mut *tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_item", "move", Some(id))
+            .before(&before)
+            .after(&item),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(item)
+}
+
+fn flatten_order(
+    nodes: &[NavigationOrderNodeDto],
+    parent_id: Option<Uuid>,
+    out: &mut Vec<(Uuid, Option<Uuid>, i32)>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        out.push((node.id, parent_id, i as i32 + 1));
+        flatten_order(&node.children, Some(node.id), out);
+    }
+}
+
+/// Sets every item's parent and `display_order` from the full menu tree.
+/// All active items must appear exactly once; inactive ones may be left out
+/// and keep their place.
+pub async fn reorder_navigation_tree(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    tree: Vec<NavigationOrderNodeDto>,
+) -> Result<()> {
+    let mut entries = vec![];
+    flatten_order(&tree, None, &mut entries);
+
+    let mut seen = HashSet::new();
+    if let Some((id, _, _)) = entries.iter().find(|(id, _, _)| !seen.insert(*id)) {
+        return Err(anyhow!("Navigation item {} is listed more than once", id));
+    }
+
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx).await?;
+    let before = load_tree(&mut tx).await?;
+
+    let items = get_navigation_items(&mut *tx, None).await?;
+    let known: HashSet<Uuid> = items.iter().map(|i| i.id).collect();
+    if let Some((id, _, _)) = entries.iter().find(|(id, _, _)| !known.contains(id)) {
+        return Err(anyhow!("Navigation item {} not found", id));
+    }
+    if let Some(missing) = items.iter().find(|i| i.is_active && !seen.contains(&i.id)) {
+        return Err(anyhow!("The tree is missing {}", missing.path));
+    }
+
+    let now = Utc::now().naive_utc();
+    for (id, parent_id, display_order) in entries {
+        sqlx::query(
+            "UPDATE navigation_items SET parent_id = $1, display_order = $2, updated_at = $3 WHERE id = $4",
+        )
+        .bind(parent_id)
+        .bind(display_order)
+        .bind(now)
         .bind(id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
         ?;
+    }
+
+    let after = load_tree(&mut tx).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_tree", "reorder", None)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
 
     Ok(())
 }
 
-#[derive(sqlx::FromRow)]
-struct NavigationWithPermissions {
+/// The whole menu, active or not, as a tree ordered by `display_order`.
+pub async fn export_navigation_tree(pool: &PgPool) -> Result<Vec<NavigationTreeNodeDto>> {
+    let mut conn = pool.acquire().await?;
+    load_tree(&mut conn).await
+}
+
+async fn load_tree(conn: &mut PgConnection) -> Result<Vec<NavigationTreeNodeDto>> {
+    let items = get_navigation_items(&mut *conn, None).await?;
+
+    let mut translations: HashMap<Uuid, BTreeMap<String, String>> = HashMap::new();
+    for t in get_all_translations(&mut *conn).await? {
+        translations
+            .entry(t.navigation_item_id)
+            .or_default()
+            .insert(t.locale, t.name);
+    }
+
+    let mut children: HashMap<Option<Uuid>, Vec<&NavigationItem>> = HashMap::new();
+    for item in &items {
+        children.entry(item.parent_id).or_default().push(item);
+    }
+
+    fn build(
+        parent_id: Option<Uuid>,
+        children: &HashMap<Option<Uuid>, Vec<&NavigationItem>>,
+        translations: &HashMap<Uuid, BTreeMap<String, String>>,
+    ) -> Vec<NavigationTreeNodeDto> {
+        children
+            .get(&parent_id)
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|item| NavigationTreeNodeDto {
+                        name: item.name.clone(),
+                        path: item.path.clone(),
+                        icon: item.icon.clone(),
+                        is_active: Some(item.is_active),
+                        translations: translations.get(&item.id).cloned().unwrap_or_default(),
+                        children: build(Some(item.id), children, translations),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    Ok(build(None, &children, &translations))
+}
+
+fn flatten_tree<'a>(
+    nodes: &'a [NavigationTreeNodeDto],
+    parent_path: Option<&'a str>,
+    out: &mut Vec<(&'a NavigationTreeNodeDto, Option<&'a str>, i32)>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        out.push((node, parent_path, i as i32 + 1));
+        flatten_tree(&node.children, Some(&node.path), out);
+    }
+}
+
+/// Creates or updates, by path, every node of an exported tree in one
+/// transaction. With `deactivate_missing`, items whose path isn't in the
+/// tree are deactivated. Returns the number of nodes imported.
+pub async fn import_navigation_tree(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    tree: Vec<NavigationTreeNodeDto>,
+    deactivate_missing: bool,
+) -> Result<usize> {
+    let mut nodes = vec![];
+    flatten_tree(&tree, None, &mut nodes);
+
+    let mut seen = HashSet::new();
+    if let Some((node, _, _)) = nodes.iter().find(|(node, _, _)| !seen.insert(node.path.as_str())) {
+        return Err(anyhow!("{} is listed more than once", node.path));
+    }
+
+    let mut tx = pool.begin().await?;
+    lock_tree(&mut tx).await?;
+    let before = load_tree(&mut tx).await?;
+    let now = Utc::now().naive_utc();
+    // Parents come before their children, so their ids are known by then
+    let mut ids: HashMap<&str, Uuid> = HashMap::new();
+    for (node, parent_path, display_order) in &nodes {
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO navigation_items (name, path, icon, parent_id, display_order, is_active, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+            ON CONFLICT (path) DO UPDATE SET
+                name = EXCLUDED.name,
+                icon = EXCLUDED.icon,
+                parent_id = EXCLUDED.parent_id,
+                display_order = EXCLUDED.display_order,
+                is_active = EXCLUDED.is_active,
+                updated_at = EXCLUDED.updated_at
+            RETURNING id
+            "#,
+        )
+        .bind(&node.name)
+        .bind(&node.path)
+        .bind(&node.icon)
+        .bind(parent_path.map(|p| ids[p]))
+        .bind(display_order)
+        .bind(node.is_active.unwrap_or(true))
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await
+        ?;
+        ids.insert(&node.path, id);
+
+        for (locale, name) in &node.translations {
+            let locale = translation_locale(locale)?;
+            upsert_translation(&mut tx, id, locale.as_str(), name).await?;
+        }
+    }
+
+    if deactivate_missing {
+        let paths: Vec<&str> = ids.keys().copied().collect();
+        sqlx::query(
+            "UPDATE navigation_items SET is_active = false, updated_at = $1 WHERE is_active = true AND path <> ALL($2)",
+        )
+        .bind(now)
+        .bind(&paths)
+        .execute(&mut *tx)
+        .await
+        ?;
+    }
+
+    let after = load_tree(&mut tx).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_tree", "import", None)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(nodes.len())
+}
+
+/// Parses the locale of a translation. English isn't one: it is the item's
+/// own name.
+pub fn translation_locale(tag: &str) -> Result<Locale> {
+    match Locale::parse(tag) {
+        Some(Locale::En) => Err(anyhow!("English is the navigation item's own name")),
+        Some(locale) => Ok(locale),
+        None => Err(anyhow!("Unsupported locale \"{}\"", tag)),
+    }
+}
+
+async fn get_all_translations(conn: &mut PgConnection) -> Result<Vec<NavigationItemTranslation>> {
+    let translations = sqlx::query_as::<_, NavigationItemTranslation>(
+        "SELECT * FROM navigation_item_translations ORDER BY locale",
+    )
+    .fetch_all(conn)
+    .await
+    ?;
+
+    Ok(translations)
+}
+
+pub async fn get_navigation_translations<'e, E: PgExecutor<'e>>(
+    pool: E,
     id: Uuid,
-    name: String,
-    path: String,
-    icon: Option<String>,
-    parent_id: Option<Uuid>,
-    display_order: i32,
-    can_create: Option<bool>,
-    can_read: Option<bool>,
-    can_update: Option<bool>,
-    can_delete: Option<bool>,
+) -> Result<Vec<NavigationItemTranslation>> {
+    let translations = sqlx::query_as::<_, NavigationItemTranslation>(
+        "SELECT * FROM navigation_item_translations WHERE navigation_item_id = $1 ORDER BY locale",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
+    ?;
+
+    Ok(translations)
 }
 
-pub async fn get_user_navigation(
+async fn upsert_translation(
+    conn: &mut PgConnection,
+    id: Uuid,
+    locale: &str,
+    name: &str,
+) -> Result<NavigationItemTranslation> {
+    let translation = sqlx::query_as::<_, NavigationItemTranslation>(
+        r#"
+        INSERT INTO navigation_item_translations (navigation_item_id, locale, name, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        ON CONFLICT (navigation_item_id, locale) DO UPDATE SET
+            name = EXCLUDED.name,
+            updated_at = EXCLUDED.updated_at
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(locale)
+    .bind(name)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(conn)
+    .await
+    ?;
+
+    Ok(translation)
+}
+
+async fn find_translation(
+    conn: &mut PgConnection,
+    id: Uuid,
+    locale: Locale,
+) -> Result<Option<NavigationItemTranslation>> {
+    let translations = get_navigation_translations(conn, id).await?;
+    Ok(translations.into_iter().find(|t| t.locale == locale.as_str()))
+}
+
+pub async fn set_navigation_translation(
     pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<UserNavigationItemDto>> {
-    // Get user's department and position from employees or interns
-    // Define a struct to hold the role info
-    #[derive(sqlx::FromRow)]
-    struct RoleInfo {
-        department_id: Option<Uuid>,
-        position_id: Option<Uuid>,
+    ctx: &AuditContext,
+    id: Uuid,
+    locale: &str,
+    name: &str,
+) -> Result<NavigationItemTranslation> {
+    let locale = translation_locale(locale)?;
+    if name.trim().is_empty() {
+        return Err(anyhow!("name is required"));
     }
 
-    let role_info: Option<RoleInfo> = sqlx::query_as::<_, RoleInfo>(
+    let mut tx = pool.begin().await?;
+    get_navigation_item_by_id(&mut *tx, id).await?;
+    let before = find_translation(&mut tx, id, locale).await?;
+    let translation = upsert_translation(&mut tx, id, locale.as_str(), name.trim()).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_item", "set_translation", Some(id))
+            .before(&before)
+            .after(&translation),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(translation)
+}
+
+pub async fn delete_navigation_translation(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    id: Uuid,
+    locale: &str,
+) -> Result<()> {
+    let locale = translation_locale(locale)?;
+    let mut tx = pool.begin().await?;
+    let before = find_translation(&mut tx, id, locale)
+        .await?
+        .ok_or_else(|| anyhow!("Translation not found"))?;
+
+    sqlx::query(
+        "DELETE FROM navigation_item_translations WHERE navigation_item_id = $1 AND locale = $2",
+    )
+    .bind(id)
+    .bind(locale.as_str())
+    .execute(&mut *tx)
+    .await
+    ?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("navigation_item", "delete_translation", Some(id)).before(&before),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub struct NavigationWithPermissions {
+    pub id: Uuid,
+    pub name: String,
+    pub path: String,
+    pub icon: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub display_order: i32,
+    pub can_create: Option<bool>,
+    pub can_read: Option<bool>,
+    pub can_update: Option<bool>,
+    pub can_delete: Option<bool>,
+    pub data_scope: DataScope,
+}
+
+/// The departments and positions whose role permissions apply to a user,
+/// from their employee and intern records.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Placement {
+    pub department_ids: Vec<Uuid>,
+    pub position_ids: Vec<Uuid>,
+}
+
+pub async fn get_user_placement(pool: &PgPool, user_id: Uuid) -> Result<Placement> {
+    let rows = sqlx::query_as::<_, (Option<Uuid>, Option<Uuid>)>(
         r#"
-        SELECT department_id, position_id FROM employees 
+        SELECT department_id, position_id FROM employees
         WHERE person_id = (SELECT person_id FROM users WHERE id = $1)
         UNION
-        SELECT department_id, position_id FROM interns 
+        SELECT department_id, position_id FROM interns
         WHERE person_id = (SELECT person_id FROM users WHERE id = $1)
         "#,
     )
     .bind(user_id)
-    .fetch_optional(pool)
+    .fetch_all(pool)
     .await
     ?;
 
-    if role_info.is_none() {
-        // User has no employee/intern record - return empty navigation
-        return Ok(vec![]);
+    let mut placement = Placement::default();
+    for (department_id, position_id) in rows {
+        placement.department_ids.extend(department_id);
+        placement.position_ids.extend(position_id);
     }
+    Ok(placement)
+}
 
-    let role = role_info.unwrap();
+/// A `role_permissions` or `user_permissions` row that applies to a user.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PermissionSource {
+    pub navigation_item_id: Uuid,
+    // "department", "position", "role", "grant" or "deny"
+    pub source: String,
+    // None for the synthesized "admin" source
+    pub permission_id: Option<Uuid>,
+    // The department, position or role the row targets
+    pub target_id: Option<Uuid>,
+    pub target_name: Option<String>,
+    pub data_scope: String,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+}
 
-    // Get navigation items with aggregated permissions
-    let nav_with_perms = sqlx::query_as::<_, NavigationWithPermissions>(
+/// Every permission row that applies to the user with the given placement,
+/// optionally only for the active navigation item at `path`.
+pub async fn get_permission_sources(
+    pool: &PgPool,
+    user_id: Uuid,
+    placement: &Placement,
+    path: Option<&str>,
+) -> Result<Vec<PermissionSource>> {
+    let sources = sqlx::query_as::<_, PermissionSource>(
         r#"
-        SELECT DISTINCT 
-            n.id,
-            n.name,
-            n.path,
-            n.icon,
-            n.parent_id,
-            n.display_order,
-            COALESCE(MAX(rp.can_create::int), 0)::bool as can_create,
-            COALESCE(MAX(rp.can_read::int), 0)::bool as can_read,
-            COALESCE(MAX(rp.can_update::int), 0)::bool as can_update,
-            COALESCE(MAX(rp.can_delete::int), 0)::bool as can_delete
-        FROM navigation_items n
-        INNER JOIN role_permissions rp ON n.id = rp.navigation_item_id
+        SELECT s.* FROM (
+            SELECT rp.navigation_item_id, 'department' as source, rp.id as permission_id,
+                d.id as target_id, d.name as target_name, rp.data_scope,
+                rp.can_create, rp.can_read, rp.can_update, rp.can_delete
+            FROM role_permissions rp
+            INNER JOIN departments d ON rp.department_id = d.id
+            WHERE rp.department_id = ANY($2)
+            UNION ALL
+            SELECT rp.navigation_item_id, 'position', rp.id, p.id, p.name, rp.data_scope,
+                rp.can_create, rp.can_read, rp.can_update, rp.can_delete
+            FROM role_permissions rp
+            INNER JOIN positions p ON rp.position_id = p.id
+            WHERE rp.position_id = ANY($3)
+            UNION ALL
+            SELECT rp.navigation_item_id, 'role', rp.id, r.id, r.name, rp.data_scope,
+                rp.can_create, rp.can_read, rp.can_update, rp.can_delete
+            FROM role_permissions rp
+            INNER JOIN roles r ON rp.role_id = r.id
+            INNER JOIN user_roles ur ON ur.role_id = r.id
+            WHERE ur.user_id = $1 AND r.is_active = true
+            UNION ALL
+            SELECT up.navigation_item_id, up.effect, up.id, NULL, NULL, up.data_scope,
+                up.can_create, up.can_read, up.can_update, up.can_delete
+            FROM user_permissions up
+            WHERE up.user_id = $1
+        ) s
+        INNER JOIN navigation_items n ON s.navigation_item_id = n.id
         WHERE n.is_active = true
-        AND (
-            (rp.department_id = $1 OR rp.position_id = $2)
-        )
-        GROUP BY n.id, n.name, n.path, n.icon, n.parent_id, n.display_order
-        ORDER BY n.display_order, n.name
+        AND ($4::text IS NULL OR n.path = $4)
         "#,
     )
-    .bind(role.department_id)
-    .bind(role.position_id)
+    .bind(user_id)
+    .bind(&placement.department_ids)
+    .bind(&placement.position_ids)
+    .bind(path)
     .fetch_all(pool)
     .await
     ?;
 
+    Ok(sources)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CrudFlags {
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+}
+
+/// The resolution order, applied to the sources for one navigation item:
+///
+/// 1. the union of the role permissions of the user's department, position
+///    and active roles,
+/// 2. plus their own grants,
+/// 3. minus their own denies, which always win.
+///
+/// Returns `None` when nothing grants anything on the item, so it isn't
+/// listed at all.
+pub fn resolve_permissions<'a>(
+    sources: impl IntoIterator<Item = &'a PermissionSource>,
+) -> Option<CrudFlags> {
+    let mut granted: Option<CrudFlags> = None;
+    let mut denied = CrudFlags::default();
+
+    for source in sources {
+        let flags = if source.source == "deny" {
+            &mut denied
+        } else {
+            granted.get_or_insert_with(CrudFlags::default)
+        };
+        flags.can_create |= source.can_create;
+        flags.can_read |= source.can_read;
+        flags.can_update |= source.can_update;
+        flags.can_delete |= source.can_delete;
+    }
+
+    granted.map(|g| CrudFlags {
+        can_create: g.can_create && !denied.can_create,
+        can_read: g.can_read && !denied.can_read,
+        can_update: g.can_update && !denied.can_update,
+        can_delete: g.can_delete && !denied.can_delete,
+    })
+}
+
+/// Which records of a resource a user can see, narrowest first. Stored as
+/// `data_scope` on permission rows and applied through the
+/// `scoped_employee_ids` SQL function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataScope {
+    // Only their own records
+    Own,
+    // Their reporting line through employees.manager_id
+    Reports,
+    // Everyone in their department(s)
+    Department,
+    All,
+}
+
+impl DataScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataScope::Own => "own",
+            DataScope::Reports => "reports",
+            DataScope::Department => "department",
+            DataScope::All => "all",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "own" => Some(DataScope::Own),
+            "reports" => Some(DataScope::Reports),
+            "department" => Some(DataScope::Department),
+            "all" => Some(DataScope::All),
+            _ => None,
+        }
+    }
+}
+
+/// The widest scope among the sources granting read. Denies don't narrow it;
+/// they take read away altogether.
+pub fn resolve_data_scope<'a>(sources: impl IntoIterator<Item = &'a PermissionSource>) -> DataScope {
+    sources
+        .into_iter()
+        .filter(|s| s.source != "deny" && s.can_read)
+        .filter_map(|s| DataScope::parse(&s.data_scope))
+        .max()
+        .unwrap_or(DataScope::Own)
+}
+
+/// Resolves the user's CRUD flags and data scope per navigation item with
+/// `resolve_permissions` and `resolve_data_scope`. Shared by the menu, the route authorization
+/// middleware and the permission explain endpoint so they always agree.
+pub async fn get_effective_permissions(
+    pool: &PgPool,
+    user_id: Uuid,
+    path: Option<&str>,
+) -> Result<Vec<NavigationWithPermissions>> {
+    let placement = get_user_placement(pool, user_id).await?;
+    let sources = get_permission_sources(pool, user_id, &placement, path).await?;
+    if sources.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let items = sqlx::query_as::<_, NavigationItem>(
+        r#"
+        SELECT * FROM navigation_items
+        WHERE is_active = true
+        AND ($1::text IS NULL OR path = $1)
+        ORDER BY display_order, name
+        "#,
+    )
+    .bind(path)
+    .fetch_all(pool)
+    .await
+    ?;
+
+    let nav_with_perms = items
+        .into_iter()
+        .filter_map(|n| {
+            let item_sources = sources.iter().filter(|s| s.navigation_item_id == n.id);
+            let flags = resolve_permissions(item_sources.clone())?;
+            Some(NavigationWithPermissions {
+                id: n.id,
+                name: n.name,
+                path: n.path,
+                icon: n.icon,
+                parent_id: n.parent_id,
+                display_order: n.display_order,
+                can_create: Some(flags.can_create),
+                can_read: Some(flags.can_read),
+                can_update: Some(flags.can_update),
+                can_delete: Some(flags.can_delete),
+                data_scope: resolve_data_scope(item_sources),
+            })
+        })
+        .collect();
+
+    Ok(nav_with_perms)
+}
+
+/// The user's menu with names in `locale`, falling back to the English name
+/// for items that aren't translated.
+pub async fn get_user_navigation(
+    pool: &PgPool,
+    user_id: Uuid,
+    locale: Locale,
+) -> Result<Vec<UserNavigationItemDto>> {
+    let nav_with_perms = get_effective_permissions(pool, user_id, None).await?;
+
+    let names: HashMap<Uuid, String> = if locale == Locale::En {
+        HashMap::new()
+    } else {
+        sqlx::query_as::<_, (Uuid, String)>(
+            "SELECT navigation_item_id, name FROM navigation_item_translations WHERE locale = $1",
+        )
+        .bind(locale.as_str())
+        .fetch_all(pool)
+        .await
+        ?
+        .into_iter()
+        .collect()
+    };
+
     // Build hierarchical structure
     let mut items_map: HashMap<Uuid, UserNavigationItemDto> = HashMap::new();
     let mut root_items: Vec<UserNavigationItemDto> = Vec::new();
@@ -198,7 +906,7 @@ pub async fn get_user_navigation(
     for nav in &nav_with_perms {
         let item = UserNavigationItemDto {
             id: nav.id,
-            name: nav.name.clone(),
+            name: names.get(&nav.id).unwrap_or(&nav.name).clone(),
             path: nav.path.clone(),
             icon: nav.icon.clone(),
             parent_id: nav.parent_id,
@@ -214,21 +922,20 @@ pub async fn get_user_navigation(
 
     // Second pass: build hierarchy
     for nav in &nav_with_perms {
-        if let Some(parent_id) = nav.parent_id {
-            if let Some(item) = items_map.remove(&nav.id) {
-                if let Some(parent) = items_map.get_mut(&parent_id) {
-                    parent.children.push(item);
-                }
-            }
+        if let Some(parent_id) = nav.parent_id
+            && let Some(item) = items_map.remove(&nav.id)
+            && let Some(parent) = items_map.get_mut(&parent_id)
+        {
+            parent.children.push(item);
         }
     }
 
     // Collect root items
     for nav in &nav_with_perms {
-        if nav.parent_id.is_none() {
-            if let Some(item) = items_map.remove(&nav.id) {
-                root_items.push(item);
-            }
+        if nav.parent_id.is_none()
+            && let Some(item) = items_map.remove(&nav.id)
+        {
+            root_items.push(item);
         }
     }
 
@@ -236,3 +943,92 @@ pub async fn get_user_navigation(
 
     Ok(root_items)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A source with the flags given as "crud", e.g. "cr" for create and read
+    fn source(kind: &str, flags: &str) -> PermissionSource {
+        PermissionSource {
+            navigation_item_id: Uuid::nil(),
+            source: kind.to_string(),
+            permission_id: None,
+            target_id: None,
+            target_name: None,
+            data_scope: "own".to_string(),
+            can_create: flags.contains('c'),
+            can_read: flags.contains('r'),
+            can_update: flags.contains('u'),
+            can_delete: flags.contains('d'),
+        }
+    }
+
+    fn flags(create: bool, read: bool, update: bool, delete: bool) -> Option<CrudFlags> {
+        Some(CrudFlags {
+            can_create: create,
+            can_read: read,
+            can_update: update,
+            can_delete: delete,
+        })
+    }
+
+    #[test]
+    fn grants_are_combined() {
+        let sources = [source("department", "r"), source("role", "cu"), source("grant", "d")];
+        assert_eq!(resolve_permissions(&sources), flags(true, true, true, true));
+    }
+
+    #[test]
+    fn denies_win_over_every_grant() {
+        let sources = [
+            source("department", "crud"),
+            source("position", "crud"),
+            source("grant", "crud"),
+            source("deny", "ud"),
+        ];
+        assert_eq!(resolve_permissions(&sources), flags(true, true, false, false));
+    }
+
+    #[test]
+    fn deny_order_does_not_matter() {
+        let sources = [source("deny", "r"), source("role", "cr")];
+        assert_eq!(resolve_permissions(&sources), flags(true, false, false, false));
+    }
+
+    #[test]
+    fn items_without_grants_are_not_listed() {
+        assert_eq!(resolve_permissions(&[]), None);
+        assert_eq!(resolve_permissions(&[source("deny", "crud")]), None);
+    }
+
+    #[test]
+    fn an_empty_grant_still_lists_the_item() {
+        assert_eq!(resolve_permissions(&[source("role", "")]), flags(false, false, false, false));
+    }
+
+    fn scoped(kind: &str, flags: &str, data_scope: &str) -> PermissionSource {
+        PermissionSource {
+            data_scope: data_scope.to_string(),
+            ..source(kind, flags)
+        }
+    }
+
+    #[test]
+    fn data_scope_is_the_widest_readable_grant() {
+        let sources = [
+            scoped("department", "r", "department"),
+            scoped("role", "r", "reports"),
+            // Write-only grants don't widen what can be seen
+            scoped("grant", "cu", "all"),
+        ];
+        assert_eq!(resolve_data_scope(&sources), DataScope::Department);
+    }
+
+    #[test]
+    fn data_scope_ignores_denies_and_defaults_to_own() {
+        assert_eq!(resolve_data_scope(&[scoped("deny", "r", "all")]), DataScope::Own);
+        assert_eq!(resolve_data_scope(&[scoped("role", "r", "bogus")]), DataScope::Own);
+        assert_eq!(resolve_data_scope(&[]), DataScope::Own);
+    }
+}
@@ -2,15 +2,21 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
 
+use crate::api::navigation::service::{DataScope, PermissionSource};
+
+/// Targets either a role or a department and/or position.
 #[derive(Debug, Deserialize)]
 pub struct AssignPermissionDto {
     pub department_id: Option<Uuid>,
     pub position_id: Option<Uuid>,
+    pub role_id: Option<Uuid>,
     pub navigation_item_id: Uuid,
     pub can_create: bool,
     pub can_read: bool,
     pub can_update: bool,
     pub can_delete: bool,
+    // "own", "reports", "department" or "all" (the default)
+    pub data_scope: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -20,6 +26,8 @@ pub struct PermissionResponseDto {
     pub department_name: Option<String>,
     pub position_id: Option<Uuid>,
     pub position_name: Option<String>,
+    pub role_id: Option<Uuid>,
+    pub role_name: Option<String>,
     pub navigation_item_id: Uuid,
     pub navigation_name: String,
     pub navigation_path: String,
@@ -27,5 +35,134 @@ pub struct PermissionResponseDto {
     pub can_read: bool,
     pub can_update: bool,
     pub can_delete: bool,
+    pub data_scope: String,
     pub created_at: NaiveDateTime,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct AssignUserPermissionDto {
+    pub user_id: Uuid,
+    pub navigation_item_id: Uuid,
+    // "grant" adds the flagged rights, "deny" removes them
+    pub effect: String,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    // Same as on AssignPermissionDto; ignored for a deny
+    pub data_scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct UserPermissionResponseDto {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub navigation_item_id: Uuid,
+    pub navigation_name: String,
+    pub navigation_path: String,
+    pub effect: String,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub data_scope: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Why a user has the rights they have on one navigation item.
+#[derive(Debug, Serialize)]
+pub struct EffectivePermissionItemDto {
+    pub navigation_item_id: Uuid,
+    pub name: String,
+    pub path: String,
+    pub parent_id: Option<Uuid>,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub data_scope: DataScope,
+    // Every row that contributed, including denies; "admin" for admins
+    pub sources: Vec<PermissionSource>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EffectivePermissionsDto {
+    pub user_id: Uuid,
+    pub is_admin: bool,
+    // Whether a hypothetical department and/or position was evaluated
+    pub what_if: bool,
+    pub department_ids: Vec<Uuid>,
+    pub position_ids: Vec<Uuid>,
+    pub items: Vec<EffectivePermissionItemDto>,
+}
+
+/// The department, position and/or role a set of role permissions belongs
+/// to. Same rules as `AssignPermissionDto`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PermissionTargetDto {
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub role_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PermissionMatrixEntryDto {
+    pub navigation_item_id: Uuid,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub data_scope: Option<String>,
+}
+
+/// The full grid for one target. Navigation items left out lose all rights.
+#[derive(Debug, Deserialize)]
+pub struct SetPermissionMatrixDto {
+    #[serde(flatten)]
+    pub target: PermissionTargetDto,
+    pub items: Vec<PermissionMatrixEntryDto>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PermissionMatrixItemDto {
+    pub navigation_item_id: Uuid,
+    pub name: String,
+    pub path: String,
+    pub parent_id: Option<Uuid>,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub data_scope: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PermissionMatrixDto {
+    #[serde(flatten)]
+    pub target: PermissionTargetDto,
+    pub items: Vec<PermissionMatrixItemDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopyPermissionsDto {
+    pub from: PermissionTargetDto,
+    pub to: PermissionTargetDto,
+}
+
+/// One `role_permissions` row in an export. Targets and navigation items are
+/// referenced by name and path, so a file taken from one environment can be
+/// imported into another.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PermissionExportRowDto {
+    pub department: Option<String>,
+    pub position: Option<String>,
+    pub role: Option<String>,
+    pub navigation_path: String,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    // Missing in exports from before data scopes
+    pub data_scope: Option<String>,
+}
@@ -1,7 +1,14 @@
-use crate::{api::permissions::{dto::*, service}, db::Db};
+use crate::{
+    api::{
+        permissions::{dto::*, service},
+    },
+    db::Db,
+    extractors::AuditContext,
+};
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
@@ -10,11 +17,15 @@ use uuid::Uuid;
 
 pub async fn assign_permission_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Json(payload): Json<AssignPermissionDto>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::assign_permission(&db, payload)
+    service::assign_permission(&db, &ctx, payload)
         .await
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(|e| {
+            eprintln!("Error assigning permission: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
     
     Ok((
         StatusCode::OK,
@@ -28,9 +39,10 @@ pub async fn get_permissions_handler(
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
     let department_id = query.get("department_id").and_then(|v| Uuid::parse_str(v).ok());
     let position_id = query.get("position_id").and_then(|v| Uuid::parse_str(v).ok());
+    let role_id = query.get("role_id").and_then(|v| Uuid::parse_str(v).ok());
     let navigation_item_id = query.get("navigation_item_id").and_then(|v| Uuid::parse_str(v).ok());
     
-    let permissions = service::get_permissions(&db, department_id, position_id, navigation_item_id)
+    let permissions = service::get_permissions(&db, department_id, position_id, role_id, navigation_item_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
@@ -39,9 +51,10 @@ pub async fn get_permissions_handler(
 
 pub async fn delete_permission_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    service::delete_permission(&db, id)
+    service::delete_permission(&db, &ctx, id)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
     
@@ -50,3 +63,180 @@ pub async fn delete_permission_handler(
         Json(json!({"message": "Permission deleted successfully"})),
     ))
 }
+
+pub async fn assign_user_permission_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<AssignUserPermissionDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let permission = service::assign_user_permission(&db, &ctx, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error assigning user permission: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((StatusCode::OK, Json(json!(permission))))
+}
+
+pub async fn get_user_permissions_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let user_id = query.get("user_id").and_then(|v| Uuid::parse_str(v).ok());
+
+    let permissions = service::get_user_permissions(&db, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::OK, Json(json!(permissions))))
+}
+
+pub async fn delete_user_permission_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::delete_user_permission(&db, &ctx, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "User permission deleted successfully"})),
+    ))
+}
+
+pub async fn get_effective_permissions_handler(
+    Extension(db): Extension<Db>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let department_id = query.get("department_id").and_then(|v| Uuid::parse_str(v).ok());
+    let position_id = query.get("position_id").and_then(|v| Uuid::parse_str(v).ok());
+
+    let permissions = service::explain_permissions(&db, user_id, department_id, position_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok((StatusCode::OK, Json(json!(permissions))))
+}
+
+fn target_from_query(query: &HashMap<String, String>) -> PermissionTargetDto {
+    PermissionTargetDto {
+        department_id: query.get("department_id").and_then(|v| Uuid::parse_str(v).ok()),
+        position_id: query.get("position_id").and_then(|v| Uuid::parse_str(v).ok()),
+        role_id: query.get("role_id").and_then(|v| Uuid::parse_str(v).ok()),
+    }
+}
+
+pub async fn get_permission_matrix_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let matrix = service::get_permission_matrix(&db, target_from_query(&query))
+        .await
+        .map_err(|e| {
+            eprintln!("Error getting permission matrix: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((StatusCode::OK, Json(json!(matrix))))
+}
+
+pub async fn set_permission_matrix_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<SetPermissionMatrixDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let matrix = service::set_permission_matrix(&db, &ctx, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error setting permission matrix: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((StatusCode::OK, Json(json!(matrix))))
+}
+
+pub async fn copy_permissions_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<CopyPermissionsDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let copied = service::copy_permissions(&db, &ctx, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error copying permissions: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Permissions copied successfully", "copied": copied})),
+    ))
+}
+
+/// `?format=csv` for a CSV download, JSON otherwise.
+pub async fn export_permissions_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    let rows = service::export_permissions(&db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if query.get("format").map(String::as_str) != Some("csv") {
+        return Ok((StatusCode::OK, Json(json!(rows))).into_response());
+    }
+
+    let csv = service::permissions_to_csv(&rows).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"permissions.csv\""),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Takes an export as JSON or, with a `text/csv` content type, as CSV.
+/// `?replace_all=true` also clears the targets that aren't in the file.
+pub async fn import_permissions_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/csv"));
+    let replace_all = query.get("replace_all").is_some_and(|v| v == "true");
+
+    let rows = if is_csv {
+        service::permissions_from_csv(&body)
+    } else {
+        serde_json::from_str::<Vec<PermissionExportRowDto>>(&body).map_err(Into::into)
+    };
+
+    let imported = match rows {
+        Ok(rows) => service::import_permissions(&db, &ctx, rows, replace_all).await,
+        Err(e) => Err(e),
+    }
+    .map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"message": format!("{:#}", e)})),
+        )
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Permissions imported successfully", "imported": imported})),
+    ))
+}
@@ -1,9 +1,18 @@
 use crate::api::permissions::handlers;
-use axum::{routing::{delete, get, post}, Router};
+use axum::{routing::{delete, get, post, put}, Router};
 
 pub fn permissions_routes() -> Router {
     Router::new()
         .route("/", post(handlers::assign_permission_handler))
         .route("/", get(handlers::get_permissions_handler))
         .route("/{id}", delete(handlers::delete_permission_handler))
+        .route("/users", post(handlers::assign_user_permission_handler))
+        .route("/users", get(handlers::get_user_permissions_handler))
+        .route("/users/{id}", delete(handlers::delete_user_permission_handler))
+        .route("/matrix", get(handlers::get_permission_matrix_handler))
+        .route("/matrix", put(handlers::set_permission_matrix_handler))
+        .route("/copy", post(handlers::copy_permissions_handler))
+        .route("/export", get(handlers::export_permissions_handler))
+        .route("/import", post(handlers::import_permissions_handler))
+        .route("/effective/{user_id}", get(handlers::get_effective_permissions_handler))
 }
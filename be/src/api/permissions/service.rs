@@ -1,35 +1,79 @@
-use anyhow::{anyhow, Result};
-use crate::models::role_permission::RolePermission;
+use anyhow::{anyhow, Context, Result};
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        navigation::service::{
+            get_permission_sources, get_user_placement, resolve_data_scope, resolve_permissions,
+            CrudFlags, DataScope, Placement, PermissionSource,
+        },
+    },
+    extractors::AuditContext,
+    models::{
+        navigation_item::NavigationItem, role_permission::RolePermission,
+        user_permission::UserPermission,
+    },
+};
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use super::dto::{AssignPermissionDto, PermissionResponseDto};
+use super::dto::{
+    AssignPermissionDto, AssignUserPermissionDto, CopyPermissionsDto, EffectivePermissionItemDto,
+    EffectivePermissionsDto, PermissionExportRowDto, PermissionMatrixDto, PermissionMatrixEntryDto,
+    PermissionMatrixItemDto, PermissionResponseDto, PermissionTargetDto, SetPermissionMatrixDto,
+    UserPermissionResponseDto,
+};
 
 pub async fn assign_permission(
     pool: &PgPool,
+    ctx: &AuditContext,
     dto: AssignPermissionDto,
 ) -> Result<RolePermission> {
-    // Validate that at least one of department_id or position_id is provided
-    if dto.department_id.is_none() && dto.position_id.is_none() {
-        return Err(anyhow!(
-            "Either department_id or position_id must be provided"
-        ));
-    }
+    validate_target(&PermissionTargetDto {
+        department_id: dto.department_id,
+        position_id: dto.position_id,
+        role_id: dto.role_id,
+    })?;
+
+    let data_scope = parse_data_scope(dto.data_scope.as_deref())?;
+
+    let mut tx = pool.begin().await?;
+    let permission = if dto.role_id.is_some() {
+        assign_role_permission(&mut tx, dto, data_scope).await?
+    } else {
+        assign_placement_permission(&mut tx, dto, data_scope).await?
+    };
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("role_permission", "assign", Some(permission.id)).after(&permission),
+    )
+    .await?;
+    tx.commit().await?;
 
+    Ok(permission)
+}
+
+async fn assign_placement_permission(
+    conn: &mut PgConnection,
+    dto: AssignPermissionDto,
+    data_scope: DataScope,
+) -> Result<RolePermission> {
     // Use INSERT ON CONFLICT to update if permission already exists
     let permission = sqlx::query_as::<_, RolePermission>(
         r#"
         INSERT INTO role_permissions (
             department_id, position_id, navigation_item_id,
-            can_create, can_read, can_update, can_delete, created_at
-        )VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            can_create, can_read, can_update, can_delete, data_scope, created_at
+        )VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         ON CONFLICT (department_id, position_id, navigation_item_id)
         DO UPDATE SET
             can_create = EXCLUDED.can_create,
             can_read = EXCLUDED.can_read,
             can_update = EXCLUDED.can_update,
-            can_delete = EXCLUDED.can_delete
+            can_delete = EXCLUDED.can_delete,
+            data_scope = EXCLUDED.data_scope
         RETURNING *
         "#,
     )
@@ -40,8 +84,74 @@ pub async fn assign_permission(
     .bind(dto.can_read)
     .bind(dto.can_update)
     .bind(dto.can_delete)
+    .bind(data_scope.as_str())
     .bind(Utc::now().naive_utc())
-    .fetch_one(pool)
+    .fetch_one(conn)
+    .await
+    ?;
+
+    Ok(permission)
+}
+
+fn parse_data_scope(value: Option<&str>) -> Result<DataScope> {
+    match value {
+        Some(value) => DataScope::parse(value).ok_or_else(|| {
+            anyhow!("data_scope must be \"own\", \"reports\", \"department\" or \"all\"")
+        }),
+        None => Ok(DataScope::All),
+    }
+}
+
+fn validate_target(target: &PermissionTargetDto) -> Result<()> {
+    if target.role_id.is_some() {
+        if target.department_id.is_some() || target.position_id.is_some() {
+            return Err(anyhow!(
+                "A role permission cannot also target a department or position"
+            ));
+        }
+        return Ok(());
+    }
+
+    // Validate that at least one of department_id or position_id is provided
+    if target.department_id.is_none() && target.position_id.is_none() {
+        return Err(anyhow!(
+            "Either role_id, department_id or position_id must be provided"
+        ));
+    }
+
+    Ok(())
+}
+
+async fn assign_role_permission(
+    conn: &mut PgConnection,
+    dto: AssignPermissionDto,
+    data_scope: DataScope,
+) -> Result<RolePermission> {
+    let permission = sqlx::query_as::<_, RolePermission>(
+        r#"
+        INSERT INTO role_permissions (
+            role_id, navigation_item_id,
+            can_create, can_read, can_update, can_delete, data_scope, created_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (role_id, navigation_item_id) WHERE role_id IS NOT NULL
+        DO UPDATE SET
+            can_create = EXCLUDED.can_create,
+            can_read = EXCLUDED.can_read,
+            can_update = EXCLUDED.can_update,
+            can_delete = EXCLUDED.can_delete,
+            data_scope = EXCLUDED.data_scope
+        RETURNING *
+        "#,
+    )
+    .bind(dto.role_id)
+    .bind(dto.navigation_item_id)
+    .bind(dto.can_create)
+    .bind(dto.can_read)
+    .bind(dto.can_update)
+    .bind(dto.can_delete)
+    .bind(data_scope.as_str())
+    .bind(Utc::now().naive_utc())
+    .fetch_one(conn)
     .await
     ?;
 
@@ -55,6 +165,8 @@ struct PermissionWithNames {
     department_name: Option<String>,
     position_id: Option<Uuid>,
     position_name: Option<String>,
+    role_id: Option<Uuid>,
+    role_name: Option<String>,
     navigation_item_id: Uuid,
     navigation_name: String,
     navigation_path: String,
@@ -62,6 +174,7 @@ struct PermissionWithNames {
     can_read: bool,
     can_update: bool,
     can_delete: bool,
+    data_scope: String,
     created_at: chrono::NaiveDateTime,
 }
 
@@ -69,6 +182,7 @@ pub async fn get_permissions(
     pool: &PgPool,
     department_id: Option<Uuid>,
     position_id: Option<Uuid>,
+    role_id: Option<Uuid>,
     navigation_item_id: Option<Uuid>,
 ) -> Result<Vec<PermissionResponseDto>> {
     let mut query = String::from(
@@ -79,6 +193,8 @@ pub async fn get_permissions(
             d.name as department_name,
             rp.position_id,
             p.name as position_name,
+            rp.role_id,
+            r.name as role_name,
             rp.navigation_item_id,
             n.name as navigation_name,
             n.path as navigation_path,
@@ -86,10 +202,12 @@ pub async fn get_permissions(
             rp.can_read,
             rp.can_update,
             rp.can_delete,
+            rp.data_scope,
             rp.created_at
         FROM role_permissions rp
         LEFT JOIN departments d ON rp.department_id = d.id
         LEFT JOIN positions p ON rp.position_id = p.id
+        LEFT JOIN roles r ON rp.role_id = r.id
         INNER JOIN navigation_items n ON rp.navigation_item_id = n.id
         WHERE 1=1
         "#,
@@ -104,12 +222,16 @@ pub async fn get_permissions(
         bind_count += 1;
         query.push_str(&format!(" AND rp.position_id = ${}", bind_count));
     }
+    if role_id.is_some() {
+        bind_count += 1;
+        query.push_str(&format!(" AND rp.role_id = ${}", bind_count));
+    }
     if navigation_item_id.is_some() {
         bind_count += 1;
         query.push_str(&format!(" AND rp.navigation_item_id = ${}", bind_count));
     }
 
-    query.push_str(" ORDER BY d.name, p.name, n.name");
+    query.push_str(" ORDER BY r.name, d.name, p.name, n.name");
 
     let mut query_builder = sqlx::query_as::<_, PermissionWithNames>(&query);
 
@@ -119,6 +241,9 @@ pub async fn get_permissions(
     if let Some(pos_id) = position_id {
         query_builder = query_builder.bind(pos_id);
     }
+    if let Some(role_id) = role_id {
+        query_builder = query_builder.bind(role_id);
+    }
     if let Some(nav_id) = navigation_item_id {
         query_builder = query_builder.bind(nav_id);
     }
@@ -136,6 +261,8 @@ pub async fn get_permissions(
             department_name: p.department_name,
             position_id: p.position_id,
             position_name: p.position_name,
+            role_id: p.role_id,
+            role_name: p.role_name,
             navigation_item_id: p.navigation_item_id,
             navigation_name: p.navigation_name,
             navigation_path: p.navigation_path,
@@ -143,6 +270,7 @@ pub async fn get_permissions(
             can_read: p.can_read,
             can_update: p.can_update,
             can_delete: p.can_delete,
+            data_scope: p.data_scope,
             created_at: p.created_at,
         })
         .collect();
@@ -150,12 +278,595 @@ pub async fn get_permissions(
     Ok(response)
 }
 
-pub async fn delete_permission(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("DELETE FROM role_permissions WHERE id = $1")
+/// The deleted permission, `None` when there was none with that id.
+pub async fn delete_permission(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    id: Uuid,
+) -> Result<Option<RolePermission>> {
+    let mut tx = pool.begin().await?;
+    let permission = sqlx::query_as::<_, RolePermission>(
+        "DELETE FROM role_permissions WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    ?;
+
+    if let Some(permission) = &permission {
+        audit::record_in(
+            &mut tx,
+            ctx,
+            AuditEntry::new("role_permission", "delete", Some(id)).before(permission),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(permission)
+}
+
+pub async fn assign_user_permission(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    dto: AssignUserPermissionDto,
+) -> Result<UserPermission> {
+    if dto.effect != "grant" && dto.effect != "deny" {
+        return Err(anyhow!("effect must be \"grant\" or \"deny\""));
+    }
+    let data_scope = parse_data_scope(dto.data_scope.as_deref())?;
+
+    let mut tx = pool.begin().await?;
+    let permission = sqlx::query_as::<_, UserPermission>(
+        r#"
+        INSERT INTO user_permissions (
+            user_id, navigation_item_id, effect,
+            can_create, can_read, can_update, can_delete, data_scope, created_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (user_id, navigation_item_id, effect)
+        DO UPDATE SET
+            can_create = EXCLUDED.can_create,
+            can_read = EXCLUDED.can_read,
+            can_update = EXCLUDED.can_update,
+            can_delete = EXCLUDED.can_delete,
+            data_scope = EXCLUDED.data_scope
+        RETURNING *
+        "#,
+    )
+    .bind(dto.user_id)
+    .bind(dto.navigation_item_id)
+    .bind(&dto.effect)
+    .bind(dto.can_create)
+    .bind(dto.can_read)
+    .bind(dto.can_update)
+    .bind(dto.can_delete)
+    .bind(data_scope.as_str())
+    .bind(Utc::now().naive_utc())
+    .fetch_one(&mut *tx)
+    .await
+    ?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user_permission", "assign", Some(permission.id)).after(&permission),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(permission)
+}
+
+pub async fn get_user_permissions(
+    pool: &PgPool,
+    user_id: Option<Uuid>,
+) -> Result<Vec<UserPermissionResponseDto>> {
+    let perms = sqlx::query_as::<_, UserPermissionResponseDto>(
+        r#"
+        SELECT
+            up.id,
+            up.user_id,
+            u.user_name,
+            up.navigation_item_id,
+            n.name as navigation_name,
+            n.path as navigation_path,
+            up.effect,
+            up.can_create,
+            up.can_read,
+            up.can_update,
+            up.can_delete,
+            up.data_scope,
+            up.created_at
+        FROM user_permissions up
+        INNER JOIN users u ON up.user_id = u.id
+        INNER JOIN navigation_items n ON up.navigation_item_id = n.id
+        WHERE ($1::uuid IS NULL OR up.user_id = $1)
+        ORDER BY u.user_name, n.name, up.effect
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    ?;
+
+    Ok(perms)
+}
+
+pub async fn delete_user_permission(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    id: Uuid,
+) -> Result<UserPermission> {
+    let mut tx = pool.begin().await?;
+    let permission = sqlx::query_as::<_, UserPermission>("DELETE FROM user_permissions WHERE id = $1 RETURNING *")
         .bind(id)
-        .execute(pool)
+        .fetch_optional(&mut *tx)
+        .await
+        ?
+        .ok_or_else(|| anyhow!("User permission not found"))?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user_permission", "delete", Some(id)).before(&permission),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(permission)
+}
+
+/// Explains a user's effective permissions on every active navigation item.
+/// A given `department_id` or `position_id` replaces the user's current one,
+/// to check what a move would change before making it. `None` when the user
+/// doesn't exist.
+pub async fn explain_permissions(
+    pool: &PgPool,
+    user_id: Uuid,
+    department_id: Option<Uuid>,
+    position_id: Option<Uuid>,
+) -> Result<Option<EffectivePermissionsDto>> {
+    let is_admin = sqlx::query_scalar::<_, bool>("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
         .await
         ?;
+    let Some(is_admin) = is_admin else {
+        return Ok(None);
+    };
+
+    let mut placement = get_user_placement(pool, user_id).await?;
+    if let Some(department_id) = department_id {
+        placement.department_ids = vec![department_id];
+    }
+    if let Some(position_id) = position_id {
+        placement.position_ids = vec![position_id];
+    }
+
+    let sources = get_permission_sources(pool, user_id, &placement, None).await?;
+
+    let navigation = sqlx::query_as::<_, NavigationItem>(
+        "SELECT * FROM navigation_items WHERE is_active = true ORDER BY display_order, name",
+    )
+    .fetch_all(pool)
+    .await
+    ?;
+
+    let items = navigation
+        .into_iter()
+        .map(|n| {
+            let mut item_sources: Vec<PermissionSource> = sources
+                .iter()
+                .filter(|s| s.navigation_item_id == n.id)
+                .cloned()
+                .collect();
+            let mut flags = resolve_permissions(&item_sources).unwrap_or_default();
+            let mut data_scope = resolve_data_scope(&item_sources);
+
+            // Admins skip the permission check entirely
+            if is_admin {
+                item_sources.push(PermissionSource {
+                    navigation_item_id: n.id,
+                    source: "admin".to_string(),
+                    permission_id: None,
+                    target_id: None,
+                    target_name: None,
+                    data_scope: DataScope::All.as_str().to_string(),
+                    can_create: true,
+                    can_read: true,
+                    can_update: true,
+                    can_delete: true,
+                });
+                flags = CrudFlags {
+                    can_create: true,
+                    can_read: true,
+                    can_update: true,
+                    can_delete: true,
+                };
+                data_scope = DataScope::All;
+            }
+
+            EffectivePermissionItemDto {
+                navigation_item_id: n.id,
+                name: n.name,
+                path: n.path,
+                parent_id: n.parent_id,
+                can_create: flags.can_create,
+                can_read: flags.can_read,
+                can_update: flags.can_update,
+                can_delete: flags.can_delete,
+                data_scope,
+                sources: item_sources,
+            }
+        })
+        .collect();
+
+    let Placement { department_ids, position_ids } = placement;
+    Ok(Some(EffectivePermissionsDto {
+        user_id,
+        is_admin,
+        what_if: department_id.is_some() || position_id.is_some(),
+        department_ids,
+        position_ids,
+        items,
+    }))
+}
+
+pub async fn get_permission_matrix<'e, E: PgExecutor<'e>>(
+    pool: E,
+    target: PermissionTargetDto,
+) -> Result<PermissionMatrixDto> {
+    validate_target(&target)?;
+
+    let items = sqlx::query_as::<_, PermissionMatrixItemDto>(
+        r#"
+        SELECT
+            n.id as navigation_item_id,
+            n.name,
+            n.path,
+            n.parent_id,
+            COALESCE(rp.can_create, false) as can_create,
+            COALESCE(rp.can_read, false) as can_read,
+            COALESCE(rp.can_update, false) as can_update,
+            COALESCE(rp.can_delete, false) as can_delete,
+            COALESCE(rp.data_scope, 'all') as data_scope
+        FROM navigation_items n
+        LEFT JOIN role_permissions rp ON rp.navigation_item_id = n.id
+            AND rp.department_id IS NOT DISTINCT FROM $1
+            AND rp.position_id IS NOT DISTINCT FROM $2
+            AND rp.role_id IS NOT DISTINCT FROM $3
+        WHERE n.is_active = true
+        ORDER BY n.display_order, n.name
+        "#,
+    )
+    .bind(target.department_id)
+    .bind(target.position_id)
+    .bind(target.role_id)
+    .fetch_all(pool)
+    .await
+    ?;
+
+    Ok(PermissionMatrixDto { target, items })
+}
+
+/// Deletes the `role_permissions` rows of `target` on active navigation
+/// items and on `navigation_item_ids`. Grants on inactive items, which the
+/// matrix doesn't show, are kept unless they are being rewritten.
+async fn clear_target_permissions(
+    conn: &mut PgConnection,
+    target: &PermissionTargetDto,
+    navigation_item_ids: &[Uuid],
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM role_permissions
+        WHERE department_id IS NOT DISTINCT FROM $1
+        AND position_id IS NOT DISTINCT FROM $2
+        AND role_id IS NOT DISTINCT FROM $3
+        AND (
+            navigation_item_id IN (SELECT id FROM navigation_items WHERE is_active = true)
+            OR navigation_item_id = ANY($4)
+        )
+        "#,
+    )
+    .bind(target.department_id)
+    .bind(target.position_id)
+    .bind(target.role_id)
+    .bind(navigation_item_ids)
+    .execute(&mut *conn)
+    .await
+    ?;
+
+    Ok(())
+}
+
+/// Replaces the `role_permissions` rows of `target` with `entries`, see
+/// `clear_target_permissions` for which rows go. Entries without any right
+/// are dropped rather than stored as all-false rows.
+async fn replace_target_permissions(
+    conn: &mut PgConnection,
+    target: &PermissionTargetDto,
+    entries: &[PermissionMatrixEntryDto],
+) -> Result<()> {
+    let navigation_item_ids: Vec<Uuid> = entries.iter().map(|e| e.navigation_item_id).collect();
+    clear_target_permissions(conn, target, &navigation_item_ids).await?;
+
+    let now = Utc::now().naive_utc();
+    for entry in entries {
+        if !(entry.can_create || entry.can_read || entry.can_update || entry.can_delete) {
+            continue;
+        }
+        let data_scope = parse_data_scope(entry.data_scope.as_deref())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO role_permissions (
+                department_id, position_id, role_id, navigation_item_id,
+                can_create, can_read, can_update, can_delete, data_scope, created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(target.department_id)
+        .bind(target.position_id)
+        .bind(target.role_id)
+        .bind(entry.navigation_item_id)
+        .bind(entry.can_create)
+        .bind(entry.can_read)
+        .bind(entry.can_update)
+        .bind(entry.can_delete)
+        .bind(data_scope.as_str())
+        .bind(now)
+        .execute(&mut *conn)
+        .await
+        ?;
+    }
 
     Ok(())
 }
+
+pub async fn set_permission_matrix(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    dto: SetPermissionMatrixDto,
+) -> Result<PermissionMatrixDto> {
+    validate_target(&dto.target)?;
+
+    let mut seen = HashSet::new();
+    if let Some(entry) = dto.items.iter().find(|e| !seen.insert(e.navigation_item_id)) {
+        return Err(anyhow!(
+            "Navigation item {} is listed more than once",
+            entry.navigation_item_id
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    let before = get_permission_matrix(&mut *tx, dto.target).await?;
+    replace_target_permissions(&mut tx, &dto.target, &dto.items).await?;
+    let after = get_permission_matrix(&mut *tx, dto.target).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("permission_matrix", "update", None)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+/// Replaces the permissions of `dto.to` with a copy of those of `dto.from`,
+/// keeping the target's grants on inactive items the source has none for.
+/// Returns the number of rows copied.
+pub async fn copy_permissions(pool: &PgPool, ctx: &AuditContext, dto: CopyPermissionsDto) -> Result<u64> {
+    validate_target(&dto.from)?;
+    validate_target(&dto.to)?;
+    if dto.from == dto.to {
+        return Err(anyhow!("Cannot copy permissions onto the same target"));
+    }
+
+    let mut tx = pool.begin().await?;
+    let before = get_permission_matrix(&mut *tx, dto.to).await?;
+    let copied_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT navigation_item_id FROM role_permissions
+        WHERE department_id IS NOT DISTINCT FROM $1
+        AND position_id IS NOT DISTINCT FROM $2
+        AND role_id IS NOT DISTINCT FROM $3
+        "#,
+    )
+    .bind(dto.from.department_id)
+    .bind(dto.from.position_id)
+    .bind(dto.from.role_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    clear_target_permissions(&mut tx, &dto.to, &copied_ids).await?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO role_permissions (
+            department_id, position_id, role_id, navigation_item_id,
+            can_create, can_read, can_update, can_delete, data_scope, created_at
+        )
+        SELECT $4, $5, $6, navigation_item_id,
+            can_create, can_read, can_update, can_delete, data_scope, $7
+        FROM role_permissions
+        WHERE department_id IS NOT DISTINCT FROM $1
+        AND position_id IS NOT DISTINCT FROM $2
+        AND role_id IS NOT DISTINCT FROM $3
+        "#,
+    )
+    .bind(dto.from.department_id)
+    .bind(dto.from.position_id)
+    .bind(dto.from.role_id)
+    .bind(dto.to.department_id)
+    .bind(dto.to.position_id)
+    .bind(dto.to.role_id)
+    .bind(Utc::now().naive_utc())
+    .execute(&mut *tx)
+    .await
+    ?;
+
+    let after = get_permission_matrix(&mut *tx, dto.to).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("permission_matrix", "copy", None)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn export_permissions<'e, E: PgExecutor<'e>>(pool: E) -> Result<Vec<PermissionExportRowDto>> {
+    let rows = sqlx::query_as::<_, PermissionExportRowDto>(
+        r#"
+        SELECT
+            d.name as department,
+            p.name as position,
+            r.name as role,
+            n.path as navigation_path,
+            COALESCE(rp.can_create, false) as can_create,
+            COALESCE(rp.can_read, false) as can_read,
+            COALESCE(rp.can_update, false) as can_update,
+            COALESCE(rp.can_delete, false) as can_delete,
+            rp.data_scope
+        FROM role_permissions rp
+        LEFT JOIN departments d ON rp.department_id = d.id
+        LEFT JOIN positions p ON rp.position_id = p.id
+        LEFT JOIN roles r ON rp.role_id = r.id
+        INNER JOIN navigation_items n ON rp.navigation_item_id = n.id
+        ORDER BY r.name, d.name, p.name, n.path
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    ?;
+
+    Ok(rows)
+}
+
+pub fn permissions_to_csv(rows: &[PermissionExportRowDto]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+pub fn permissions_from_csv(data: &str) -> Result<Vec<PermissionExportRowDto>> {
+    csv::Reader::from_reader(data.as_bytes())
+        .deserialize()
+        .enumerate()
+        .map(|(i, row)| row.with_context(|| format!("Row {}", i + 1)))
+        .collect()
+}
+
+async fn ids_by_name(pool: &PgPool, query: &str) -> Result<HashMap<String, Uuid>> {
+    let rows = sqlx::query_as::<_, (String, Uuid)>(query)
+        .fetch_all(pool)
+        .await
+        ?;
+    Ok(rows.into_iter().collect())
+}
+
+fn lookup(ids: &HashMap<String, Uuid>, name: Option<&str>, kind: &str) -> Result<Option<Uuid>> {
+    match name.filter(|n| !n.is_empty()) {
+        Some(name) => ids
+            .get(name)
+            .copied()
+            .map(Some)
+            .ok_or_else(|| anyhow!("Unknown {} \"{}\"", kind, name)),
+        None => Ok(None),
+    }
+}
+
+fn resolve_export_row(
+    row: &PermissionExportRowDto,
+    departments: &HashMap<String, Uuid>,
+    positions: &HashMap<String, Uuid>,
+    roles: &HashMap<String, Uuid>,
+    navigation: &HashMap<String, Uuid>,
+) -> Result<(PermissionTargetDto, PermissionMatrixEntryDto)> {
+    let target = PermissionTargetDto {
+        department_id: lookup(departments, row.department.as_deref(), "department")?,
+        position_id: lookup(positions, row.position.as_deref(), "position")?,
+        role_id: lookup(roles, row.role.as_deref(), "role")?,
+    };
+    validate_target(&target)?;
+
+    let navigation_item_id = lookup(navigation, Some(&row.navigation_path), "navigation path")?
+        .ok_or_else(|| anyhow!("navigation_path is required"))?;
+
+    Ok((
+        target,
+        PermissionMatrixEntryDto {
+            navigation_item_id,
+            can_create: row.can_create,
+            can_read: row.can_read,
+            can_update: row.can_update,
+            can_delete: row.can_delete,
+            data_scope: row.data_scope.clone(),
+        },
+    ))
+}
+
+/// Imports an export in one transaction. Each target in the file gets its
+/// permissions replaced by the file's rows; with `replace_all`, targets that
+/// aren't in the file lose theirs too. Grants on inactive navigation items
+/// only change when the file has a row for them. Returns the number of rows
+/// imported.
+pub async fn import_permissions(
+    pool: &PgPool,
+    ctx: &AuditContext,
+    rows: Vec<PermissionExportRowDto>,
+    replace_all: bool,
+) -> Result<usize> {
+    let departments = ids_by_name(pool, "SELECT name, id FROM departments").await?;
+    let positions = ids_by_name(pool, "SELECT name, id FROM positions").await?;
+    let roles = ids_by_name(pool, "SELECT name, id FROM roles").await?;
+    let navigation = ids_by_name(pool, "SELECT path, id FROM navigation_items").await?;
+
+    let mut targets: Vec<(PermissionTargetDto, Vec<PermissionMatrixEntryDto>)> = vec![];
+    let mut seen = HashSet::new();
+    for (i, row) in rows.iter().enumerate() {
+        let (target, entry) = resolve_export_row(row, &departments, &positions, &roles, &navigation)
+            .with_context(|| format!("Row {}", i + 1))?;
+        if !seen.insert((target, entry.navigation_item_id)) {
+            return Err(anyhow!("Row {}: duplicate entry for {}", i + 1, row.navigation_path));
+        }
+
+        match targets.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, entries)) => entries.push(entry),
+            None => targets.push((target, vec![entry])),
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let before = export_permissions(&mut *tx).await?;
+    if replace_all {
+        sqlx::query(
+            "DELETE FROM role_permissions WHERE navigation_item_id IN (SELECT id FROM navigation_items WHERE is_active = true)",
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    for (target, entries) in &targets {
+        replace_target_permissions(&mut tx, target, entries).await?;
+    }
+    let after = export_permissions(&mut *tx).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("permission_matrix", "import", None)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(rows.len())
+}
@@ -1,4 +1,8 @@
 use axum::{routing::{get, post, put, delete}, Router, middleware};
+use crate::api::document::{
+    routes::{document_report_routes, document_routes},
+    service::DocumentOwner,
+};
 use crate::api::person::handlers;
 use crate::middlewares::auth::authenticate;
 
@@ -6,8 +10,10 @@ pub fn person_routes() -> Router {
     Router::new()
         .route("/", post(handlers::create_person_handler))
         .route("/", get(handlers::list_persons_handler))
+        .nest("/documents", document_report_routes(DocumentOwner::Person))
         .route("/{id}", get(handlers::get_person_handler))
         .route("/{id}", put(handlers::update_person_handler))
         .route("/{id}", delete(handlers::delete_person_handler))
+        .nest("/{id}/documents", document_routes(DocumentOwner::Person))
         .layer(middleware::from_fn(authenticate)) // Protect all person routes
 }
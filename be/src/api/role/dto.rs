@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRoleDto {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleDto {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleResponseDto {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignRoleDto {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RoleMemberDto {
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub email: Option<String>,
+    pub assigned_at: NaiveDateTime,
+}
@@ -0,0 +1,209 @@
+use crate::{
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        role::{dto::*, service},
+    },
+    db::Db,
+    extractors::AuditContext,
+    models::role::Role,
+};
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    Json,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Role changes are saved together with their audit entry, so a failure at
+// any step leaves both out
+fn internal_error(e: impl std::fmt::Display) -> StatusCode {
+    eprintln!("Error saving role change: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn to_response(role: Role) -> RoleResponseDto {
+    RoleResponseDto {
+        id: role.id,
+        name: role.name,
+        description: role.description,
+        is_active: role.is_active,
+        created_at: role.created_at,
+        updated_at: role.updated_at,
+    }
+}
+
+pub async fn create_role_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Json(payload): Json<CreateRoleDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let mut tx = db.begin().await.map_err(internal_error)?;
+    let role = service::create_role(&mut tx, payload)
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    audit::record_in(
+        &mut tx,
+        &ctx,
+        AuditEntry::new("role", "create", Some(role.id)).after(&role),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((StatusCode::CREATED, Json(json!(to_response(role)))))
+}
+
+pub async fn get_roles_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let is_active = query.get("is_active").and_then(|v| v.parse::<bool>().ok());
+    let user_id = query.get("user_id").and_then(|v| Uuid::parse_str(v).ok());
+
+    let roles = service::get_roles(&db, is_active, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let response: Vec<RoleResponseDto> = roles.into_iter().map(to_response).collect();
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+pub async fn get_role_handler(
+    Extension(db): Extension<Db>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let role = service::get_role_by_id(&db, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok((StatusCode::OK, Json(json!(to_response(role)))))
+}
+
+pub async fn update_role_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateRoleDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let mut tx = db.begin().await.map_err(internal_error)?;
+    let before = service::get_role_for_update(&mut tx, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let role = service::update_role(&mut tx, before.clone(), payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error updating role: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+    audit::record_in(
+        &mut tx,
+        &ctx,
+        AuditEntry::new("role", "update", Some(id))
+            .before(&before)
+            .after(&role),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((StatusCode::OK, Json(json!(to_response(role)))))
+}
+
+pub async fn delete_role_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let mut tx = db.begin().await.map_err(internal_error)?;
+    let before = service::get_role_for_update(&mut tx, id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let role = service::delete_role(&mut tx, id)
+        .await
+        .map_err(internal_error)?;
+    audit::record_in(
+        &mut tx,
+        &ctx,
+        AuditEntry::new("role", "delete", Some(id))
+            .before(&before)
+            .after(&role),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Role deleted successfully"})),
+    ))
+}
+
+pub async fn get_role_members_handler(
+    Extension(db): Extension<Db>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let members = service::get_role_members(&db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::OK, Json(json!(members))))
+}
+
+pub async fn assign_role_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<AssignRoleDto>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let mut tx = db.begin().await.map_err(internal_error)?;
+    let assigned = service::assign_role(&mut tx, id, payload.user_id)
+        .await
+        .map_err(|e| {
+            eprintln!("Error assigning role: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+    // Assigning a role the user already has changes nothing
+    if assigned {
+        audit::record_in(
+            &mut tx,
+            &ctx,
+            AuditEntry::new("user_role", "assign", Some(id))
+                .after(&json!({"roleId": id, "userId": payload.user_id})),
+        )
+        .await
+        .map_err(internal_error)?;
+    }
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Role assigned successfully"})),
+    ))
+}
+
+pub async fn unassign_role_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let mut tx = db.begin().await.map_err(internal_error)?;
+    service::unassign_role(&mut tx, id, user_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    audit::record_in(
+        &mut tx,
+        &ctx,
+        AuditEntry::new("user_role", "unassign", Some(id))
+            .before(&json!({"roleId": id, "userId": user_id})),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Role removed successfully"})),
+    ))
+}
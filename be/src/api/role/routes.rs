@@ -0,0 +1,14 @@
+use crate::api::role::handlers;
+use axum::{routing::{delete, get, post, put}, Router};
+
+pub fn role_routes() -> Router {
+    Router::new()
+        .route("/", post(handlers::create_role_handler))
+        .route("/", get(handlers::get_roles_handler))
+        .route("/{id}", get(handlers::get_role_handler))
+        .route("/{id}", put(handlers::update_role_handler))
+        .route("/{id}", delete(handlers::delete_role_handler))
+        .route("/{id}/users", get(handlers::get_role_members_handler))
+        .route("/{id}/users", post(handlers::assign_role_handler))
+        .route("/{id}/users/{user_id}", delete(handlers::unassign_role_handler))
+}
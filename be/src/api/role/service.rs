@@ -0,0 +1,149 @@
+use crate::models::role::Role;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use super::dto::{CreateRoleDto, RoleMemberDto, UpdateRoleDto};
+
+pub async fn create_role(conn: &mut PgConnection, dto: CreateRoleDto) -> Result<Role> {
+    let role = sqlx::query_as::<_, Role>(
+        r#"
+        INSERT INTO roles (name, description, created_at, updated_at)
+        VALUES ($1, $2, $3, $3)
+        RETURNING *
+        "#,
+    )
+    .bind(&dto.name)
+    .bind(&dto.description)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(conn)
+    .await?;
+
+    Ok(role)
+}
+
+/// Lists roles, optionally only active ones or those assigned to a user.
+pub async fn get_roles(
+    pool: &PgPool,
+    is_active: Option<bool>,
+    user_id: Option<Uuid>,
+) -> Result<Vec<Role>> {
+    let roles = sqlx::query_as::<_, Role>(
+        r#"
+        SELECT * FROM roles
+        WHERE ($1::bool IS NULL OR is_active = $1)
+          AND ($2::uuid IS NULL OR id IN (SELECT role_id FROM user_roles WHERE user_id = $2))
+        ORDER BY name
+        "#,
+    )
+    .bind(is_active)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles)
+}
+
+pub async fn get_role_by_id(pool: &PgPool, id: Uuid) -> Result<Role> {
+    let role = sqlx::query_as::<_, Role>("SELECT * FROM roles WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow!("Role not found"))?;
+
+    Ok(role)
+}
+
+// Locks the role until the end of the caller's transaction
+pub async fn get_role_for_update(conn: &mut PgConnection, id: Uuid) -> Result<Role> {
+    let role = sqlx::query_as::<_, Role>("SELECT * FROM roles WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| anyhow!("Role not found"))?;
+
+    Ok(role)
+}
+
+pub async fn update_role(conn: &mut PgConnection, current: Role, dto: UpdateRoleDto) -> Result<Role> {
+    let role = sqlx::query_as::<_, Role>(
+        r#"
+        UPDATE roles
+        SET name = $1,
+            description = $2,
+            is_active = $3,
+            updated_at = $4
+        WHERE id = $5
+        RETURNING *
+        "#,
+    )
+    .bind(dto.name.unwrap_or(current.name))
+    .bind(dto.description.or(current.description))
+    .bind(dto.is_active.unwrap_or(current.is_active))
+    .bind(Utc::now().naive_utc())
+    .bind(current.id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(role)
+}
+
+// Deactivated roles keep their assignments but no longer grant anything
+pub async fn delete_role(conn: &mut PgConnection, id: Uuid) -> Result<Role> {
+    let role = sqlx::query_as::<_, Role>(
+        "UPDATE roles SET is_active = false, updated_at = $1 WHERE id = $2 RETURNING *",
+    )
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(role)
+}
+
+pub async fn get_role_members(pool: &PgPool, role_id: Uuid) -> Result<Vec<RoleMemberDto>> {
+    let members = sqlx::query_as::<_, RoleMemberDto>(
+        r#"
+        SELECT u.id as user_id, u.user_name, u.email, ur.created_at as assigned_at
+        FROM user_roles ur
+        INNER JOIN users u ON ur.user_id = u.id
+        WHERE ur.role_id = $1
+        ORDER BY u.user_name
+        "#,
+    )
+    .bind(role_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members)
+}
+
+/// Returns whether the user didn't have the role yet.
+pub async fn assign_role(conn: &mut PgConnection, role_id: Uuid, user_id: Uuid) -> Result<bool> {
+    get_role_for_update(conn, role_id).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO user_roles (user_id, role_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(role_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn unassign_role(conn: &mut PgConnection, role_id: Uuid, user_id: Uuid) -> Result<()> {
+    let result = sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2")
+        .bind(user_id)
+        .bind(role_id)
+        .execute(conn)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(anyhow!("User does not have this role"));
+    }
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{api_key::ApiKey, login_attempt::LoginAttempt};
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
@@ -12,6 +15,19 @@ pub struct CreateUserRequest {
     pub is_admin: Option<bool>,
 }
 
+/// Invites the person to choose their own username and password. `email`
+/// and `phone` default to the person's contact details.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteUserRequest {
+    pub person_id: Uuid,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    // Suggested username, the invitee can still change it
+    pub user_name: Option<String>,
+    pub is_admin: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateUserRequest {
@@ -26,3 +42,47 @@ pub struct UpdateUserRequest {
 pub struct ChangePasswordRequest {
     pub new_password: String,
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListLoginAttemptsQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub user_id: Option<Uuid>,
+    pub user_name: Option<String>,
+    pub ip_address: Option<String>,
+    pub success: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListLoginAttemptsResponse {
+    pub attempts: Vec<LoginAttempt>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateServiceAccountRequest {
+    pub user_name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// The new key with its plain text value, which is only ever returned here.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
@@ -1,8 +1,24 @@
-use axum::{Extension, Json, http::StatusCode};
+use axum::{Extension, Json, extract::{Path, Query}, http::StatusCode};
+use uuid::Uuid;
 use crate::{
-    api::user::{dto::CreateUserRequest, service},
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        auth::{
+            api_key::{self, ApiKeyAuth},
+            password_policy,
+        },
+        user::{
+            dto::{
+                CreateApiKeyRequest, CreateApiKeyResponse, CreateServiceAccountRequest,
+                CreateUserRequest, InviteUserRequest, ListLoginAttemptsQuery, ListLoginAttemptsResponse,
+            },
+            service,
+        },
+    },
     db::Db,
-    models::user::User,
+    extractors::AuditContext,
+    i18n::Locale,
+    models::{api_key::ApiKey, user::User},
 };
 
 pub async fn list_users_handler(
@@ -16,13 +32,20 @@ pub async fn list_users_handler(
 
 pub async fn create_user_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    locale: Locale,
     Json(payload): Json<CreateUserRequest>,
-) -> Result<(StatusCode, Json<User>), StatusCode> {
-    let user = service::create_user(&db, payload)
+) -> Result<(StatusCode, Json<User>), (StatusCode, Json<serde_json::Value>)> {
+    let user = service::create_user(&db, &ctx, payload)
         .await
         .map_err(|e| {
-            eprintln!("Error creating user: {}", e);
-            StatusCode::BAD_REQUEST
+            password_policy::error_response(&e, locale).unwrap_or_else(|| {
+                eprintln!("Error creating user: {}", e);
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                )
+            })
         })?;
     Ok((StatusCode::CREATED, Json(user)))
 }
@@ -37,13 +60,68 @@ pub async fn get_user_handler(
     Ok((StatusCode::OK, Json(user)))
 }
 
+pub async fn invite_user_handler(
+    Extension(db): Extension<Db>,
+    Extension(admin): Extension<User>,
+    ctx: AuditContext,
+    Json(payload): Json<InviteUserRequest>,
+) -> Result<(StatusCode, Json<User>), (StatusCode, Json<serde_json::Value>)> {
+    let user = service::invite_user(&db, &ctx, admin.id, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error inviting user: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+pub async fn resend_invitation_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    service::resend_invitation(&db, &ctx, id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"message": "Invitation sent"})),
+    ))
+}
+
+pub async fn revoke_invitation_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    service::revoke_invitation(&db, &ctx, id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn update_user_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
     Json(payload): Json<crate::api::user::dto::UpdateUserRequest>,
 ) -> Result<(StatusCode, Json<User>), StatusCode> {
     let user = service::update_user(
         &db,
+        &ctx,
         id,
         payload.user_name,
         payload.email,
@@ -60,9 +138,10 @@ pub async fn update_user_handler(
 
 pub async fn delete_user_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
     axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
 ) -> Result<StatusCode, StatusCode> {
-    service::delete_user(&db, id)
+    service::delete_user(&db, &ctx, id)
         .await
         .map_err(|e| {
             eprintln!("Error deleting user: {}", e);
@@ -73,14 +152,196 @@ pub async fn delete_user_handler(
 
 pub async fn change_password_handler(
     Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    locale: Locale,
     axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
     Json(payload): Json<crate::api::user::dto::ChangePasswordRequest>,
-) -> Result<StatusCode, StatusCode> {
-    service::change_password(&db, id, payload.new_password)
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    service::change_password(&db, &ctx, id, payload.new_password)
         .await
         .map_err(|e| {
-            eprintln!("Error changing password: {}", e);
-            StatusCode::BAD_REQUEST
+            password_policy::error_response(&e, locale).unwrap_or_else(|| {
+                eprintln!("Error changing password: {}", e);
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                )
+            })
         })?;
     Ok(StatusCode::OK)
 }
+
+pub async fn unlock_user_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::unlock_user(&db, &ctx, id)
+        .await
+        .map_err(|e| {
+            eprintln!("Error unlocking user: {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"message": "User unlocked"})),
+    ))
+}
+
+pub async fn deactivate_user_handler(
+    Extension(db): Extension<Db>,
+    Extension(current_user): Extension<User>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, Json<serde_json::Value>)> {
+    if id == current_user.id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "You can't deactivate your own account"})),
+        ));
+    }
+    let internal_error = |e: &dyn std::fmt::Display| {
+        eprintln!("Error deactivating user: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Unable to deactivate user"})))
+    };
+    let mut tx = db.begin().await.map_err(|e| internal_error(&e))?;
+    let before = service::get_by_id(&mut *tx, id).await.ok();
+    service::deactivate_user(&mut tx, id)
+        .await
+        .map_err(|e| {
+            eprintln!("Error deactivating user: {}", e);
+            (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": e.to_string()})))
+        })?;
+    let after = service::get_by_id(&mut *tx, id).await.ok();
+    audit::record_in(
+        &mut tx,
+        &ctx,
+        AuditEntry::new("user", "deactivate", Some(id))
+            .before(&before)
+            .after(&after),
+    )
+    .await
+    .map_err(|e| internal_error(&e))?;
+    tx.commit().await.map_err(|e| internal_error(&e))?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"message": "User deactivated"})),
+    ))
+}
+
+pub async fn reactivate_user_handler(
+    Extension(db): Extension<Db>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    service::reactivate_user(&db, &ctx, id)
+        .await
+        .map_err(|e| {
+            eprintln!("Error reactivating user: {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"message": "User reactivated"})),
+    ))
+}
+
+pub async fn list_login_attempts_handler(
+    Extension(db): Extension<Db>,
+    Query(query): Query<ListLoginAttemptsQuery>,
+) -> Result<(StatusCode, Json<ListLoginAttemptsResponse>), StatusCode> {
+    let attempts = service::list_login_attempts(&db, query)
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing login attempts: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::OK, Json(attempts)))
+}
+
+// Keys can't be used to manage keys, otherwise a `users:write` key could
+// mint itself broader ones
+fn reject_api_key(api_key: &Option<Extension<ApiKeyAuth>>) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if api_key.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "API keys cannot manage service accounts or keys"})),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn create_service_account_handler(
+    Extension(db): Extension<Db>,
+    api_key: Option<Extension<ApiKeyAuth>>,
+    ctx: AuditContext,
+    Json(payload): Json<CreateServiceAccountRequest>,
+) -> Result<(StatusCode, Json<User>), (StatusCode, Json<serde_json::Value>)> {
+    reject_api_key(&api_key)?;
+    let user = service::create_service_account(&db, &ctx, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error creating service account: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+pub async fn list_api_key_scopes_handler() -> (StatusCode, Json<Vec<String>>) {
+    (StatusCode::OK, Json(api_key::known_scopes()))
+}
+
+pub async fn list_api_keys_handler(
+    Extension(db): Extension<Db>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Vec<ApiKey>>), StatusCode> {
+    let keys = service::list_api_keys(&db, id)
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing API keys: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((StatusCode::OK, Json(keys)))
+}
+
+pub async fn create_api_key_handler(
+    Extension(db): Extension<Db>,
+    Extension(current_user): Extension<User>,
+    api_key: Option<Extension<ApiKeyAuth>>,
+    ctx: AuditContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), (StatusCode, Json<serde_json::Value>)> {
+    reject_api_key(&api_key)?;
+    let key = service::create_api_key(&db, &ctx, id, current_user.id, payload)
+        .await
+        .map_err(|e| {
+            eprintln!("Error creating API key: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+pub async fn revoke_api_key_handler(
+    Extension(db): Extension<Db>,
+    api_key: Option<Extension<ApiKeyAuth>>,
+    ctx: AuditContext,
+    Path((id, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    reject_api_key(&api_key)?;
+    service::revoke_api_key(&db, &ctx, id, key_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,17 +1,40 @@
-use axum::{Router, routing::{get, put, delete as del}};
+use axum::{Router, routing::{get, post, put, delete as del}};
 
 use crate::api::user::handler::{
     create_user_handler, 
+    invite_user_handler,
+    resend_invitation_handler,
+    revoke_invitation_handler,
     list_users_handler, 
     get_user_handler,
     update_user_handler,
     delete_user_handler,
     change_password_handler,
+    unlock_user_handler,
+    deactivate_user_handler,
+    reactivate_user_handler,
+    list_login_attempts_handler,
+    create_service_account_handler,
+    list_api_key_scopes_handler,
+    list_api_keys_handler,
+    create_api_key_handler,
+    revoke_api_key_handler,
 };
 
 pub fn user_routes() -> Router {
     Router::new()
         .route("/", get(list_users_handler).post(create_user_handler))
         .route("/{id}", get(get_user_handler).put(update_user_handler).delete(del(delete_user_handler)))
+        .route("/invite", post(invite_user_handler))
+        .route("/{id}/invitation", del(revoke_invitation_handler))
+        .route("/{id}/invitation/resend", post(resend_invitation_handler))
+        .route("/login-attempts", get(list_login_attempts_handler))
         .route("/{id}/password", put(change_password_handler))
+        .route("/{id}/unlock", put(unlock_user_handler))
+        .route("/{id}/deactivate", put(deactivate_user_handler))
+        .route("/{id}/reactivate", put(reactivate_user_handler))
+        .route("/service-accounts", post(create_service_account_handler))
+        .route("/api-key-scopes", get(list_api_key_scopes_handler))
+        .route("/{id}/api-keys", get(list_api_keys_handler).post(create_api_key_handler))
+        .route("/{id}/api-keys/{key_id}", del(revoke_api_key_handler))
 }
@@ -1,12 +1,33 @@
 use uuid::Uuid;
 use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+use sqlx::{PgConnection, PgExecutor};
 use crate::{
-    api::{auth::password::hash_password, user::dto::CreateUserRequest},
+    INVITATION_TTL_DAYS,
+    api::{
+        audit::service::{self as audit, AuditEntry},
+        auth::{
+            api_key,
+            email::{TokenPurpose, generate_verification_token},
+            password::{NO_PASSWORD, hash_password},
+            password_policy,
+            throttle::{self, ThrottleKey},
+        },
+        user::dto::{
+            CreateApiKeyRequest, CreateApiKeyResponse, CreateServiceAccountRequest,
+            CreateUserRequest, InviteUserRequest, ListLoginAttemptsQuery, ListLoginAttemptsResponse,
+        },
+    },
     db::Db,
-    models::user::User,
+    extractors::AuditContext,
+    mail::{outbox, templates::EmailTemplate},
+    models::{
+        api_key::ApiKey, login_attempt::LoginAttempt, person::Person,
+        person_contact::PersonContact, user::User,
+    },
 };
 
-pub async fn get_by_id(db: &Db, id: Uuid) -> Result<User, sqlx::Error> {
+pub async fn get_by_id<'e, E: PgExecutor<'e>>(db: E, id: Uuid) -> Result<User, sqlx::Error> {
     sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(id)
         .fetch_one(db)
@@ -22,7 +43,7 @@ pub async fn list_users(db: &Db) -> Result<Vec<User>, axum::http::StatusCode> {
     Ok(users)
 }
 
-pub async fn create_user(db: &Db, req: CreateUserRequest) -> Result<User> {
+pub async fn create_user(db: &Db, ctx: &AuditContext, req: CreateUserRequest) -> Result<User> {
     // Check if user already exists for this person
     let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE person_id = $1)")
         .bind(req.person_id)
@@ -45,13 +66,16 @@ pub async fn create_user(db: &Db, req: CreateUserRequest) -> Result<User> {
         return Err(anyhow!("Username already taken"));
     }
 
+    password_policy::check_new(&req.password, &req.user_name)?;
     let hashed = hash_password(&req.password)?;
     let user_id = Uuid::new_v4();
+    let mut tx = db.begin().await?;
 
+    // Addresses entered by an admin are trusted, so the account starts verified
     let user = sqlx::query_as::<_, User>(
         r#"
-        INSERT INTO users (id, user_name, email, phone, password_hash, person_id, is_admin, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+        INSERT INTO users (id, user_name, email, phone, password_hash, person_id, is_admin, created_at, email_verified_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
         RETURNING *
         "#,
     )
@@ -59,18 +83,204 @@ pub async fn create_user(db: &Db, req: CreateUserRequest) -> Result<User> {
     .bind(&req.user_name)
     .bind(&req.email)
     .bind(&req.phone)
-    .bind(hashed)
+    .bind(&hashed)
     .bind(req.person_id)
     .bind(req.is_admin.unwrap_or(false))
-    .fetch_one(db)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| anyhow!("Failed to create user: {}", e))?;
 
+    password_policy::record_history(&mut tx, user_id, &user.password_hash).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "create", Some(user.id)).after(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+/// Creates a pending account for the person, without a password, and emails
+/// them a link to choose their username and password. The address defaults
+/// to the person's first contact email.
+pub async fn invite_user(
+    db: &Db,
+    ctx: &AuditContext,
+    invited_by: Uuid,
+    req: InviteUserRequest,
+) -> Result<User> {
+    let person = sqlx::query_as::<_, Person>("SELECT * FROM persons WHERE id = $1")
+        .bind(req.person_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| anyhow!("Person not found"))?;
+
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE person_id = $1)")
+        .bind(person.id)
+        .fetch_one(db)
+        .await?;
+
+    if exists {
+        return Err(anyhow!("User already exists for this person"));
+    }
+
+    let contact = sqlx::query_as::<_, PersonContact>(
+        "SELECT * FROM person_contacts WHERE person_id = $1 ORDER BY created_at LIMIT 1",
+    )
+    .bind(person.id)
+    .fetch_optional(db)
+    .await?;
+
+    let email = req
+        .email
+        .or_else(|| contact.as_ref().map(|c| c.email.clone()))
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .ok_or_else(|| anyhow!("An email address is required to send the invitation"))?;
+    let phone = req.phone.or_else(|| contact.and_then(|c| c.phone));
+
+    // The invitee picks their own username; until then it is the one
+    // suggested by the admin or a placeholder
+    let user_name = match req.user_name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("invited-{}", &Uuid::new_v4().simple().to_string()[..8]),
+    };
+
+    let username_exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_name = $1)")
+        .bind(&user_name)
+        .fetch_one(db)
+        .await?;
+
+    if username_exists {
+        return Err(anyhow!("Username already taken"));
+    }
+
+    let mut tx = db.begin().await?;
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (id, user_name, email, phone, password_hash, person_id, is_admin, created_at, invited_by, invited_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), $8, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(&user_name)
+    .bind(&email)
+    .bind(&phone)
+    .bind(NO_PASSWORD)
+    .bind(person.id)
+    .bind(req.is_admin.unwrap_or(false))
+    .bind(invited_by)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| anyhow!("Failed to create user: {}", e))?;
+
+    send_invitation(&mut tx, &user, &person.first_name).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "invite", Some(user.id)).after(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
     Ok(user)
 }
 
+// Issues a new invitation link, invalidating any earlier one, and queues the email
+async fn send_invitation(conn: &mut PgConnection, user: &User, first_name: &str) -> Result<()> {
+    let email = user
+        .email
+        .as_deref()
+        .ok_or_else(|| anyhow!("User has no email address"))?;
+
+    sqlx::query(
+        "UPDATE email_verification_tokens SET used_at = NOW() WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+    )
+    .bind(user.id)
+    .bind(TokenPurpose::Invitation.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    let token = generate_verification_token();
+    sqlx::query(
+        "INSERT INTO email_verification_tokens (id, user_id, token, expires_at, purpose) VALUES ($1,$2,$3,$4,$5)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user.id)
+    .bind(&token)
+    .bind((Utc::now() + Duration::days(INVITATION_TTL_DAYS)).naive_utc())
+    .bind(TokenPurpose::Invitation.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    let message = EmailTemplate::Invitation {
+        first_name,
+        token: &token,
+        expires_in_days: INVITATION_TTL_DAYS,
+    }
+    .render(email);
+    outbox::enqueue(&mut *conn, &message).await?;
+    Ok(())
+}
+
+/// Sends a fresh invitation link to a user who hasn't accepted yet.
+pub async fn resend_invitation(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE id = $1 AND invited_at IS NOT NULL AND invitation_accepted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| anyhow!("User has no pending invitation"))?;
+
+    let first_name = sqlx::query_scalar::<_, String>("SELECT first_name FROM persons WHERE id = $1")
+        .bind(user.person_id)
+        .fetch_optional(db)
+        .await?
+        .unwrap_or_default();
+
+    let mut tx = db.begin().await?;
+    send_invitation(&mut tx, &user, &first_name).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "resend_invitation", Some(id)),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Withdraws a pending invitation. The pending account is deleted so the
+/// person can be invited or given an account again.
+pub async fn revoke_invitation(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let user = sqlx::query_as::<_, User>(
+        "DELETE FROM users WHERE id = $1 AND invited_at IS NOT NULL AND invitation_accepted_at IS NULL RETURNING *",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("User has no pending invitation"))?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "revoke_invitation", Some(id)).before(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn update_user(
     db: &Db,
+    ctx: &AuditContext,
     id: Uuid,
     user_name: Option<String>,
     email: Option<String>,
@@ -93,6 +303,13 @@ pub async fn update_user(
         }
     }
 
+    let mut tx = db.begin().await?;
+    let before = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("User not found"))?;
+
     // Build dynamic update query
     let user = sqlx::query_as::<_, User>(
         r#"
@@ -111,40 +328,364 @@ pub async fn update_user(
     .bind(phone)
     .bind(is_admin)
     .bind(id)
-    .fetch_one(db)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| anyhow!("Failed to update user: {}", e))?;
 
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "update", Some(id))
+            .before(&before)
+            .after(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
     Ok(user)
 }
 
-pub async fn delete_user(db: &Db, id: Uuid) -> Result<()> {
-    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+pub async fn delete_user(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let user = sqlx::query_as::<_, User>("DELETE FROM users WHERE id = $1 RETURNING *")
         .bind(id)
-        .execute(db)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| anyhow!("Failed to delete user: {}", e))?;
+        .map_err(|e| anyhow!("Failed to delete user: {}", e))?
+        .ok_or_else(|| anyhow!("User not found"))?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "delete", Some(id)).before(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn change_password(
+    db: &Db,
+    ctx: &AuditContext,
+    id: Uuid,
+    new_password: String,
+) -> Result<()> {
+    let mut tx = db.begin().await?;
+
+    let (user_name, ldap_dn) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT user_name, ldap_dn FROM users WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| anyhow!("User not found"))?;
+
+    if ldap_dn.is_some() {
+        return Err(anyhow!("Password is managed by the LDAP directory"));
+    }
+
+    password_policy::set_password(&mut tx, id, &user_name, &new_password).await?;
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "change_password", Some(id)),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Lifts a login lockout or backoff on the user's account.
+pub async fn unlock_user(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let user = get_by_id(&mut *tx, id)
+        .await
+        .map_err(|_| anyhow!("User not found"))?;
+
+    throttle::reset(&mut *tx, &ThrottleKey::user(&user.user_name)).await?;
+    audit::record_in(&mut tx, ctx, AuditEntry::new("user", "unlock", Some(id))).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Stops the user signing in, e.g. once they've left the company: their
+/// sessions and API keys are revoked. Their data stays.
+pub async fn deactivate_user(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+    let result = sqlx::query(
+        "UPDATE users SET deactivated_at = COALESCE(deactivated_at, NOW()) WHERE id = $1",
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
 
     if result.rows_affected() == 0 {
         return Err(anyhow!("User not found"));
     }
 
+    sqlx::query(
+        "UPDATE user_sessions SET revoked_at = NOW(), revoked_reason = 'deactivated' WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
     Ok(())
 }
 
-pub async fn change_password(db: &Db, id: Uuid, new_password: String) -> Result<()> {
-    let hashed = hash_password(&new_password)?;
-
-    let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
-        .bind(hashed)
+/// Lets a deactivated user sign in again. Revoked sessions and API keys
+/// stay revoked.
+pub async fn reactivate_user(db: &Db, ctx: &AuditContext, id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let before = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 FOR UPDATE")
         .bind(id)
-        .execute(db)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("User not found"))?;
+
+    let after = sqlx::query_as::<_, User>(
+        "UPDATE users SET deactivated_at = NULL WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "reactivate", Some(id))
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn list_login_attempts(
+    db: &Db,
+    query: ListLoginAttemptsQuery,
+) -> Result<ListLoginAttemptsResponse> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).min(200);
+    let offset = (page - 1) * page_size;
+
+    let mut conditions: Vec<String> = vec!["1=1".to_string()];
+    let mut param_index = 1;
+
+    if query.user_id.is_some() {
+        conditions.push(format!("user_id = ${}", param_index));
+        param_index += 1;
+    }
+
+    if query.user_name.is_some() {
+        conditions.push(format!("LOWER(user_name) = LOWER(${})", param_index));
+        param_index += 1;
+    }
+
+    if query.ip_address.is_some() {
+        conditions.push(format!("ip_address = ${}", param_index));
+        param_index += 1;
+    }
+
+    if query.success.is_some() {
+        conditions.push(format!("success = ${}", param_index));
+        param_index += 1;
+    }
+
+    let where_clause = conditions.join(" AND ");
+    let count_query = format!("SELECT COUNT(*) FROM login_attempts WHERE {}", where_clause);
+    let select_query = format!(
+        "SELECT * FROM login_attempts WHERE {} ORDER BY created_at DESC LIMIT ${} OFFSET ${}",
+        where_clause,
+        param_index,
+        param_index + 1
+    );
+
+    let mut count_q = sqlx::query_scalar::<_, i64>(&count_query);
+    let mut select_q = sqlx::query_as::<_, LoginAttempt>(&select_query);
+
+    if let Some(user_id) = query.user_id {
+        count_q = count_q.bind(user_id);
+        select_q = select_q.bind(user_id);
+    }
+
+    if let Some(user_name) = &query.user_name {
+        count_q = count_q.bind(user_name);
+        select_q = select_q.bind(user_name);
+    }
+
+    if let Some(ip_address) = &query.ip_address {
+        count_q = count_q.bind(ip_address);
+        select_q = select_q.bind(ip_address);
+    }
+
+    if let Some(success) = query.success {
+        count_q = count_q.bind(success);
+        select_q = select_q.bind(success);
+    }
+
+    select_q = select_q.bind(page_size).bind(offset);
+
+    let total = count_q.fetch_one(db).await?;
+    let attempts = select_q.fetch_all(db).await?;
+
+    Ok(ListLoginAttemptsResponse {
+        attempts,
+        total,
+        page,
+        page_size,
+    })
+}
+
+/// Creates a user that can only authenticate with API keys: it has no person,
+/// email, phone or usable password.
+pub async fn create_service_account(
+    db: &Db,
+    ctx: &AuditContext,
+    req: CreateServiceAccountRequest,
+) -> Result<User> {
+    let user_name = req.user_name.trim();
+    if user_name.is_empty() {
+        return Err(anyhow!("Username is required"));
+    }
+
+    let username_exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_name = $1)")
+        .bind(user_name)
+        .fetch_one(db)
         .await
-        .map_err(|e| anyhow!("Failed to change password: {}", e))?;
+        .map_err(|e| anyhow!("Database error: {}", e))?;
+
+    if username_exists {
+        return Err(anyhow!("Username already taken"));
+    }
+
+    let mut tx = db.begin().await?;
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (id, user_name, password_hash, is_service_account, description, created_at)
+        VALUES ($1, $2, $3, true, $4, NOW())
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_name)
+    .bind(NO_PASSWORD)
+    .bind(&req.description)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| anyhow!("Failed to create service account: {}", e))?;
+
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("user", "create_service_account", Some(user.id)).after(&user),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+pub async fn list_api_keys(db: &Db, user_id: Uuid) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Issues a key for a service account. The plain text key is returned once
+/// and only its hash is kept.
+pub async fn create_api_key(
+    db: &Db,
+    ctx: &AuditContext,
+    user_id: Uuid,
+    created_by: Uuid,
+    req: CreateApiKeyRequest,
+) -> Result<CreateApiKeyResponse> {
+    let user = get_by_id(db, user_id)
+        .await
+        .map_err(|_| anyhow!("User not found"))?;
+    if !user.is_service_account {
+        return Err(anyhow!("API keys can only be issued to service accounts"));
+    }
+
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Name is required"));
+    }
+    if req.scopes.is_empty() {
+        return Err(anyhow!("At least one scope is required"));
+    }
+    let known = api_key::known_scopes();
+    if let Some(scope) = req.scopes.iter().find(|s| !known.contains(s)) {
+        return Err(anyhow!("Unknown scope: {}", scope));
+    }
+    if let Some(expires_at) = req.expires_at
+        && expires_at <= chrono::Utc::now().naive_utc()
+    {
+        return Err(anyhow!("Expiry must be in the future"));
+    }
+
+    let (key, prefix) = api_key::generate_key();
+    let mut tx = db.begin().await?;
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(&prefix)
+    .bind(api_key::hash_key(&key))
+    .bind(&req.scopes)
+    .bind(req.expires_at)
+    .bind(created_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Without the key itself, which is only ever shown once
+    audit::record_in(
+        &mut tx,
+        ctx,
+        AuditEntry::new("api_key", "create", Some(api_key.id)).after(&api_key),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(CreateApiKeyResponse { api_key, key })
+}
+
+pub async fn revoke_api_key(db: &Db, ctx: &AuditContext, user_id: Uuid, key_id: Uuid) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
 
     if result.rows_affected() == 0 {
-        return Err(anyhow!("User not found"));
+        return Err(anyhow!("API key not found"));
     }
 
+    audit::record_in(&mut tx, ctx, AuditEntry::new("api_key", "revoke", Some(key_id))).await?;
+    tx.commit().await?;
+
     Ok(())
 }
@@ -0,0 +1,282 @@
+use std::{
+    convert::Infallible,
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{
+        header::{ACCEPT_LANGUAGE, USER_AGENT},
+        request::Parts,
+    },
+};
+
+use uuid::Uuid;
+
+use crate::{
+    i18n::Locale,
+    middlewares::request_id::RequestId,
+    models::{impersonation::Impersonation, user::User},
+};
+
+/// Caller's IP address and user agent. The address is the socket peer's,
+/// unless the peer is one of `TRUSTED_PROXIES` (such as the nginx proxy);
+/// then it is the right-most `X-Forwarded-For` hop that isn't a trusted
+/// proxy, or `X-Real-IP`. Clients can't set their own address this way.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientInfo::from_parts(parts))
+    }
+}
+
+impl ClientInfo {
+    pub fn from_parts(parts: &Parts) -> Self {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip_address = client_ip(
+            peer,
+            header("x-forwarded-for"),
+            header("x-real-ip"),
+            trusted_proxies(),
+        )
+        .map(|ip| ip.to_string());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.chars().take(512).collect());
+
+        ClientInfo {
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+/// An address or network (`10.0.0.0/8`) in `TRUSTED_PROXIES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, prefix.parse().ok()?),
+            None => {
+                let address = value.parse::<IpAddr>().ok()?;
+                (address, if address.is_ipv4() { 32 } else { 128 })
+            }
+        };
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        (prefix <= max).then_some(IpNetwork { address, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        let (network, ip, bits) = match (self.address, ip) {
+            (IpAddr::V4(n), IpAddr::V4(i)) => (u32::from(n) as u128, u32::from(i) as u128, 32),
+            (IpAddr::V6(n), IpAddr::V6(i)) => (u128::from(n), u128::from(i), 128),
+            _ => return false,
+        };
+        let shift = bits - self.prefix as u32;
+        shift >= bits || (network >> shift) == (ip >> shift)
+    }
+}
+
+static TRUSTED_PROXIES: OnceLock<Vec<IpNetwork>> = OnceLock::new();
+
+/// Comma separated addresses or networks from `TRUSTED_PROXIES`; none by
+/// default. Invalid entries are logged and skipped.
+fn trusted_proxies() -> &'static [IpNetwork] {
+    TRUSTED_PROXIES.get_or_init(|| {
+        env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .filter_map(|v| {
+                let network = IpNetwork::parse(v);
+                if network.is_none() {
+                    tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry: {}", v);
+                }
+                network
+            })
+            .collect()
+    })
+}
+
+fn client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    real_ip: Option<&str>,
+    trusted: &[IpNetwork],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|n| n.contains(ip));
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+
+    if let Some(forwarded_for) = forwarded_for {
+        // Each proxy appends the address it got the request from, so walk
+        // back from the right until the first hop we don't trust
+        let mut client = peer;
+        for hop in forwarded_for.rsplit(',').map(str::trim) {
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !is_trusted(ip) {
+                break;
+            }
+        }
+        return Some(client);
+    }
+
+    real_ip
+        .and_then(|v| v.parse::<IpAddr>().ok())
+        .or(Some(peer))
+}
+
+/// The response language from the `Accept-Language` header.
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(Locale::from_accept_language)
+            .unwrap_or_default())
+    }
+}
+
+/// Who is making a change and from where, for the audit log. The actor is
+/// the authenticated user, so this only has one behind `authenticate`; while
+/// impersonating, the admin is kept as the impersonator.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<Uuid>,
+    pub impersonator_id: Option<Uuid>,
+    pub client: ClientInfo,
+    pub request_id: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AuditContext {
+            actor_id: parts.extensions.get::<User>().map(|u| u.id),
+            impersonator_id: parts
+                .extensions
+                .get::<Impersonation>()
+                .map(|i| i.admin_id),
+            client: ClientInfo::from_parts(parts),
+            request_id: parts
+                .extensions
+                .get::<RequestId>()
+                .map(|RequestId(id)| id.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn networks(values: &[&str]) -> Vec<IpNetwork> {
+        values.iter().map(|v| IpNetwork::parse(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn ignores_forwarded_headers_from_untrusted_peers() {
+        let trusted = networks(&["127.0.0.1"]);
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), Some("1.2.3.4"), Some("5.6.7.8"), &trusted),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), Some("1.2.3.4"), None, &[]),
+            Some(ip("203.0.113.9"))
+        );
+    }
+
+    #[test]
+    fn takes_right_most_untrusted_hop_behind_trusted_proxy() {
+        let trusted = networks(&["127.0.0.1", "10.0.0.0/8"]);
+        // The client forged the first entry; the proxies appended the rest
+        assert_eq!(
+            client_ip(
+                Some(ip("127.0.0.1")),
+                Some("9.9.9.9, 198.51.100.7, 10.1.2.3"),
+                None,
+                &trusted
+            ),
+            Some(ip("198.51.100.7"))
+        );
+    }
+
+    #[test]
+    fn stops_at_unparseable_hops() {
+        let trusted = networks(&["127.0.0.1"]);
+        assert_eq!(
+            client_ip(Some(ip("127.0.0.1")), Some("garbage"), None, &trusted),
+            Some(ip("127.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn uses_real_ip_from_trusted_proxy_without_forwarded_for() {
+        let trusted = networks(&["127.0.0.1"]);
+        assert_eq!(
+            client_ip(Some(ip("127.0.0.1")), None, Some("198.51.100.7"), &trusted),
+            Some(ip("198.51.100.7"))
+        );
+        assert_eq!(client_ip(None, Some("1.2.3.4"), None, &trusted), None);
+    }
+
+    #[test]
+    fn matches_networks() {
+        let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains(ip("10.255.0.1")));
+        assert!(!network.contains(ip("11.0.0.1")));
+        assert!(network.contains(ip("::ffff:10.0.0.1")));
+        assert!(IpNetwork::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(IpNetwork::parse("fd00::/8").unwrap().contains(ip("fd12::1")));
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("nonsense").is_none());
+    }
+}
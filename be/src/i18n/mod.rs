@@ -0,0 +1,81 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::OnceLock,
+};
+
+/// A language the API can answer in. English text lives in the code next to
+/// each message code; other languages are catalogs of code to text, embedded
+/// from `i18n/<locale>.json`. Placeholders like `{min_length}` are filled
+/// from the message's params.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Ne,
+}
+
+static NE_CATALOG: &str = include_str!("ne.json");
+
+static CATALOGS: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    CATALOGS.get_or_init(|| {
+        HashMap::from([(
+            Locale::Ne.as_str(),
+            serde_json::from_str(NE_CATALOG).expect("i18n/ne.json is not a valid catalog"),
+        )])
+    })
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Ne];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ne => "ne",
+        }
+    }
+
+    /// Matches a language tag on its primary subtag, so "ne-NP" is Nepali.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        Locale::ALL
+            .into_iter()
+            .find(|l| l.as_str().eq_ignore_ascii_case(primary))
+    }
+
+    /// The supported language the client prefers most in an
+    /// `Accept-Language` header, English when none is supported.
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut best: Option<(Locale, f32)> = None;
+        for entry in header.split(',') {
+            let mut parts = entry.split(';');
+            let Some(locale) = parts.next().and_then(Locale::parse) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+/// The text for message `code` in `locale` with its params filled in, or
+/// `None` when the catalog has no entry, in which case the English message
+/// from the code should be kept.
+pub fn translate(locale: Locale, code: &str, params: &BTreeMap<String, String>) -> Option<String> {
+    let text = catalogs().get(locale.as_str())?.get(code)?;
+    Some(
+        params
+            .iter()
+            .fold(text.clone(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            }),
+    )
+}
@@ -1,10 +1,14 @@
 pub mod api;
 pub mod db;
 pub mod errors;
+pub mod extractors;
+pub mod i18n;
+pub mod mail;
 pub mod middleware;
 pub mod middlewares;
 pub mod models;
 pub mod routes;
+pub mod storage;
 
 pub use db::init_pool;
 pub use routes::build_routes;
@@ -12,3 +16,6 @@ pub use routes::build_routes;
 pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
 pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
 pub const VERIFICATION_TOKEN_TTL_HOURS: i64 = 3;
+pub const MFA_TOKEN_TTL_MINUTES: i64 = 5;
+pub const IMPERSONATION_TTL_MINUTES: i64 = 30;
+pub const INVITATION_TTL_DAYS: i64 = 7;
@@ -0,0 +1,47 @@
+use std::{env, path::PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use uuid::Uuid;
+
+use crate::mail::{EmailMessage, Mailer, build_message, sender_from_env};
+
+/// Writes each message as an `.eml` file into a maildir (`tmp/`, `new/`,
+/// `cur/`) so development and tests can inspect mail without a server.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: Mailbox) -> Result<Self> {
+        let dir = dir.into();
+        for sub in ["tmp", "new", "cur"] {
+            std::fs::create_dir_all(dir.join(sub))
+                .with_context(|| format!("Failed to create maildir {}", dir.display()))?;
+        }
+        Ok(FileMailer { dir, from })
+    }
+
+    /// Uses `MAIL_DIR`, defaulting to `./maildir`.
+    pub fn from_env() -> Result<Self> {
+        let dir = env::var("MAIL_DIR").unwrap_or_else(|_| "maildir".to_string());
+        FileMailer::new(dir, sender_from_env()?)
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_message(&self.from, message)?;
+        let name = format!("{}.{}.eml", Utc::now().timestamp(), Uuid::new_v4());
+
+        // Write to tmp/ first and rename so readers never see a partial file
+        let tmp_path = self.dir.join("tmp").join(&name);
+        tokio::fs::write(&tmp_path, email.formatted()).await?;
+        tokio::fs::rename(&tmp_path, self.dir.join("new").join(&name)).await?;
+        Ok(())
+    }
+}
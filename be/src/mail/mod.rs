@@ -0,0 +1,81 @@
+pub mod file;
+pub mod outbox;
+pub mod smtp;
+pub mod templates;
+
+use std::{env, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use lettre::{
+    Message,
+    message::{Mailbox, MultiPart},
+};
+
+use crate::mail::{file::FileMailer, smtp::SmtpMailer};
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+/// A delivery backend. Services never call this directly; they queue messages
+/// with `outbox::enqueue` and the outbox worker hands them to the mailer.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<()>;
+}
+
+/// Only logs the message. Used when no backend is configured.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        tracing::info!(
+            "[EMAIL MOCK] to={} subject={:?}\n{}",
+            message.to,
+            message.subject,
+            message.text_body
+        );
+        Ok(())
+    }
+}
+
+/// Builds the mailer selected by `MAIL_BACKEND` (`smtp`, `file` or `log`).
+pub fn mailer_from_env() -> Result<Arc<dyn Mailer>> {
+    let backend = env::var("MAIL_BACKEND").unwrap_or_else(|_| "log".to_string());
+    match backend.as_str() {
+        "smtp" => Ok(Arc::new(SmtpMailer::from_env()?)),
+        "file" => Ok(Arc::new(FileMailer::from_env()?)),
+        "log" => Ok(Arc::new(LogMailer)),
+        other => Err(anyhow!("Unknown MAIL_BACKEND: {}", other)),
+    }
+}
+
+pub(crate) fn sender_from_env() -> Result<Mailbox> {
+    env::var("MAIL_FROM")
+        .unwrap_or_else(|_| "Ubuck ERP <no-reply@localhost>".to_string())
+        .parse()
+        .map_err(|e| anyhow!("Invalid MAIL_FROM: {}", e))
+}
+
+pub(crate) fn build_message(from: &Mailbox, message: &EmailMessage) -> Result<Message> {
+    let to: Mailbox = message
+        .to
+        .parse()
+        .map_err(|e| anyhow!("Invalid recipient {}: {}", message.to, e))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            message.html_body.clone(),
+        ))
+        .map_err(|e| anyhow!("Failed to build email: {}", e))
+}
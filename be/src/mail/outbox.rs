@@ -0,0 +1,117 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::{
+    db::Db,
+    mail::{EmailMessage, Mailer},
+    models::email_outbox::OutboxEmail,
+};
+
+const POLL_INTERVAL_SECONDS: u64 = 15;
+const BATCH_SIZE: i64 = 20;
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_SECONDS: i64 = 30;
+
+/// Queues a message for delivery. Pass the caller's transaction so the email
+/// is only sent if the surrounding change commits.
+pub async fn enqueue<'e, E: PgExecutor<'e>>(executor: E, message: &EmailMessage) -> Result<Uuid> {
+    let id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO email_outbox (recipient, subject, html_body, text_body)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(&message.to)
+    .bind(&message.subject)
+    .bind(&message.html_body)
+    .bind(&message.text_body)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Sends one batch of due messages and returns how many were delivered.
+/// Failures are retried with exponential backoff until `MAX_ATTEMPTS`.
+pub async fn process_pending(db: &Db, mailer: &dyn Mailer) -> Result<usize> {
+    let mut tx = db.begin().await?;
+
+    // SKIP LOCKED lets several instances drain the outbox without double sends
+    let due = sqlx::query_as::<_, OutboxEmail>(
+        r#"
+        SELECT * FROM email_outbox
+        WHERE status = 'pending' AND next_attempt_at <= NOW()
+        ORDER BY created_at
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut sent = 0;
+    for email in due {
+        let message = EmailMessage {
+            to: email.recipient,
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+        };
+
+        match mailer.send(&message).await {
+            Ok(()) => {
+                sqlx::query(
+                    "UPDATE email_outbox SET status = 'sent', attempts = attempts + 1, sent_at = NOW(), last_error = NULL WHERE id = $1",
+                )
+                .bind(email.id)
+                .execute(&mut *tx)
+                .await?;
+                sent += 1;
+            }
+            Err(e) => {
+                let attempts = email.attempts + 1;
+                let status = if attempts >= MAX_ATTEMPTS { "failed" } else { "pending" };
+                let delay = BASE_RETRY_SECONDS * 2_i64.pow(attempts as u32 - 1);
+                tracing::warn!("Email {} delivery attempt {} failed: {}", email.id, attempts, e);
+
+                sqlx::query(
+                    r#"
+                    UPDATE email_outbox
+                    SET status = $2,
+                        attempts = $3,
+                        last_error = $4,
+                        next_attempt_at = NOW() + make_interval(secs => $5)
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(email.id)
+                .bind(status)
+                .bind(attempts)
+                .bind(e.to_string())
+                .bind(delay as f64)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(sent)
+}
+
+pub fn spawn_worker(db: Db, mailer: Arc<dyn Mailer>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(POLL_INTERVAL_SECONDS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = process_pending(&db, mailer.as_ref()).await {
+                tracing::error!("Email outbox worker error: {}", e);
+            }
+        }
+    })
+}
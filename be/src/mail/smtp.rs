@@ -0,0 +1,57 @@
+use std::env;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor, message::Mailbox,
+    transport::smtp::authentication::Credentials,
+};
+
+use crate::mail::{EmailMessage, Mailer, build_message, sender_from_env};
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Reads `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD` and
+    /// `SMTP_TLS` (`starttls` by default, `tls` for implicit TLS or `none`).
+    pub fn from_env() -> Result<Self> {
+        let host = env::var("SMTP_HOST").context("SMTP_HOST not set")?;
+        let tls = env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+
+        let mut builder = match tls.as_str() {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => return Err(anyhow!("Unknown SMTP_TLS mode: {}", other)),
+        };
+
+        if let Ok(port) = env::var("SMTP_PORT") {
+            builder = builder.port(port.parse().context("SMTP_PORT must be a number")?);
+        }
+
+        if let Ok(username) = env::var("SMTP_USERNAME") {
+            let password = env::var("SMTP_PASSWORD").unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from: sender_from_env()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_message(&self.from, message)?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| anyhow!("SMTP delivery failed: {}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+
+use chrono::NaiveDate;
+
+use crate::mail::EmailMessage;
+
+pub enum EmailTemplate<'a> {
+    VerifyEmail {
+        token: &'a str,
+    },
+    PasswordReset {
+        token: &'a str,
+    },
+    Invitation {
+        first_name: &'a str,
+        token: &'a str,
+        expires_in_days: i64,
+    },
+    LeaveApproved {
+        employee_name: &'a str,
+        leave_type: &'a str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        notes: Option<&'a str>,
+    },
+    LeaveRejected {
+        employee_name: &'a str,
+        leave_type: &'a str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        notes: Option<&'a str>,
+    },
+    PayslipReady {
+        employee_name: &'a str,
+        period: &'a str,
+    },
+}
+
+// Frontend origin used to build links in emails
+pub(crate) fn app_base_url() -> String {
+    env::var("APP_BASE_URL")
+        .unwrap_or_else(|_| "http://localhost:3110".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Wraps the body paragraphs in the shared HTML layout
+fn layout(title: &str, paragraphs: &[String]) -> String {
+    let body: String = paragraphs
+        .iter()
+        .map(|p| format!("<p>{}</p>", p))
+        .collect();
+    format!(
+        "<!DOCTYPE html><html><body style=\"font-family: sans-serif; color: #222;\">\
+         <h2>{}</h2>{}<p style=\"color: #888; font-size: 12px;\">Ubuck ERP</p></body></html>",
+        escape_html(title),
+        body
+    )
+}
+
+fn link_paragraph(url: &str, label: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(label))
+}
+
+impl EmailTemplate<'_> {
+    pub fn render(&self, to: &str) -> EmailMessage {
+        let (subject, html_body, text_body) = match self {
+            EmailTemplate::VerifyEmail { token } => {
+                let url = format!("{}/verify-email?token={}", app_base_url(), token);
+                let subject = "Verify your email address".to_string();
+                let html = layout(
+                    &subject,
+                    &[
+                        "Please confirm your email address to activate your account.".to_string(),
+                        link_paragraph(&url, "Verify email address"),
+                    ],
+                );
+                let text = format!(
+                    "Please confirm your email address to activate your account.\n\n{}\n",
+                    url
+                );
+                (subject, html, text)
+            }
+            EmailTemplate::PasswordReset { token } => {
+                let url = format!("{}/reset-password?token={}", app_base_url(), token);
+                let subject = "Reset your password".to_string();
+                let html = layout(
+                    &subject,
+                    &[
+                        "We received a request to reset your password.".to_string(),
+                        link_paragraph(&url, "Choose a new password"),
+                        "If you didn't ask for this you can ignore this email.".to_string(),
+                    ],
+                );
+                let text = format!(
+                    "We received a request to reset your password.\n\n{}\n\nIf you didn't ask for this you can ignore this email.\n",
+                    url
+                );
+                (subject, html, text)
+            }
+            EmailTemplate::Invitation {
+                first_name,
+                token,
+                expires_in_days,
+            } => {
+                let url = format!("{}/accept-invitation?token={}", app_base_url(), token);
+                let subject = "You have been invited to Ubuck ERP".to_string();
+                let expiry = format!("The link expires in {} days.", expires_in_days);
+                let html = layout(
+                    &subject,
+                    &[
+                        format!("Hi {},", escape_html(first_name)),
+                        "An account has been created for you. Choose your username and password to get started.".to_string(),
+                        link_paragraph(&url, "Accept invitation"),
+                        expiry.clone(),
+                    ],
+                );
+                let text = format!(
+                    "Hi {},\n\nAn account has been created for you. Choose your username and password to get started.\n\n{}\n\n{}\n",
+                    first_name, url, expiry
+                );
+                (subject, html, text)
+            }
+            EmailTemplate::LeaveApproved {
+                employee_name,
+                leave_type,
+                start_date,
+                end_date,
+                notes,
+            }
+            | EmailTemplate::LeaveRejected {
+                employee_name,
+                leave_type,
+                start_date,
+                end_date,
+                notes,
+            } => {
+                let decision = if matches!(self, EmailTemplate::LeaveApproved { .. }) {
+                    "approved"
+                } else {
+                    "rejected"
+                };
+                let subject = format!("Your leave request was {}", decision);
+                let summary = format!(
+                    "Your {} request from {} to {} has been {}.",
+                    leave_type, start_date, end_date, decision
+                );
+                let mut paragraphs = vec![
+                    format!("Hi {},", escape_html(employee_name)),
+                    escape_html(&summary),
+                ];
+                let mut text = format!("Hi {},\n\n{}\n", employee_name, summary);
+                if let Some(notes) = notes {
+                    paragraphs.push(format!("Notes: {}", escape_html(notes)));
+                    text.push_str(&format!("\nNotes: {}\n", notes));
+                }
+                (subject.clone(), layout(&subject, &paragraphs), text)
+            }
+            EmailTemplate::PayslipReady {
+                employee_name,
+                period,
+            } => {
+                let url = format!("{}/admin/profile/information", app_base_url());
+                let subject = format!("Your payslip for {} is ready", period);
+                let html = layout(
+                    &subject,
+                    &[
+                        format!("Hi {},", escape_html(employee_name)),
+                        format!(
+                            "Your payslip for {} is now available.",
+                            escape_html(period)
+                        ),
+                        link_paragraph(&url, "View payslip"),
+                    ],
+                );
+                let text = format!(
+                    "Hi {},\n\nYour payslip for {} is now available.\n\n{}\n",
+                    employee_name, period, url
+                );
+                (subject, html, text)
+            }
+        };
+
+        EmailMessage {
+            to: to.to_string(),
+            subject,
+            html_body,
+            text_body,
+        }
+    }
+}
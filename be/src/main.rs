@@ -1,13 +1,20 @@
 use be::{
+    api::{
+        auth::{jwt, ldap, purge},
+        employee::history,
+    },
     build_routes,
     init_pool,
+    mail,
     middleware,
+    middlewares::request_id::{REQUEST_ID_HEADER, request_id},
+    storage,
 };
 
 use dotenvy::dotenv;
 use std::net::SocketAddr;
 use tower_http::{cors::{CorsLayer, Any}, trace::TraceLayer, set_header::SetResponseHeaderLayer};
-use axum::http::{header::CACHE_CONTROL, HeaderValue};
+use axum::http::{header::CACHE_CONTROL, HeaderName, HeaderValue};
 use tracing_subscriber::EnvFilter;
 
 
@@ -24,15 +31,27 @@ async fn main() {
     tracing::info!("Starting Axum server...");
 
     let db_pool = init_pool().await.expect("Failed to init DB pool");
+    jwt::init_keys().expect("Failed to load JWT keys");
+
+    let mailer = mail::mailer_from_env().expect("Failed to configure mailer");
+    mail::outbox::spawn_worker(db_pool.clone(), mailer);
+    purge::spawn_purge_worker(db_pool.clone());
+    ldap::spawn_sync_worker(db_pool.clone());
+    history::spawn_scheduler(db_pool.clone());
+
+    let storage = storage::storage_from_env().expect("Failed to configure document storage");
 
     use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, ACCEPT};
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE, ACCEPT]);
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE, ACCEPT, HeaderName::from_static(REQUEST_ID_HEADER)])
+        .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)]);
 
     let app = build_routes()
         .layer(middleware::add_extensions(db_pool))
+        .layer(axum::Extension(storage))
+        .layer(axum::middleware::from_fn(request_id))
         .layer(cors)
         .layer(SetResponseHeaderLayer::overriding(
             CACHE_CONTROL,
@@ -52,5 +71,7 @@ async fn main() {
     println!("Listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
\ No newline at end of file
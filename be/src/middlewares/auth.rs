@@ -1,13 +1,91 @@
 use axum::{
     body::Body,
+    extract::OriginalUri,
     http::{self, Request, Response, StatusCode},
     middleware::Next,
     response::IntoResponse,
 };
 
-use crate::{api::auth::jwt::validate_token, errors::AuthError};
+use crate::{
+    api::auth::{
+        api_key::{API_KEY_PREFIX, ApiKeyAuth, authenticate_key},
+        email::email_verification_required,
+        impersonation,
+        jwt::{is_access_token, validate_token},
+    },
+    errors::AuthError,
+    extractors::ClientInfo,
+    models::impersonation::Impersonation,
+};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+// API keys come in `X-API-Key` or as a bearer token with the key prefix
+fn api_key(req: &Request<Body>) -> Option<String> {
+    let headers = req.headers();
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(key.trim().to_string());
+    }
+    headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split_whitespace().nth(1))
+        .filter(|t| t.starts_with(API_KEY_PREFIX))
+        .map(str::to_string)
+}
+
+async fn authenticate_api_key(req: Request<Body>, next: Next, key: String) -> Response<Body> {
+    let (parts, body) = req.into_parts();
+    let client = ClientInfo::from_parts(&parts);
+    let mut req = Request::from_parts(parts, body);
+
+    let db = match req.extensions().get::<crate::db::Db>() {
+        Some(db) => db,
+        None => {
+            return AuthError {
+                message: "Database connection missing".to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+            .into_response();
+        }
+    };
 
+    let (user, api_key) = match authenticate_key(db, &key, client.ip_address.as_deref()).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return AuthError {
+                message: "Invalid API key".to_string(),
+                status_code: StatusCode::UNAUTHORIZED,
+            }
+            .into_response();
+        }
+        Err(e) => {
+            eprintln!("Error checking API key: {}", e);
+            return AuthError {
+                message: "Unable to check API key".to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+            .into_response();
+        }
+    };
+
+    req.extensions_mut().insert(user);
+    req.extensions_mut().insert(ApiKeyAuth {
+        key_id: api_key.id,
+        scopes: api_key.scopes,
+    });
+    next.run(req).await
+}
+
+/// Accepts either a JWT access token or a service account API key. JWTs put
+/// the `User` and `Claims` in the request extensions, API keys the `User`
+/// and `ApiKeyAuth`. Impersonation tokens also add the running
+/// `Impersonation` and are audited.
 pub async fn authenticate(mut req: Request<Body>, next: Next) -> Response<Body> {
+    if let Some(key) = api_key(&req) {
+        return authenticate_api_key(req, next, key).await;
+    }
+
     let auth_header = req.headers_mut().get(http::header::AUTHORIZATION);
     let auth_header = match auth_header {
         Some(header) => match header.to_str() {
@@ -36,7 +114,8 @@ pub async fn authenticate(mut req: Request<Body>, next: Next) -> Response<Body>
         validate_token(t)
     }
 
-    let token_data = match token.and_then(fun_name) {
+    // Scoped tokens (refresh, MFA pending) only work on their own endpoint
+    let token_data = match token.and_then(fun_name).filter(is_access_token) {
         Some(data) => data,
         None => {
             return AuthError {
@@ -69,6 +148,100 @@ pub async fn authenticate(mut req: Request<Body>, next: Next) -> Response<Body>
         }
     };
 
+    // Access tokens stay valid for their short lifetime unless the session
+    // they were issued for has been logged out or revoked. Impersonation
+    // tokens live in the admin's session.
+    if let Some(session_id) = token_data.sid {
+        let session_user = token_data.act.as_ref().map_or(current_user.id, |a| a.sub);
+        let active = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM user_sessions WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL)",
+        )
+        .bind(session_id)
+        .bind(session_user)
+        .fetch_one(db)
+        .await
+        .unwrap_or(false);
+
+        if !active {
+            return AuthError {
+                message: "Session has been revoked".to_string(),
+                status_code: StatusCode::UNAUTHORIZED,
+            }
+            .into_response();
+        }
+    }
+
+    if current_user.deactivated_at.is_some() {
+        return AuthError {
+            message: "This account has been deactivated".to_string(),
+            status_code: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+
+    if email_verification_required() && current_user.email_verified_at.is_none() {
+        return AuthError {
+            message: "Email address has not been verified".to_string(),
+            status_code: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+
+    let impersonation = if token_data.act.is_some() {
+        match impersonation::find_active(db, &token_data).await {
+            Ok(Some(impersonation)) => Some(impersonation),
+            Ok(None) => {
+                return AuthError {
+                    message: "Impersonation has ended".to_string(),
+                    status_code: StatusCode::UNAUTHORIZED,
+                }
+                .into_response();
+            }
+            Err(e) => {
+                eprintln!("Error checking impersonation: {}", e);
+                return AuthError {
+                    message: "Unable to check impersonation".to_string(),
+                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                }
+                .into_response();
+            }
+        }
+    } else {
+        None
+    };
+
+    let Some(impersonation) = impersonation else {
+        req.extensions_mut().insert(current_user);
+        req.extensions_mut().insert(token_data);
+        return next.run(req).await;
+    };
+
+    // Routes that authenticate again (e.g. /navigation/user) were already
+    // recorded by the outer pass
+    if req.extensions().get::<Impersonation>().is_some() {
+        req.extensions_mut().insert(current_user);
+        req.extensions_mut().insert(token_data);
+        return next.run(req).await;
+    }
+
+    // Every request made while impersonating is recorded with its outcome
+    let db = db.clone();
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| req.uri().to_string(), |uri| uri.0.to_string());
+    let impersonation_id = impersonation.id;
+
     req.extensions_mut().insert(current_user);
-    next.run(req).await
+    req.extensions_mut().insert(token_data);
+    req.extensions_mut().insert(impersonation);
+    let response = next.run(req).await;
+
+    if let Err(e) =
+        impersonation::record_request(&db, impersonation_id, &method, &path, response.status()).await
+    {
+        eprintln!("Error recording impersonated request: {}", e);
+    }
+    response
 }
@@ -0,0 +1,358 @@
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Method, Request, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+
+use crate::{
+    api::{
+        auth::{
+            api_key::{self, ApiKeyAuth},
+            impersonation, mfa, password_policy,
+        },
+        navigation::service::{get_effective_permissions, DataScope, NavigationWithPermissions},
+    },
+    db::Db,
+    errors::AuthError,
+    models::{impersonation::Impersonation, user::User},
+};
+use uuid::Uuid;
+
+/// A group of routes: the navigation item (by path) whose `role_permissions`
+/// govern it, and the name API key scopes use for it.
+#[derive(Debug, Clone, Copy)]
+pub struct Resource {
+    pub path: &'static str,
+    pub scope: &'static str,
+}
+
+impl Resource {
+    const fn new(path: &'static str, scope: &'static str) -> Self {
+        Resource { path, scope }
+    }
+
+    pub const EMPLOYEES: Resource = Resource::new("/admin/hr/employee", "employees");
+    pub const INTERNS: Resource = Resource::new("/admin/hr/intern", "interns");
+    pub const LEAVE: Resource = Resource::new("/admin/hr/leave", "leave");
+    pub const ATTENDANCE: Resource = Resource::new("/admin/hr/attendance", "attendance");
+    pub const DEPARTMENTS: Resource = Resource::new("/admin/settings/department", "departments");
+    pub const POSITIONS: Resource = Resource::new("/admin/settings/position", "positions");
+    pub const NAVIGATION: Resource = Resource::new("/admin/settings/navigation", "navigation");
+    pub const PERMISSIONS: Resource = Resource::new("/admin/settings/permissions", "permissions");
+    pub const ROLES: Resource = Resource::new("/admin/settings/role", "roles");
+    pub const PERSONS: Resource = Resource::new("/admin/settings/contact", "persons");
+    pub const USERS: Resource = Resource::new("/admin/settings/user", "users");
+
+    pub const ALL: [Resource; 11] = [
+        Resource::EMPLOYEES,
+        Resource::INTERNS,
+        Resource::LEAVE,
+        Resource::ATTENDANCE,
+        Resource::DEPARTMENTS,
+        Resource::POSITIONS,
+        Resource::NAVIGATION,
+        Resource::PERMISSIONS,
+        Resource::ROLES,
+        Resource::PERSONS,
+        Resource::USERS,
+    ];
+}
+
+/// The records of the current resource the user may see, put in the request
+/// extensions by `authorize`. List queries filter on it with `condition`.
+#[derive(Debug, Clone, Copy)]
+pub struct RowScope {
+    pub user_id: Uuid,
+    pub scope: DataScope,
+}
+
+impl RowScope {
+    /// SQL condition limiting `column`, an `employees.id`, to the scope with
+    /// the user id bound as `$param_index`. `None` when nothing is filtered.
+    pub fn condition(&self, column: &str, param_index: i32) -> Option<String> {
+        (self.scope != DataScope::All).then(|| {
+            format!(
+                "{} IN (SELECT scoped_employee_ids(${}, '{}'))",
+                column,
+                param_index,
+                self.scope.as_str()
+            )
+        })
+    }
+
+    /// Whether the employee is within the scope, for single-record lookups.
+    pub async fn includes(&self, db: &Db, employee_id: Uuid) -> Result<bool> {
+        if self.scope == DataScope::All {
+            return Ok(true);
+        }
+
+        let included = sqlx::query_scalar::<_, bool>(
+            "SELECT $2 IN (SELECT scoped_employee_ids($1, $3))",
+        )
+        .bind(self.user_id)
+        .bind(employee_id)
+        .bind(self.scope.as_str())
+        .fetch_one(db)
+        .await?;
+
+        Ok(included)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Read,
+    Update,
+    Delete,
+}
+
+impl Action {
+    pub fn from_method(method: &Method) -> Self {
+        match *method {
+            Method::POST => Action::Create,
+            Method::PUT | Method::PATCH => Action::Update,
+            Method::DELETE => Action::Delete,
+            _ => Action::Read,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Read => "read",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        }
+    }
+
+    /// Whether the user's effective permission on the resource, if they have
+    /// one, grants this action.
+    pub fn granted_by(&self, permission: Option<&NavigationWithPermissions>) -> bool {
+        permission.is_some_and(|p| {
+            match self {
+                Action::Create => p.can_create,
+                Action::Read => p.can_read,
+                Action::Update => p.can_update,
+                Action::Delete => p.can_delete,
+            }
+            .unwrap_or(false)
+        })
+    }
+}
+
+/// Rejects the request unless the authenticated user's effective permission on
+/// `resource` grants the verb implied by the HTTP method, or if the MFA policy
+/// requires the user to enroll first or their password has expired. Requests
+/// made with an API key are checked against the key's scopes instead, and an
+/// impersonating admin can't change users or permissions. Adds the user's
+/// `RowScope` on the resource to the request extensions. Must run after
+/// `authenticate`, which puts the `User` in the request extensions.
+pub async fn authorize(
+    State(resource): State<Resource>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let user = match req.extensions().get::<User>() {
+        Some(user) => user.clone(),
+        None => {
+            return AuthError {
+                message: "You are not an authorized user".to_string(),
+                status_code: StatusCode::UNAUTHORIZED,
+            }
+            .into_response();
+        }
+    };
+
+    let db = match req.extensions().get::<Db>() {
+        Some(db) => db.clone(),
+        None => {
+            return AuthError {
+                message: "Database connection missing".to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+            .into_response();
+        }
+    };
+
+    let action = Action::from_method(req.method());
+
+    // API keys are limited to their scopes; the service account's roles,
+    // MFA and password policy don't apply to them
+    if let Some(key) = req.extensions().get::<ApiKeyAuth>() {
+        if !api_key::allows(&key.scopes, resource, action) {
+            return AuthError {
+                message: format!(
+                    "API key does not have the {}:{} scope",
+                    resource.scope,
+                    if action == Action::Read { "read" } else { "write" }
+                ),
+                status_code: StatusCode::FORBIDDEN,
+            }
+            .into_response();
+        }
+        req.extensions_mut().insert(RowScope {
+            user_id: user.id,
+            scope: DataScope::All,
+        });
+        return next.run(req).await;
+    }
+
+    if req.extensions().get::<Impersonation>().is_some() && impersonation::blocks(resource, action) {
+        return impersonation::forbidden();
+    }
+
+    // Users the MFA policy applies to must enroll before using protected
+    // resources; the /auth/mfa endpoints stay reachable for that.
+    match mfa::enrollment_required(&db, &user).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return AuthError {
+                message: "Two-factor authentication must be enabled for your account".to_string(),
+                status_code: StatusCode::FORBIDDEN,
+            }
+            .into_response();
+        }
+        Err(e) => {
+            eprintln!("Error checking MFA policy: {}", e);
+            return AuthError {
+                message: "Unable to resolve permissions".to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+            .into_response();
+        }
+    }
+
+    if password_policy::must_change(&user) {
+        return AuthError {
+            message: "Your password has expired and must be changed".to_string(),
+            status_code: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+
+    if user.is_admin {
+        req.extensions_mut().insert(RowScope {
+            user_id: user.id,
+            scope: DataScope::All,
+        });
+        return next.run(req).await;
+    }
+
+    let permissions = match get_effective_permissions(&db, user.id, Some(resource.path)).await {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            eprintln!("Error resolving permissions: {}", e);
+            return AuthError {
+                message: "Unable to resolve permissions".to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+            .into_response();
+        }
+    };
+
+    let permission = permissions.first();
+    if !action.granted_by(permission) {
+        return AuthError {
+            message: format!(
+                "You do not have permission to {} {}",
+                action.as_str(),
+                resource.path
+            ),
+            status_code: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+
+    req.extensions_mut().insert(RowScope {
+        user_id: user.id,
+        scope: permission.map_or(DataScope::Own, |p| p.data_scope),
+    });
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(scope: DataScope) -> RowScope {
+        RowScope { user_id: Uuid::nil(), scope }
+    }
+
+    #[test]
+    fn all_scope_adds_no_condition() {
+        assert_eq!(scope(DataScope::All).condition("e.id", 1), None);
+    }
+
+    #[test]
+    fn narrower_scopes_filter_through_scoped_employee_ids() {
+        assert_eq!(
+            scope(DataScope::Own).condition("e.id", 1).as_deref(),
+            Some("e.id IN (SELECT scoped_employee_ids($1, 'own'))")
+        );
+        assert_eq!(
+            scope(DataScope::Reports).condition("lr.employee_id", 3).as_deref(),
+            Some("lr.employee_id IN (SELECT scoped_employee_ids($3, 'reports'))")
+        );
+        assert_eq!(
+            scope(DataScope::Department).condition("a.employee_id", 2).as_deref(),
+            Some("a.employee_id IN (SELECT scoped_employee_ids($2, 'department'))")
+        );
+    }
+
+    #[test]
+    fn actions_follow_the_http_method() {
+        assert_eq!(Action::from_method(&Method::GET), Action::Read);
+        assert_eq!(Action::from_method(&Method::HEAD), Action::Read);
+        assert_eq!(Action::from_method(&Method::POST), Action::Create);
+        assert_eq!(Action::from_method(&Method::PUT), Action::Update);
+        assert_eq!(Action::from_method(&Method::PATCH), Action::Update);
+        assert_eq!(Action::from_method(&Method::DELETE), Action::Delete);
+    }
+
+    fn permission(create: bool, read: bool, update: bool, delete: bool) -> NavigationWithPermissions {
+        NavigationWithPermissions {
+            id: Uuid::nil(),
+            name: "Employee".to_string(),
+            path: Resource::EMPLOYEES.path.to_string(),
+            icon: None,
+            parent_id: None,
+            display_order: 0,
+            can_create: Some(create),
+            can_read: Some(read),
+            can_update: Some(update),
+            can_delete: Some(delete),
+            data_scope: DataScope::All,
+        }
+    }
+
+    const ACTIONS: [Action; 4] = [Action::Create, Action::Read, Action::Update, Action::Delete];
+
+    #[test]
+    fn each_action_needs_its_own_flag() {
+        let grants = [
+            permission(true, false, false, false),
+            permission(false, true, false, false),
+            permission(false, false, true, false),
+            permission(false, false, false, true),
+        ];
+        for (i, grant) in grants.iter().enumerate() {
+            for (j, action) in ACTIONS.iter().enumerate() {
+                assert_eq!(action.granted_by(Some(grant)), i == j, "{} with grant {}", action.as_str(), i);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_grants_are_rejected() {
+        let mut unset = permission(false, false, false, false);
+        unset.can_read = None;
+        for action in ACTIONS {
+            assert!(!action.granted_by(None), "{}", action.as_str());
+            assert!(!action.granted_by(Some(&unset)), "{}", action.as_str());
+        }
+    }
+}
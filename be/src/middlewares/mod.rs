@@ -1 +1,3 @@
 pub mod auth;
+pub mod authorize;
+pub mod request_id;
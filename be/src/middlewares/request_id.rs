@@ -0,0 +1,42 @@
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request, Response},
+    middleware::Next,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The id of the request being handled, from `X-Request-ID` when the proxy
+/// or client sent a usable one, otherwise generated.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+fn usable(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+}
+
+/// Tags every request with a `RequestId` extension and echoes it back in the
+/// `X-Request-ID` response header so entries in the audit log can be matched
+/// to client and proxy logs.
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response<Body> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| usable(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ChecklistTemplate {
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    pub department_id: Option<Uuid>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ChecklistTemplateTask {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_role: String,
+    pub due_offset_days: i32,
+    pub display_order: i32,
+}
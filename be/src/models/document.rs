@@ -0,0 +1,48 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct DocumentCategory {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub requires_expiry: bool,
+    pub expiry_warning_days: i32,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Document {
+    pub id: Uuid,
+    pub employee_id: Option<Uuid>,
+    pub intern_id: Option<Uuid>,
+    pub person_id: Option<Uuid>,
+    pub category_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub current_version: i32,
+    pub expires_on: Option<NaiveDate>,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct DocumentVersion {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    pub version: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub storage_key: String,
+    pub expires_on: Option<NaiveDate>,
+    pub note: Option<String>,
+    pub uploaded_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
@@ -18,6 +18,8 @@ pub struct Employee {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub face_descriptor: Option<String>,
+    pub termination_date: Option<NaiveDate>,
+    pub termination_reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -37,6 +39,8 @@ pub struct EmployeeWithPerson {
     pub salary: Option<sqlx::types::BigDecimal>,
     pub manager_id: Option<Uuid>,
     pub status: String,
+    pub termination_date: Option<NaiveDate>,
+    pub termination_reason: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
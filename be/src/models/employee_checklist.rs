@@ -0,0 +1,31 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EmployeeChecklist {
+    pub id: Uuid,
+    pub employee_id: Uuid,
+    pub template_id: Option<Uuid>,
+    pub kind: String,
+    pub name: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EmployeeChecklistTask {
+    pub id: Uuid,
+    pub checklist_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_role: String,
+    pub assignee_employee_id: Option<Uuid>,
+    pub due_date: NaiveDate,
+    pub display_order: i32,
+    pub notes: Option<String>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub completed_by: Option<Uuid>,
+}
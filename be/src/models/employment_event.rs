@@ -0,0 +1,23 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EmploymentEvent {
+    pub id: Uuid,
+    pub employee_id: Uuid,
+    pub event_type: String,
+    pub effective_date: NaiveDate,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub manager_id: Option<Uuid>,
+    pub salary: Option<sqlx::types::BigDecimal>,
+    pub status: Option<String>,
+    // Columns this event sets to NULL, since NULL fields mean "unchanged"
+    pub cleared_fields: Vec<String>,
+    pub reason: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub applied_at: Option<NaiveDateTime>,
+}
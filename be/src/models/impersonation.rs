@@ -0,0 +1,30 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Impersonation {
+    pub id: Uuid,
+    pub admin_id: Uuid,
+    pub user_id: Uuid,
+    pub admin_session_id: Uuid,
+    pub reason: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationRequest {
+    pub id: Uuid,
+    pub impersonation_id: Uuid,
+    pub method: String,
+    pub path: String,
+    pub status: i16,
+    pub created_at: NaiveDateTime,
+}
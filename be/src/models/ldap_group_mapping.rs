@@ -0,0 +1,15 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LdapGroupMapping {
+    pub id: Uuid,
+    pub group_dn: String,
+    pub department_id: Option<Uuid>,
+    pub position_id: Option<Uuid>,
+    pub priority: i32,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,17 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LdapSyncRun {
+    pub id: Uuid,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub created_count: i32,
+    pub updated_count: i32,
+    pub removed_count: i32,
+    pub error_count: i32,
+    pub error: Option<String>,
+}
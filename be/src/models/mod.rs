@@ -1,13 +1,30 @@
+pub mod api_key;
 pub mod attendance;
+pub mod audit_log;
+pub mod checklist_template;
 pub mod department;
+pub mod document;
+pub mod email_outbox;
+pub mod email_verification_token;
 pub mod employee;
+pub mod employee_checklist;
+pub mod employment_event;
+pub mod impersonation;
 pub mod intern;
+pub mod ldap_group_mapping;
+pub mod ldap_sync_run;
 pub mod leave;
+pub mod login_attempt;
 pub mod navigation_item;
+pub mod navigation_item_translation;
 pub mod person;
 pub mod person_contact;
 pub mod position;
 pub mod refresh_token;
+pub mod role;
 pub mod role_permission;
 pub mod service_response;
 pub mod user;
+pub mod user_mfa;
+pub mod user_permission;
+pub mod user_session;
@@ -10,4 +10,6 @@ pub struct RefreshToken {
     pub token: String,
     pub expires_at: NaiveDateTime,
     pub created_at: NaiveDateTime,
+    pub session_id: Uuid,
+    pub used_at: Option<NaiveDateTime>,
 }
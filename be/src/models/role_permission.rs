@@ -8,10 +8,12 @@ pub struct RolePermission {
     pub id: Uuid,
     pub department_id: Option<Uuid>,
     pub position_id: Option<Uuid>,
+    pub role_id: Option<Uuid>,
     pub navigation_item_id: Uuid,
     pub can_create: bool,
     pub can_read: bool,
     pub can_update: bool,
     pub can_delete: bool,
+    pub data_scope: String,
     pub created_at: NaiveDateTime,
 }
@@ -1,9 +1,23 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 
-#[derive(Serialize)]
+use crate::i18n::{self, Locale};
+
+/// `code` is a stable message key; `message` is the English text until the
+/// response is `localized`. `params` fill the placeholders of translations.
+#[derive(Debug, Clone, Serialize)]
 pub struct ErrorItem {
     pub code: String,
     pub message: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+
+impl ErrorItem {
+    pub fn with_param(mut self, name: &str, value: impl ToString) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -31,6 +45,17 @@ where
             count: None,
         }
     }
+
+    /// Translates each error's message by its code, keeping the English text
+    /// when `locale` has no translation for it.
+    pub fn localized(mut self, locale: Locale) -> Self {
+        for error in &mut self.errors {
+            if let Some(message) = i18n::translate(locale, &error.code, &error.params) {
+                error.message = message;
+            }
+        }
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -68,6 +93,7 @@ where
         self.errors.push(ErrorItem {
             code: code.into(),
             message: message.into(),
+            params: BTreeMap::new(),
         });
         self
     }
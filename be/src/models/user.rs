@@ -10,9 +10,23 @@ pub struct User {
     pub user_name: String,
     #[serde(skip)]
     pub password_hash: String,
-    pub email: String,
-    pub phone: String,
-    pub person_id: Uuid,
+    // Service accounts have no email, phone or person
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub person_id: Option<Uuid>,
     pub is_admin: bool,
     pub created_at: NaiveDateTime,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub password_changed_at: NaiveDateTime,
+    pub is_service_account: bool,
+    pub description: Option<String>,
+    // Set for users managed by the LDAP directory
+    pub ldap_dn: Option<String>,
+    pub ldap_synced_at: Option<NaiveDateTime>,
+    // Invited users are pending, without a password, until they accept
+    pub invited_by: Option<Uuid>,
+    pub invited_at: Option<NaiveDateTime>,
+    pub invitation_accepted_at: Option<NaiveDateTime>,
+    // Deactivated users can't sign in, e.g. once they've left the company
+    pub deactivated_at: Option<NaiveDateTime>,
 }
@@ -0,0 +1,14 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct UserMfa {
+    pub user_id: Uuid,
+    #[serde(skip)]
+    pub secret: String,
+    pub enabled_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// A grant or deny on one navigation item for a single user, applied on top
+/// of the permissions from their department, position and roles.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct UserPermission {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub navigation_item_id: Uuid,
+    // "grant" or "deny"
+    pub effect: String,
+    pub can_create: bool,
+    pub can_read: bool,
+    pub can_update: bool,
+    pub can_delete: bool,
+    pub data_scope: String,
+    pub created_at: NaiveDateTime,
+}
@@ -1,32 +1,77 @@
-use axum::{Router, routing::get};
+use axum::{Router, middleware::from_fn_with_state, routing::get};
 
-use crate::api::{
-    attendance::routes::attendance_routes,
-    auth::routes::auth_routes,
-    department::routes::department_routes,
-    employee::routes::employee_routes,
-    home::handlers::health_check_handler,
-    intern::routes::intern_routes,
-    leave::routes::leave_routes,
-    navigation::routes::navigation_routes,
-    permissions::routes::permissions_routes,
-    position::routes::position_routes,
-    person::routes::person_routes,
-    user::routes::user_routes,
+use crate::{
+    api::{
+        attendance::routes::attendance_routes,
+        audit::routes::audit_routes,
+        auth::routes::auth_routes,
+        department::routes::department_routes,
+        employee::routes::employee_routes,
+        home::handlers::health_check_handler,
+        intern::routes::intern_routes,
+        leave::routes::leave_routes,
+        navigation::routes::{navigation_routes, user_navigation_routes},
+        permissions::routes::permissions_routes,
+        position::routes::position_routes,
+        person::routes::person_routes,
+        role::routes::role_routes,
+        user::routes::user_routes,
+    },
+    middlewares::authorize::{Resource, authorize},
 };
 
 pub fn build_routes() -> Router {
+    // Each module is guarded by the navigation item whose role_permissions
+    // decide what the caller may do there; `authenticate` runs first.
     let protected_routes = Router::new()
-        .nest("/employees", employee_routes())
-        .nest("/interns", intern_routes())
-        .nest("/leave", leave_routes())
-        .nest("/attendance", attendance_routes())
-        .nest("/departments", department_routes())
-        .nest("/positions", position_routes())
-        .nest("/navigation", navigation_routes())
-        .nest("/permissions", permissions_routes())
-        .nest("/persons", person_routes())
-        .nest("/users", user_routes())
+        .nest(
+            "/employees",
+            employee_routes().route_layer(from_fn_with_state(Resource::EMPLOYEES, authorize)),
+        )
+        .nest(
+            "/interns",
+            intern_routes().route_layer(from_fn_with_state(Resource::INTERNS, authorize)),
+        )
+        .nest(
+            "/leave",
+            leave_routes().route_layer(from_fn_with_state(Resource::LEAVE, authorize)),
+        )
+        .nest(
+            "/attendance",
+            attendance_routes().route_layer(from_fn_with_state(Resource::ATTENDANCE, authorize)),
+        )
+        .nest(
+            "/departments",
+            department_routes().route_layer(from_fn_with_state(Resource::DEPARTMENTS, authorize)),
+        )
+        .nest(
+            "/positions",
+            position_routes().route_layer(from_fn_with_state(Resource::POSITIONS, authorize)),
+        )
+        .nest(
+            "/navigation",
+            navigation_routes()
+                .route_layer(from_fn_with_state(Resource::NAVIGATION, authorize))
+                .merge(user_navigation_routes()),
+        )
+        .nest(
+            "/permissions",
+            permissions_routes().route_layer(from_fn_with_state(Resource::PERMISSIONS, authorize)),
+        )
+        .nest(
+            "/roles",
+            role_routes().route_layer(from_fn_with_state(Resource::ROLES, authorize)),
+        )
+        .nest(
+            "/persons",
+            person_routes().route_layer(from_fn_with_state(Resource::PERSONS, authorize)),
+        )
+        .nest(
+            "/users",
+            user_routes().route_layer(from_fn_with_state(Resource::USERS, authorize)),
+        )
+        // Admins only, checked in the handler
+        .nest("/audit", audit_routes())
         .route_layer(axum::middleware::from_fn(
             crate::middlewares::auth::authenticate,
         ));
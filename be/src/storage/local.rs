@@ -0,0 +1,62 @@
+use std::{env, io::ErrorKind, path::PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::storage::{Storage, validate_key};
+
+/// Keeps files under a directory on the local filesystem.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create storage directory {}", root.display()))?;
+        Ok(LocalStorage { root })
+    }
+
+    /// Uses `STORAGE_DIR`, defaulting to `./storage`.
+    pub fn from_env() -> Result<Self> {
+        let root = env::var("STORAGE_DIR").unwrap_or_else(|_| "storage".to_string());
+        LocalStorage::new(root)
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8], _content_type: &str) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write next to the target and rename so readers never see a partial file
+        let tmp_path = path.with_file_name(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(key)?;
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", key))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+pub mod local;
+pub mod s3;
+
+use std::{env, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+
+use crate::storage::{local::LocalStorage, s3::S3Storage};
+
+/// Where uploaded files are kept. Keys are `/`-separated paths chosen by the
+/// caller, such as `documents/<document id>/<version id>`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, data: &[u8], content_type: &str) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+    /// Succeeds when the key doesn't exist.
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Builds the storage selected by `STORAGE_BACKEND` (`local` or `s3`).
+pub fn storage_from_env() -> Result<Arc<dyn Storage>> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+    match backend.as_str() {
+        "local" => Ok(Arc::new(LocalStorage::from_env()?)),
+        "s3" => Ok(Arc::new(S3Storage::from_env()?)),
+        other => Err(anyhow!("Unknown STORAGE_BACKEND: {}", other)),
+    }
+}
+
+/// Rejects keys that could escape the storage root.
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && key
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..");
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid storage key: {}", key))
+    }
+}
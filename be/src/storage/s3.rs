@@ -0,0 +1,165 @@
+use std::{env, time::Duration};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::storage::{Storage, validate_key};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Keeps files in a bucket of Amazon S3 or a compatible service such as
+/// MinIO, addressed path-style and signed with AWS Signature Version 4.
+pub struct S3Storage {
+    client: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Storage {
+    /// Reads `S3_ENDPOINT`, `S3_BUCKET`, `S3_REGION` (`us-east-1` by
+    /// default), `S3_ACCESS_KEY_ID` and `S3_SECRET_ACCESS_KEY`.
+    pub fn from_env() -> Result<Self> {
+        let endpoint = env::var("S3_ENDPOINT").context("S3_ENDPOINT not set")?;
+        let endpoint = Url::parse(endpoint.trim_end_matches('/'))
+            .map_err(|e| anyhow!("Invalid S3_ENDPOINT: {}", e))?;
+        if endpoint.host_str().is_none() {
+            return Err(anyhow!("S3_ENDPOINT has no host"));
+        }
+
+        Ok(S3Storage {
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+            endpoint,
+            bucket: env::var("S3_BUCKET").context("S3_BUCKET not set")?,
+            region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key_id: env::var("S3_ACCESS_KEY_ID").context("S3_ACCESS_KEY_ID not set")?,
+            secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+                .context("S3_SECRET_ACCESS_KEY not set")?,
+        })
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        validate_key(key)?;
+
+        let path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            uri_encode(&self.bucket),
+            key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex(&Sha256::digest(&body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let credential_scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            credential_scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut signing_key = hmac_sha256(
+            format!("AWS4{}", self.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes());
+        }
+        let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, credential_scope, signed_headers, signature
+        );
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+
+        Ok(request.body(body).send().await?)
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, data: &[u8], content_type: &str) -> Result<()> {
+        let response = self
+            .send(Method::PUT, key, data.to_vec(), Some(content_type))
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("S3 upload of {} failed: {}", key, response.status()));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self.send(Method::GET, key, Vec::new(), None).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("S3 download of {} failed: {}", key, response.status()));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self.send(Method::DELETE, key, Vec::new(), None).await?;
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::NOT_FOUND {
+            return Err(anyhow!("S3 delete of {} failed: {}", key, status));
+        }
+        Ok(())
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Percent-encodes everything but the unreserved characters, as SigV4 expects.
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}